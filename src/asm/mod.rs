@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+
+use crate::isa::{self, Instruction};
+use crate::loader::{Image, Symbol};
+use crate::memory;
+
+// IEEE-754 binary16/binary32 runtime, see the header of the file for the calling convention
+pub const SOFTFLOAT: &str = include_str!("softfloat.s");
+
+// Register `call` loads the target address into
+pub const CALL_REGISTER: u8 = 14;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    UnknownMnemonic { line: usize, mnemonic: String },
+    BadOperand { line: usize, operand: String },
+    OperandCount { line: usize, expected: usize, found: usize },
+    UndefinedSymbol { line: usize, name: String },
+    DuplicateSymbol { line: usize, name: String },
+    OutOfRange { line: usize, value: i32 }, // Immediate, register or branch distance does not fit its field
+    OutOfMemory,                             // Code runs past the end of the address space
+    Overlap { line: usize, addr: u32 },      // Output at this far address was already placed
+}
+
+// One instruction or data directive, placed by the first pass
+struct Statement<'a> {
+    line: usize,
+    bank: u8, // Code bank the statement is placed in, set by a far .org
+    addr: u16,
+    mnemonic: String,
+    operands: Vec<&'a str>,
+    size: u16, // Bytes, fixed in the first pass so labels stay put
+}
+
+enum Operand {
+    Register(u16),
+    Value(i32),
+    Indexed { base: u8, offset: i32 }, // [rB+offset]
+    PostIncrement(u8),                 // [rB]+
+}
+
+// Assemble `source` into an image. Syntax, one statement per line:
+//   label:  mnemonic operand, operand   ; comment
+// Registers are r0-r1023 (sp is r15), numbers decimal, 0x hex or 0b binary, and an
+// expression is a sum of numbers and symbols. Directives: .org addr, .equ name, value and
+// .word value, ... (16-bit, little endian). A far .org (bank << 16 | addr, addr in a bank
+// window) places what follows in that memory bank. Branch and jump targets are addresses,
+// normally labels; the relative forms get the word offset from the statement. Besides the
+// instructions there are the pseudo-instructions li/la (isa::expand_load_immediate/
+// expand_load_address), call target (la r14, target; fcall bank, r14, with the bank the
+// call is placed in) and ret (fret). The entry point is _start if it is defined.
+pub fn assemble(source: &str) -> Result<Image, AsmError> {
+    let mut symbols: HashMap<String, i32> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut statements = Vec::new();
+
+    let mut bank: u8 = 0;
+    let mut addr: u32 = 0;
+    let mut end: u32 = 0x10000; // Banked output has to stay inside its window
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let mut text = text.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = label_end(text) {
+            define(&mut symbols, &mut order, &text[..colon], addr as i32, line)?;
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(space) => (&text[..space], text[space..].trim()),
+            None => (text, ""),
+        };
+        let mnemonic = mnemonic.to_ascii_lowercase();
+        let operands: Vec<&str> = if rest.is_empty() { Vec::new() } else { rest.split(',').map(str::trim).collect() };
+
+        match mnemonic.as_str() {
+            ".org" => {
+                expect_operands(&operands, 1, line)?;
+                let far = evaluate(operands[0], &symbols, line)?;
+                let (far_bank, far_addr) = (far >> 16, far as u16);
+                if far < 0 || far_bank as usize >= memory::NUM_MEMORY_BANKS || far_bank != 0 && memory::window_of(far_addr).is_none() {
+                    return Err(AsmError::OutOfRange { line, value: far });
+                }
+                bank = far_bank as u8;
+                addr = far_addr as u32;
+                end = match memory::window_of(far_addr) {
+                    Some(window) if bank != 0 => window as u32 + memory::BANK_SIZE as u32,
+                    _ => 0x10000,
+                };
+                continue;
+            }
+            ".equ" => {
+                expect_operands(&operands, 2, line)?;
+                let value = evaluate(operands[1], &symbols, line)?;
+                define(&mut symbols, &mut order, operands[0], value, line)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let size = match mnemonic.as_str() {
+            ".word" => 2 * operands.len() as u16,
+            // A constant known now gets the shortest sequence, anything else the patchable pair
+            "li" => match operands.get(1).map(|value| evaluate(value, &symbols, line)) {
+                Some(Ok(value)) => 4 * isa::expand_load_immediate(0, value as u16).len() as u16,
+                _ => 8,
+            },
+            "la" => 8,
+            "call" => 12,
+            _ => 4,
+        };
+        if addr + size as u32 > end {
+            return Err(AsmError::OutOfMemory);
+        }
+        statements.push(Statement { line, bank, addr: addr as u16, mnemonic, operands, size });
+        addr += size as u32;
+    }
+    check_overlap(&statements)?;
+
+    let mut image = Image::new();
+    for statement in &statements {
+        let bytes = if statement.mnemonic == ".word" {
+            let mut bytes = Vec::new();
+            for operand in &statement.operands {
+                let value = evaluate(operand, &symbols, statement.line)?;
+                bytes.extend_from_slice(&(check_range(value, -0x8000, 0xFFFF, statement.line)? as u16).to_le_bytes());
+            }
+            bytes
+        } else {
+            let instructions = translate(statement, &symbols)?;
+            debug_assert_eq!(instructions.len() * 4, statement.size as usize);
+            instructions.iter().flat_map(|instruction| isa::encode(instruction).to_le_bytes()).collect()
+        };
+        // Statements were checked to fit their bank and window
+        image.add_data((statement.bank as u32) << 16 | statement.addr as u32, &bytes).map_err(|_| AsmError::OutOfMemory)?;
+    }
+
+    for name in order {
+        image.symbols.push(Symbol { name: name.clone(), addr: symbols[&name] as u16 });
+    }
+    image.entry = image.symbol("_start");
+    Ok(image)
+}
+
+// A backwards .org must not place output over earlier output
+fn check_overlap(statements: &[Statement]) -> Result<(), AsmError> {
+    let mut placed: Vec<&Statement> = statements.iter().filter(|statement| statement.size > 0).collect();
+    placed.sort_by_key(|statement| (statement.bank, statement.addr));
+    for pair in placed.windows(2) {
+        let (first, second) = (pair[0], pair[1]);
+        if first.bank == second.bank && first.addr as u32 + first.size as u32 > second.addr as u32 {
+            let line = first.line.max(second.line);
+            return Err(AsmError::Overlap { line, addr: (second.bank as u32) << 16 | second.addr as u32 });
+        }
+    }
+    Ok(())
+}
+
+// Position of the colon ending a leading label, if the statement starts with one
+fn label_end(text: &str) -> Option<usize> {
+    let colon = text.find(':')?;
+    let name = text[..colon].trim();
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')).then_some(colon)
+}
+
+fn define(symbols: &mut HashMap<String, i32>, order: &mut Vec<String>, name: &str, value: i32, line: usize) -> Result<(), AsmError> {
+    let name = name.trim();
+    if symbols.insert(name.to_string(), value).is_some() {
+        return Err(AsmError::DuplicateSymbol { line, name: name.to_string() });
+    }
+    order.push(name.to_string());
+    Ok(())
+}
+
+fn expect_operands(operands: &[&str], expected: usize, line: usize) -> Result<(), AsmError> {
+    if operands.len() != expected {
+        return Err(AsmError::OperandCount { line, expected, found: operands.len() });
+    }
+    Ok(())
+}
+
+fn check_range(value: i32, min: i32, max: i32, line: usize) -> Result<i32, AsmError> {
+    if value < min || value > max {
+        return Err(AsmError::OutOfRange { line, value });
+    }
+    Ok(value)
+}
+
+// Sum of terms: numbers and symbols, each with an optional sign
+fn evaluate(text: &str, symbols: &HashMap<String, i32>, line: usize) -> Result<i32, AsmError> {
+    let text = text.trim();
+    let bad = || AsmError::BadOperand { line, operand: text.to_string() };
+    let mut total: i32 = 0;
+    let mut rest = text;
+    let mut negative = false;
+    loop {
+        rest = rest.trim_start();
+        if let Some(stripped) = rest.strip_prefix('-') {
+            negative = !negative;
+            rest = stripped;
+            continue;
+        }
+        if let Some(stripped) = rest.strip_prefix('+') {
+            rest = stripped;
+            continue;
+        }
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+        if term.is_empty() {
+            return Err(bad());
+        }
+        let value = if term.starts_with(|c: char| c.is_ascii_digit()) {
+            parse_number(term).ok_or_else(bad)?
+        } else if term.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            *symbols.get(term).ok_or_else(|| AsmError::UndefinedSymbol { line, name: term.to_string() })?
+        } else {
+            return Err(bad());
+        };
+        total = if negative { total.wrapping_sub(value) } else { total.wrapping_add(value) };
+        negative = false;
+        rest = &rest[end..];
+        if rest.is_empty() {
+            return Ok(total);
+        }
+    }
+}
+
+fn parse_number(text: &str) -> Option<i32> {
+    let text = text.to_ascii_lowercase();
+    let value = if let Some(hex) = text.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = text.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else {
+        text.parse::<i64>().ok()?
+    };
+    i32::try_from(value).ok()
+}
+
+fn register_number(text: &str) -> Option<u16> {
+    let text = text.trim().to_ascii_lowercase();
+    if text == "sp" {
+        return Some(isa::STACK_POINTER as u16);
+    }
+    let number = text.strip_prefix('r')?.parse::<u16>().ok()?;
+    (number < 1024).then_some(number)
+}
+
+fn operand(text: &str, symbols: &HashMap<String, i32>, line: usize) -> Result<Operand, AsmError> {
+    let bad = || AsmError::BadOperand { line, operand: text.to_string() };
+    if let Some(reg) = register_number(text) {
+        return Ok(Operand::Register(reg));
+    }
+    let Some(inner) = text.strip_prefix('[') else {
+        return Ok(Operand::Value(evaluate(text, symbols, line)?));
+    };
+    let base8 = |reg: u16| u8::try_from(reg).map_err(|_| bad());
+    if let Some(base) = inner.strip_suffix("]+") {
+        return Ok(Operand::PostIncrement(base8(register_number(base).ok_or_else(bad)?)?));
+    }
+    let inner = inner.strip_suffix(']').ok_or_else(bad)?;
+    let split = inner.find(['+', '-']).unwrap_or(inner.len());
+    let base = base8(register_number(&inner[..split]).ok_or_else(bad)?)?;
+    let offset = if split == inner.len() { 0 } else { evaluate(&inner[split..], symbols, line)? };
+    Ok(Operand::Indexed { base, offset })
+}
+
+// Instructions for one statement
+fn translate(statement: &Statement, symbols: &HashMap<String, i32>) -> Result<Vec<Instruction>, AsmError> {
+    let line = statement.line;
+    let operands = statement
+        .operands
+        .iter()
+        .map(|text| operand(text, symbols, line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let count = |expected: usize| expect_operands(&statement.operands, expected, line);
+    let bad = |i: usize| AsmError::BadOperand { line, operand: statement.operands.get(i).unwrap_or(&"").to_string() };
+
+    // Operand accessors, checked against the width of the field they go into
+    let reg = |i: usize, bits: u32| match operands.get(i) {
+        Some(Operand::Register(reg)) if (*reg as u32) < 1 << bits => Ok(*reg),
+        Some(Operand::Register(reg)) => Err(AsmError::OutOfRange { line, value: *reg as i32 }),
+        _ => Err(bad(i)),
+    };
+    let r8 = |i: usize| reg(i, 8).map(|reg| reg as u8);
+    let r10 = |i: usize| reg(i, 10);
+    let value = |i: usize, min: i32, max: i32| match operands.get(i) {
+        Some(Operand::Value(value)) => check_range(*value, min, max, line),
+        _ => Err(bad(i)),
+    };
+    let simm10 = |i: usize| value(i, -512, 511).map(|value| value as i16);
+    let uimm10 = |i: usize| value(i, 0, 1023).map(|value| value as u16);
+    let is_register = |i: usize| matches!(operands.get(i), Some(Operand::Register(_)));
+    // Word offset from this statement to a target address, for the relative branches
+    let offset = |i: usize, bits: u32| -> Result<i16, AsmError> {
+        let target = value(i, 0, 0xFFFF)?;
+        let distance = target - statement.addr as i32;
+        let limit = 1 << (bits - 1);
+        if distance % 4 != 0 || distance / 4 < -limit || distance / 4 >= limit {
+            return Err(AsmError::OutOfRange { line, value: distance });
+        }
+        Ok((distance / 4) as i16)
+    };
+    let indexed = |i: usize| match operands.get(i) {
+        Some(Operand::Indexed { base, offset }) => Ok((*base, check_range(*offset, -512, 511, line)? as i16)),
+        _ => Err(bad(i)),
+    };
+    let post_increment = |i: usize| match operands.get(i) {
+        Some(Operand::PostIncrement(base)) => Ok(*base),
+        _ => Err(bad(i)),
+    };
+    let memory_form = |i: usize| operands.get(i).map(|operand| match operand {
+        Operand::Indexed { .. } => 1,
+        Operand::PostIncrement(_) => 2,
+        _ => 0,
+    });
+
+    use Instruction::*;
+    let mnemonic = statement.mnemonic.as_str();
+    let single = match mnemonic {
+        "nop" | "halt" | "rfe" | "ei" | "di" | "tlbflush" | "fret" | "ret" => {
+            count(0)?;
+            match mnemonic {
+                "nop" => Nop,
+                "halt" => Halt,
+                "rfe" => ReturnFromException,
+                "ei" => EnableInterrupts,
+                "di" => DisableInterrupts,
+                "tlbflush" => FlushTlb,
+                _ => FarReturn,
+            }
+        }
+
+        "li" | "la" => {
+            count(2)?;
+            let dst = r8(0)?;
+            let value = value(1, -0x8000, 0xFFFF)? as u16;
+            return Ok(match statement.size {
+                8 => isa::expand_load_address(dst, value),
+                _ => isa::expand_load_immediate(dst, value),
+            });
+        }
+        "call" => {
+            count(1)?;
+            let mut instructions = isa::expand_load_address(CALL_REGISTER, value(0, 0, 0xFFFF)? as u16);
+            instructions.push(FarCall { bank: statement.bank, target: CALL_REGISTER });
+            return Ok(instructions);
+        }
+        // Loads and stores: absolute address, [base+offset] or [base]+
+        "ld" | "st" | "ldb" | "ldbs" | "stb" => {
+            count(2)?;
+            let data = r8(0)?;
+            match (mnemonic, memory_form(1)) {
+                ("ld", Some(0)) => Load { dst: data, addr: uimm10(1)? },
+                ("st", Some(0)) => Store { src: data, addr: uimm10(1)? },
+                ("ld", Some(1)) => { let (base, offset) = indexed(1)?; LoadIndexed { dst: data, base, offset } }
+                ("st", Some(1)) => { let (base, offset) = indexed(1)?; StoreIndexed { src: data, base, offset } }
+                ("ldb", Some(1)) => { let (base, offset) = indexed(1)?; LoadByte { dst: data, base, offset } }
+                ("ldbs", Some(1)) => { let (base, offset) = indexed(1)?; LoadByteSigned { dst: data, base, offset } }
+                ("stb", Some(1)) => { let (base, offset) = indexed(1)?; StoreByte { src: data, base, offset } }
+                ("ld", Some(2)) => LoadPostInc { dst: data, base: post_increment(1)? },
+                ("st", Some(2)) => StorePostInc { src: data, base: post_increment(1)? },
+                ("ldb", Some(2)) => LoadBytePostInc { dst: data, base: post_increment(1)? },
+                ("ldbs", Some(2)) => LoadByteSignedPostInc { dst: data, base: post_increment(1)? },
+                ("stb", Some(2)) => StoreBytePostInc { src: data, base: post_increment(1)? },
+                _ => return Err(bad(1)),
+            }
+        }
+        "ldi" => { count(2)?; LoadImm { dst: r8(0)?, value: simm10(1)? } }
+        "lui" => { count(2)?; LoadUpperImm { dst: r8(0)?, value: value(1, 0, 255)? as u8 } }
+
+        "mov" | "movz" | "movnz" => {
+            count(2)?;
+            let (dst, src) = (r8(0)?, r10(1)?);
+            match mnemonic {
+                "mov" => Move { dst, src },
+                "movz" => MoveIfZero { dst, src },
+                _ => MoveIfNotZero { dst, src },
+            }
+        }
+        "movw" | "movwz" | "movwnz" => {
+            count(2)?;
+            let (dst, src) = (r10(0)?, r8(1)?);
+            match mnemonic {
+                "movw" => MoveWide { dst, src },
+                "movwz" => MoveWideIfZero { dst, src },
+                _ => MoveWideIfNotZero { dst, src },
+            }
+        }
+
+        // Register or immediate second source
+        "add" | "sub" | "mul" | "and" | "or" | "xor" => {
+            count(3)?;
+            let (dst, src1) = (r8(0)?, r8(1)?);
+            if is_register(2) {
+                let src2 = r10(2)?;
+                match mnemonic {
+                    "add" => Add { dst, src1, src2 },
+                    "sub" => Sub { dst, src1, src2 },
+                    "mul" => Mult { dst, src1, src2 },
+                    "and" => And { dst, src1, src2 },
+                    "or" => Or { dst, src1, src2 },
+                    _ => Xor { dst, src1, src2 },
+                }
+            } else {
+                match mnemonic {
+                    "add" => AddImm { dst, src: src1, imm: simm10(2)? },
+                    "sub" => SubImm { dst, src: src1, imm: simm10(2)? },
+                    "mul" => MultImm { dst, src: src1, imm: simm10(2)? },
+                    "and" => AndImm { dst, src: src1, imm: uimm10(2)? },
+                    "or" => OrImm { dst, src: src1, imm: uimm10(2)? },
+                    _ => XorImm { dst, src: src1, imm: uimm10(2)? },
+                }
+            }
+        }
+        "div" | "mod" => {
+            count(3)?;
+            let (dst, src1, src2) = (r8(0)?, r8(1)?, r10(2)?);
+            if mnemonic == "div" { Div { dst, src1, src2 } } else { Mod { dst, src1, src2 } }
+        }
+        "adc" | "sbc" | "mulw" | "mulws" | "qadd" | "qsub" | "qmul" | "mac" | "min" | "max" => {
+            count(3)?;
+            let (dst, src1, src2) = (r8(0)?, r8(1)?, r8(2)?);
+            match mnemonic {
+                "adc" => AddCarry { dst, src1, src2 },
+                "sbc" => SubBorrow { dst, src1, src2 },
+                "mulw" => MultWide { dst, src1, src2 },
+                "mulws" => MultWideSigned { dst, src1, src2 },
+                "qadd" => SatAdd { dst, src1, src2 },
+                "qsub" => SatSub { dst, src1, src2 },
+                "qmul" => Q15Mult { dst, src1, src2 },
+                "mac" => MultAcc { acc: dst, src1, src2 },
+                "min" => Min { dst, src1, src2 },
+                _ => Max { dst, src1, src2 },
+            }
+        }
+        "not" | "abs" | "clz" | "ctz" | "popc" | "bswap" => {
+            count(2)?;
+            let (dst, src) = (r8(0)?, r8(1)?);
+            match mnemonic {
+                "not" => Not { dst, src },
+                "abs" => Abs { dst, src },
+                "clz" => CountLeadingZeros { dst, src },
+                "ctz" => CountTrailingZeros { dst, src },
+                "popc" => Popcount { dst, src },
+                _ => ByteSwap { dst, src },
+            }
+        }
+        "cmp" => {
+            count(2)?;
+            let src1 = r8(0)?;
+            if is_register(1) { Cmp { src1, src2: r10(1)? } } else { CmpImm { src: src1, imm: simm10(1)? } }
+        }
+
+        // Register or immediate amount
+        "shl" | "shr" | "sar" | "rol" | "ror" | "rcl" | "rcr" => {
+            count(3)?;
+            let (dst, src) = (r8(0)?, r8(1)?);
+            if is_register(2) {
+                let amount = r8(2)?;
+                match mnemonic {
+                    "shl" => ShiftLeftReg { dst, src, amount },
+                    "shr" => ShiftRightReg { dst, src, amount },
+                    "sar" => ShiftRightArithReg { dst, src, amount },
+                    "rol" => RotateLeftReg { dst, src, amount },
+                    "ror" => RotateRightReg { dst, src, amount },
+                    _ => return Err(bad(2)),
+                }
+            } else {
+                match mnemonic {
+                    "shl" => ShiftLeft { dst, src, amount: uimm10(2)? },
+                    "shr" => ShiftRight { dst, src, amount: uimm10(2)? },
+                    _ => {
+                        let amount = value(2, 0, 15)? as u16;
+                        match mnemonic {
+                            "sar" => ShiftRightArith { dst, src, amount },
+                            "rol" => RotateLeft { dst, src, amount },
+                            "ror" => RotateRight { dst, src, amount },
+                            "rcl" => RotateLeftCarry { dst, src, amount },
+                            _ => RotateRightCarry { dst, src, amount },
+                        }
+                    }
+                }
+            }
+        }
+
+        "bset" | "bclr" | "btst" => {
+            count(3)?;
+            let (dst, src, index) = (r8(0)?, r8(1)?, value(2, 0, 15)? as u8);
+            match mnemonic {
+                "bset" => BitSet { dst, src, index },
+                "bclr" => BitClear { dst, src, index },
+                _ => BitTest { dst, src, index },
+            }
+        }
+        "bfx" | "bfi" => {
+            count(4)?;
+            let (dst, src, pos, width) = (r8(0)?, r8(1)?, value(2, 0, 15)? as u8, value(3, 1, 16)? as u8);
+            if mnemonic == "bfx" { BitFieldExtract { dst, src, pos, width } } else { BitFieldInsert { dst, src, pos, width } }
+        }
+
+        "beq" | "bne" | "blt" | "bgt" => {
+            count(3)?;
+            let (src1, src2, offset) = (r8(0)?, r10(1)?, offset(2, 8)?);
+            match mnemonic {
+                "beq" => BranchEqualRel { src1, src2, offset },
+                "bne" => BranchNotEqualRel { src1, src2, offset },
+                "blt" => BranchLessThanRel { src1, src2, offset },
+                _ => BranchGreaterThanRel { src1, src2, offset },
+            }
+        }
+        "beqa" | "bnea" | "blta" | "bgta" => {
+            count(3)?;
+            let (src1, src2, addr) = (r8(0)?, r10(1)?, value(2, 0, 255)? as u16);
+            match mnemonic {
+                "beqa" => BranchEqual { src1, src2, addr },
+                "bnea" => BranchNotEqual { src1, src2, addr },
+                "blta" => BranchLessThan { src1, src2, addr },
+                _ => BranchGreaterThan { src1, src2, addr },
+            }
+        }
+        "beq32" | "bne32" | "blt32" | "bgt32" => {
+            count(3)?;
+            let (src1, src2, offset) = (r8(0)?, r8(1)?, offset(2, 8)?);
+            match mnemonic {
+                "beq32" => BranchEqual32 { src1, src2, offset },
+                "bne32" => BranchNotEqual32 { src1, src2, offset },
+                "blt32" => BranchLessThan32 { src1, src2, offset },
+                _ => BranchGreaterThan32 { src1, src2, offset },
+            }
+        }
+        "jmp" => { count(1)?; JumpRel { offset: offset(0, 10)? } }
+        "jmpa" => { count(1)?; Jump { addr: uimm10(0)? } }
+        "jr" => { count(1)?; JumpReg { reg: r8(0)? as u16 } }
+
+        "push" => { count(1)?; Push { src: r10(0)? } }
+        "pop" => { count(1)?; Pop { dst: r10(0)? } }
+        "pushm" | "popm" => {
+            count(2)?;
+            let (first, last) = (r8(0)?, r8(1)?);
+            if last < first || (first..=last).contains(&isa::STACK_POINTER) {
+                return Err(bad(1));
+            }
+            if mnemonic == "pushm" { PushMultiple { first, last } } else { PopMultiple { first, last } }
+        }
+
+        "bank" => { count(1)?; BankSelect { bank: value(0, 0, 63)? as u8 } }
+        "sys" => { count(1)?; SystemCall { code: value(0, 0, 63)? as u8 } }
+        "mtc" => { count(2)?; MoveToControl { cr: value(0, 0, 63)? as u8, src: r8(1)? } }
+        "mfc" => { count(2)?; MoveFromControl { dst: r8(0)?, cr: value(1, 0, 63)? as u8 } }
+        "tlbflushp" => { count(1)?; FlushTlbPage { reg: r8(0)? } }
+        "fjmp" | "fcall" => {
+            count(2)?;
+            let (bank, target) = (value(0, 0, 63)? as u8, r8(1)?);
+            if mnemonic == "fjmp" { FarJump { bank, target } } else { FarCall { bank, target } }
+        }
+        "setdb" => { count(1)?; SetDataBank { src: r8(0)? } }
+
+        _ => return Err(AsmError::UnknownMnemonic { line, mnemonic: statement.mnemonic.clone() }),
+    };
+    Ok(vec![single])
+}
@@ -0,0 +1,583 @@
+; IEEE-754 binary16 and binary32 arithmetic in software, round to nearest even
+;
+; Calling convention: call the routines with `call` and they return with `ret`.
+;   binary32 operands  a in {r1:r0}, b in {r3:r2}, result in {r1:r0}
+;   binary16 operands  a in r0, b in r1, result in r0
+;   integers           i32 in {r1:r0}, i16 in r0
+;   compare results    r0 = -1 (a < b), 0 (equal), 1 (a > b), 2 (unordered)
+; The routines clobber r0-r14, r20-r31 and the flags and use up to 16 bytes of stack.
+; NaN results are the default quiet NaN. Conversions to integers truncate toward zero
+; and saturate, NaN converts to 0.
+;
+; binary16 operations widen to binary32, which is exact, and round the binary32 result
+; once to binary16. binary32 has more than twice the precision plus two bits, so for
+; add, sub, mul and div that gives the correctly rounded binary16 result.
+;
+; Internal registers: W = {r21:r20} is the working significand, r22 a shift amount,
+; r10 the result sign (0 or 0x8000), r11 the result exponent and {r31:r30} is zero.
+
+; ---- binary32 ----------------------------------------------------------------
+
+f32_sub:
+    lui r23, 0x80
+    xor r3, r3, r23             ; a - b = a + -b
+f32_add:
+    call sf_operands
+    bgt32 r4, r8, sf_nan
+    bgt32 r6, r8, sf_nan
+    bgt32 r6, r4, add_swap      ; order the operands so |a| >= |b|
+add_ordered:
+    bne32 r4, r8, add_finite
+    bne32 r6, r8, add_done      ; inf + finite = inf
+    xor r23, r1, r3
+    lui r24, 0x80
+    and r23, r23, r24
+    bne r23, r30, sf_nan        ; inf - inf
+add_done:
+    ret
+add_swap:
+    mov r23, r0
+    mov r0, r2
+    mov r2, r23
+    mov r23, r1
+    mov r1, r3
+    mov r3, r23
+    mov r23, r4
+    mov r4, r6
+    mov r6, r23
+    mov r23, r5
+    mov r5, r7
+    mov r7, r23
+    jmp add_ordered
+add_finite:
+    bne32 r6, r30, add_nonzero
+    bne32 r4, r30, add_done     ; x + 0 = x
+    and r1, r1, r3              ; 0 + 0 is -0 only if both are
+    ret
+add_nonzero:
+    lui r10, 0x80
+    and r10, r1, r10            ; the larger operand decides the sign
+    xor r12, r1, r3
+    lui r23, 0x80
+    and r12, r12, r23           ; nonzero when the signs differ
+    mov r20, r6
+    mov r21, r7
+    call sf_unpack
+    mov r6, r20
+    mov r7, r21
+    mov r27, r26
+    mov r20, r4
+    mov r21, r5
+    call sf_unpack
+    mov r11, r26
+    ldi r22, 6                  ; leading bits at 29, room for the carry out
+    call sf_shift_left
+    mov r4, r20
+    mov r5, r21
+    mov r20, r6
+    mov r21, r7
+    ldi r22, 6
+    call sf_shift_left
+    sub r22, r26, r27
+    call sf_shift_right_jam     ; align b
+    bne r12, r30, add_subtract
+    add r20, r20, r4
+    adc r21, r21, r5
+    jmp sf_norm_round_pack
+add_subtract:
+    sub r20, r4, r20
+    sbc r21, r5, r21
+    beq32 r20, r30, sf_positive_zero ; x - x = +0
+    jmp sf_norm_round_pack
+
+; Shared results: sign in r10
+sf_nan:
+    li r1, 0x7FC0
+    ldi r0, 0
+    ret
+sf_inf:
+    li r1, 0x7F80
+    or r1, r1, r10
+    ldi r0, 0
+    ret
+sf_zero:
+    mov r1, r10
+    ldi r0, 0
+    ret
+sf_positive_zero:
+    ldi r0, 0
+    ldi r1, 0
+    ret
+
+f32_mul:
+    call sf_operands
+    bgt32 r4, r8, sf_nan
+    bgt32 r6, r8, sf_nan
+    xor r10, r1, r3
+    lui r23, 0x80
+    and r10, r10, r23
+    beq32 r4, r8, mul_inf_a
+    beq32 r6, r8, mul_inf_b
+    beq32 r4, r30, sf_zero
+    beq32 r6, r30, sf_zero
+    mov r20, r6
+    mov r21, r7
+    call sf_unpack
+    mov r6, r20
+    mov r7, r21
+    mov r27, r26
+    mov r20, r4
+    mov r21, r5
+    call sf_unpack
+    add r11, r26, r27
+    sub r11, r11, 0x7F
+    ldi r22, 7                  ; a with its leading bit at 30
+    call sf_shift_left
+    mov r4, r20
+    mov r5, r21
+    shl r7, r7, 8               ; b with its leading bit at 31
+    shr r23, r6, 8
+    or r7, r7, r23
+    shl r6, r6, 8
+    ; 64-bit product {r23:r22:r21:r20} from four 16x16 products
+    mulw r20, r4, r6
+    mulw r22, r5, r7
+    mulw r24, r4, r7
+    add r21, r21, r24
+    adc r22, r22, r25
+    adc r23, r23, r30
+    mulw r24, r5, r6
+    add r21, r21, r24
+    adc r22, r22, r25
+    adc r23, r23, r30
+    or r20, r20, r21            ; keep the high half, jam the low half into its last bit
+    beq r20, r30, mul_exact
+    ldi r20, 1
+mul_exact:
+    or r20, r20, r22
+    mov r21, r23
+    jmp sf_norm_round_pack
+mul_inf_a:
+    beq32 r6, r30, sf_nan       ; inf * 0
+    jmp sf_inf
+mul_inf_b:
+    beq32 r4, r30, sf_nan
+    jmp sf_inf
+
+f32_div:
+    call sf_operands
+    bgt32 r4, r8, sf_nan
+    bgt32 r6, r8, sf_nan
+    xor r10, r1, r3
+    lui r23, 0x80
+    and r10, r10, r23
+    beq32 r4, r8, div_inf_a
+    beq32 r6, r8, sf_zero       ; x / inf
+    beq32 r6, r30, div_by_zero
+    beq32 r4, r30, sf_zero      ; 0 / x
+    mov r20, r6
+    mov r21, r7
+    call sf_unpack
+    mov r6, r20
+    mov r7, r21
+    mov r27, r26
+    mov r20, r4
+    mov r21, r5
+    call sf_unpack
+    sub r11, r26, r27
+    add r11, r11, 0x7E
+    blt32 r20, r6, div_scale
+    jmp div_start
+div_scale:
+    add r20, r20, r20           ; so that b <= a < 2b
+    adc r21, r21, r21
+    sub r11, r11, 1
+div_start:
+    ; Restoring division, 31 quotient bits into {r5:r4}, remainder in W
+    ldi r4, 0
+    ldi r5, 0
+    ldi r12, 31
+div_loop:
+    add r4, r4, r4
+    adc r5, r5, r5
+    blt32 r20, r6, div_next
+    sub r20, r20, r6
+    sbc r21, r21, r7
+    or r4, r4, 1
+div_next:
+    add r20, r20, r20
+    adc r21, r21, r21
+    sub r12, r12, 1
+    bne r12, r30, div_loop
+    beq32 r20, r30, div_exact
+    or r4, r4, 1                ; inexact
+div_exact:
+    mov r20, r4
+    mov r21, r5
+    jmp sf_round_pack
+div_inf_a:
+    beq32 r6, r8, sf_nan        ; inf / inf
+    jmp sf_inf
+div_by_zero:
+    beq32 r4, r30, sf_nan       ; 0 / 0
+    jmp sf_inf
+
+f32_cmp:
+    call sf_operands
+    ldi r0, 2
+    bgt32 r4, r8, cmp_done
+    bgt32 r6, r8, cmp_done
+    or r23, r4, r6
+    or r24, r5, r7
+    or r23, r23, r24
+    ldi r0, 0
+    beq r23, r30, cmp_done      ; -0 == +0
+    lui r23, 0x80               ; as signed integers: negative values negated
+    and r24, r1, r23
+    beq r24, r30, cmp_a
+    sub r4, r30, r4
+    sbc r5, r30, r5
+cmp_a:
+    and r24, r3, r23
+    beq r24, r30, cmp_b
+    sub r6, r30, r6
+    sbc r7, r30, r7
+cmp_b:
+    ldi r0, -1
+    blt32 r4, r6, cmp_done
+    ldi r0, 1
+    bgt32 r4, r6, cmp_done
+    ldi r0, 0
+cmp_done:
+    ret
+
+f32_to_i32:
+    call sf_operands
+    bgt32 r4, r8, toi_zero      ; NaN
+    lui r10, 0x80
+    and r10, r1, r10
+    bfx r11, r1, 7, 8
+    ldi r23, 127
+    blt r11, r23, toi_zero      ; |a| < 1
+    ldi r23, 158
+    blt r11, r23, toi_in_range
+    ldi r0, -1                  ; saturate, also for infinities
+    li r1, 0x7FFF
+    beq r10, r30, toi_done
+    ldi r0, 0
+    lui r1, 0x80
+toi_done:
+    ret
+toi_in_range:
+    mov r20, r4
+    and r21, r5, 0x7F
+    or r21, r21, 0x80
+    sub r22, r11, 150           ; binary point 23 bits up
+    blt r22, r30, toi_right
+    call sf_shift_left
+    jmp toi_sign
+toi_right:
+    sub r22, r30, r22
+    call sf_shift_right         ; truncate
+toi_sign:
+    mov r0, r20
+    mov r1, r21
+    beq r10, r30, toi_done
+    sub r0, r30, r20
+    sbc r1, r30, r21
+    ret
+toi_zero:
+    ldi r0, 0
+    ldi r1, 0
+    ret
+
+i32_to_f32:
+    ldi r30, 0
+    ldi r31, 0
+    beq32 r0, r30, itf_done     ; 0 converts to +0
+    lui r10, 0x80
+    and r10, r1, r10
+    mov r20, r0
+    mov r21, r1
+    beq r10, r30, itf_positive
+    sub r20, r30, r20
+    sbc r21, r30, r21
+    blt32 r20, r30, itf_min     ; -2^31 has no positive counterpart
+itf_positive:
+    ldi r11, 0x9C
+    jmp sf_norm_round_pack
+itf_min:
+    ldi r0, 0
+    li r1, 0xCF00
+itf_done:
+    ret
+
+; ---- binary16 ----------------------------------------------------------------
+
+f16_to_f32:
+    ldi r30, 0
+    ldi r31, 0
+    lui r10, 0x80
+    and r10, r0, r10
+    bfx r11, r0, 10, 5
+    and r20, r0, 0x3FF
+    ldi r23, 31
+    beq r11, r23, h2f_special
+    bne r11, r30, h2f_normal
+    beq r20, r30, h2f_pack      ; zero
+    clz r22, r20                ; subnormal: move the leading bit to 10
+    sub r22, r22, 5
+    shl r20, r20, r22
+    and r20, r20, 0x3FF
+    ldi r11, 113
+    sub r11, r11, r22
+    jmp h2f_pack
+h2f_special:
+    ldi r11, 0xFF               ; infinity, or NaN with the payload kept
+    jmp h2f_pack
+h2f_normal:
+    add r11, r11, 112
+h2f_pack:
+    shl r1, r11, 7
+    or r1, r1, r10
+    shr r23, r20, 3
+    or r1, r1, r23
+    shl r0, r20, 13
+    ret
+
+f32_to_f16:
+    call sf_operands
+    lui r10, 0x80
+    and r10, r1, r10
+    bgt32 r4, r8, f2h_nan
+    beq32 r4, r8, f2h_overflow
+    bfx r11, r5, 7, 8
+    beq r11, r30, f2h_zero      ; binary32 subnormals are far below half the smallest binary16 one
+    mov r20, r4
+    and r21, r5, 0x7F
+    or r21, r21, 0x80
+    ldi r22, 9                  ; leading bit at 14, four rounding bits
+    call sf_shift_right_jam
+    sub r11, r11, 113
+    ; Round and pack sign r10, exponent r11 (biased, minus one) and r20 into r0
+    and r12, r20, 0xF
+    blt r11, r30, f2h_tiny
+    ldi r23, 0x1D
+    blt r11, r23, f2h_round
+    bgt r11, r23, f2h_overflow
+    li r23, 0x7FF8
+    blt r20, r23, f2h_round
+f2h_overflow:
+    li r0, 0x7C00
+    or r0, r0, r10
+    ret
+f2h_tiny:
+    sub r22, r30, r11
+    call sf_shift_right_jam
+    ldi r11, 0
+    and r12, r20, 0xF
+f2h_round:
+    add r20, r20, 8
+    shr r20, r20, 4
+    ldi r23, 8
+    bne r12, r23, f2h_pack
+    bclr r20, r20, 0            ; tie: to even
+f2h_pack:
+    shl r0, r11, 10
+    add r0, r0, r10
+    add r0, r0, r20             ; a carry out of the significand bumps the exponent
+    ret
+f2h_nan:
+    li r0, 0x7E00
+    ret
+f2h_zero:
+    mov r0, r10
+    ret
+
+f16_add:
+    la r29, f32_add
+f16_narrowed:
+    call sf_widened
+    jmp f32_to_f16
+f16_sub:
+    la r29, f32_sub
+    jmp f16_narrowed
+f16_mul:
+    la r29, f32_mul
+    jmp f16_narrowed
+f16_div:
+    la r29, f32_div
+    jmp f16_narrowed
+f16_cmp:
+    la r29, f32_cmp
+    jmp sf_widened
+
+f16_to_i16:
+    call f16_to_f32
+    call f32_to_i32
+    li r20, 0x7FFF
+    ldi r21, 0
+    bgt32 r0, r20, h2i_max
+    lui r20, 0x80
+    ldi r21, -1
+    blt32 r0, r20, h2i_min
+    ret
+h2i_max:
+    li r0, 0x7FFF
+    ret
+h2i_min:
+    lui r0, 0x80
+    ret
+
+i16_to_f16:
+    sar r1, r0, 15
+    call i32_to_f32
+    jmp f32_to_f16
+
+; Run the binary32 routine at r29 on the binary16 operands r0 and r1
+sf_widened:
+    mov r28, r0
+    mov r0, r1
+    call f16_to_f32
+    mov r2, r0
+    mov r3, r1
+    mov r0, r28
+    call f16_to_f32
+    fjmp 0, r29                 ; its ret returns to our caller
+
+; ---- helpers -----------------------------------------------------------------
+
+; |a| into {r5:r4}, |b| into {r7:r6}, infinity into {r9:r8}, clear {r31:r30}
+sf_operands:
+    ldi r30, 0
+    ldi r31, 0
+    li r12, 0x7FFF
+    mov r4, r0
+    and r5, r1, r12
+    mov r6, r2
+    and r7, r3, r12
+    ldi r8, 0
+    li r9, 0x7F80
+    ret
+
+; Finite nonzero magnitude in W to its significand in W, leading bit at 23, and its
+; exponent in r26; subnormals are normalized and get an exponent below 1
+sf_unpack:
+    bfx r26, r21, 7, 8
+    and r21, r21, 0x7F
+    beq r26, r30, unpack_subnormal
+    or r21, r21, 0x80
+    ret
+unpack_subnormal:
+    call sf_clz32
+    sub r22, r22, 8
+    ldi r26, 1
+    sub r26, r26, r22
+    jmp sf_shift_left
+
+; Normalize a nonzero W below 2^31 to its leading bit at 30, then round and pack
+sf_norm_round_pack:
+    call sf_clz32
+    sub r22, r22, 1
+    sub r11, r11, r22
+    call sf_shift_left
+; Round and pack sign r10, exponent r11 (biased, minus one) and W (leading bit at 30,
+; seven rounding bits) into {r1:r0}
+sf_round_pack:
+    and r12, r20, 0x7F
+    blt r11, r30, round_tiny
+    ldi r23, 0xFD
+    blt r11, r23, round_nearest
+    bgt r11, r23, round_overflow
+    li r24, 0xFFBF
+    li r25, 0x7FFF
+    bgt32 r20, r24, round_overflow ; rounds up past the largest exponent
+    jmp round_nearest
+round_overflow:
+    jmp sf_inf
+round_tiny:
+    sub r22, r30, r11
+    call sf_shift_right_jam
+    ldi r11, 0
+    and r12, r20, 0x7F
+round_nearest:
+    add r20, r20, 0x40
+    adc r21, r21, r30
+    shr r20, r20, 7
+    shl r23, r21, 9
+    or r20, r20, r23
+    shr r21, r21, 7
+    ldi r23, 0x40
+    bne r12, r23, round_pack
+    bclr r20, r20, 0            ; tie: to even
+round_pack:
+    shl r1, r11, 7
+    add r1, r1, r10
+    add r1, r1, r21             ; a carry out of the significand bumps the exponent
+    mov r0, r20
+    ret
+
+; Leading zeros of W into r22
+sf_clz32:
+    bne r21, r30, clz_high
+    clz r22, r20
+    add r22, r22, 16
+    ret
+clz_high:
+    clz r22, r21
+    ret
+
+; W <<= r22 (0-31)
+sf_shift_left:
+    ldi r23, 16
+    blt r22, r23, shl_short
+    sub r23, r22, 16
+    shl r21, r20, r23
+    ldi r20, 0
+    ret
+shl_short:
+    sub r23, r23, r22
+    shr r24, r20, r23
+    shl r21, r21, r22
+    or r21, r21, r24
+    shl r20, r20, r22
+    ret
+
+; W >>= r22 with the bits shifted out ORed into the last bit
+sf_shift_right_jam:
+    call sf_shift_right
+    beq r24, r30, jam_done
+    or r20, r20, 1
+jam_done:
+    ret
+
+; W >>= r22 (any amount), r24 nonzero if a one was shifted out
+sf_shift_right:
+    ldi r24, 0
+    beq r22, r30, shr_done
+    ldi r23, 32
+    blt r22, r23, shr_short
+    or r24, r20, r21
+    ldi r20, 0
+    ldi r21, 0
+shr_done:
+    ret
+shr_short:
+    ldi r23, 16
+    blt r22, r23, shr_small
+    ldi r23, 32
+    sub r23, r23, r22
+    shl r24, r21, r23
+    or r24, r24, r20
+    sub r23, r22, 16
+    shr r20, r21, r23
+    ldi r21, 0
+    ret
+shr_small:
+    sub r23, r23, r22
+    shl r24, r20, r23
+    shr r20, r20, r22
+    shl r25, r21, r23
+    or r20, r20, r25
+    shr r21, r21, r22
+    ret
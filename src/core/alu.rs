@@ -10,6 +10,20 @@ pub fn sub(a: u16, b: u16) -> (u16, bool) {
     (result, carry)
 }
 
+// Low half of the product; the flag is set when the product does not fit in 16 bits
+pub fn mult(a: u16, b: u16) -> (u16, bool) {
+    a.overflowing_mul(b)
+}
+
+// Division by zero gives all ones and leaves the dividend as the remainder
+pub fn div(a: u16, b: u16) -> u16 {
+    a.checked_div(b).unwrap_or(0xFFFF)
+}
+
+pub fn modulo(a: u16, b: u16) -> u16 {
+    a.checked_rem(b).unwrap_or(a)
+}
+
 pub fn and(a: u16, b: u16) -> u16 {
     a & b
 }
@@ -41,3 +55,160 @@ pub fn shift_right(a: u16, amount: u16) -> u16 {
         a >> amount
     }
 }
+
+pub fn shift_right_arith(a: u16, amount: u16) -> u16 {
+    if amount >= 16 {
+        if (a as i16) < 0 { 0xFFFF } else { 0 }
+    } else {
+        ((a as i16) >> amount) as u16
+    }
+}
+
+pub fn rotate_left(a: u16, amount: u16) -> u16 {
+    a.rotate_left((amount % 16) as u32)
+}
+
+pub fn rotate_right(a: u16, amount: u16) -> u16 {
+    a.rotate_right((amount % 16) as u32)
+}
+
+// Rotate through carry: the carry acts as a 17th bit above bit 15
+pub fn rotate_left_carry(a: u16, amount: u16, carry: bool) -> (u16, bool) {
+    let wide = ((carry as u32) << 16) | a as u32;
+    let n = (amount % 17) as u32;
+    let rotated = ((wide << n) | (wide >> (17 - n))) & 0x1FFFF;
+    (rotated as u16, rotated & 0x10000 != 0)
+}
+
+pub fn rotate_right_carry(a: u16, amount: u16, carry: bool) -> (u16, bool) {
+    let wide = ((carry as u32) << 16) | a as u32;
+    let n = (amount % 17) as u32;
+    let rotated = ((wide >> n) | (wide << (17 - n))) & 0x1FFFF;
+    (rotated as u16, rotated & 0x10000 != 0)
+}
+
+// Last bit shifted out of a left shift (false when nothing is shifted)
+pub fn shift_left_carry(a: u16, amount: u16) -> bool {
+    amount != 0 && amount <= 16 && (a >> (16 - amount)) & 1 != 0
+}
+
+// Last bit shifted out of a right shift; arithmetic shifts keep shifting in the sign
+pub fn shift_right_carry(a: u16, amount: u16, arithmetic: bool) -> bool {
+    if amount == 0 {
+        false
+    } else if amount > 16 {
+        arithmetic && (a as i16) < 0
+    } else {
+        (a >> (amount - 1)) & 1 != 0
+    }
+}
+
+pub fn count_leading_zeros(a: u16) -> u16 {
+    a.leading_zeros() as u16
+}
+
+pub fn count_trailing_zeros(a: u16) -> u16 {
+    a.trailing_zeros() as u16
+}
+
+pub fn popcount(a: u16) -> u16 {
+    a.count_ones() as u16
+}
+
+pub fn byte_swap(a: u16) -> u16 {
+    a.swap_bytes()
+}
+
+pub fn bit_set(a: u16, index: u16) -> u16 {
+    a | (1 << (index & 0xF))
+}
+
+pub fn bit_clear(a: u16, index: u16) -> u16 {
+    a & !(1 << (index & 0xF))
+}
+
+pub fn bit_test(a: u16, index: u16) -> u16 {
+    (a >> (index & 0xF)) & 1
+}
+
+// Mask of `width` bits starting at `pos`, clipped to 16 bits
+fn field_mask(pos: u16, width: u16) -> u16 {
+    let ones = if width >= 16 { 0xFFFF } else { (1u16 << width) - 1 };
+    shift_left(ones, pos)
+}
+
+pub fn bit_field_extract(a: u16, pos: u16, width: u16) -> u16 {
+    shift_right(a & field_mask(pos, width), pos)
+}
+
+pub fn bit_field_insert(dst: u16, src: u16, pos: u16, width: u16) -> u16 {
+    let mask = field_mask(pos, width);
+    (dst & !mask) | (shift_left(src, pos) & mask)
+}
+
+// Q15 / saturating arithmetic: the bool reports whether the result saturated
+fn saturate_16(value: i32) -> (u16, bool) {
+    if value > i16::MAX as i32 {
+        (i16::MAX as u16, true)
+    } else if value < i16::MIN as i32 {
+        (i16::MIN as u16, true)
+    } else {
+        (value as i16 as u16, false)
+    }
+}
+
+pub fn saturating_add(a: u16, b: u16) -> (u16, bool) {
+    saturate_16(a as i16 as i32 + b as i16 as i32)
+}
+
+pub fn saturating_sub(a: u16, b: u16) -> (u16, bool) {
+    saturate_16(a as i16 as i32 - b as i16 as i32)
+}
+
+// Q15 x Q15 -> Q15, rounded to nearest
+pub fn q15_mul(a: u16, b: u16) -> (u16, bool) {
+    let product = a as i16 as i32 * b as i16 as i32;
+    saturate_16((product + (1 << 14)) >> 15)
+}
+
+// 32-bit accumulator += a * b (signed), saturating
+pub fn mult_acc(acc: u32, a: u16, b: u16) -> (u32, bool) {
+    let product = a as i16 as i32 * b as i16 as i32;
+    match (acc as i32).checked_add(product) {
+        Some(sum) => (sum as u32, false),
+        None if product < 0 => (i32::MIN as u32, true),
+        None => (i32::MAX as u32, true),
+    }
+}
+
+pub fn abs_saturating(a: u16) -> (u16, bool) {
+    saturate_16((a as i16 as i32).abs())
+}
+
+pub fn min_signed(a: u16, b: u16) -> u16 {
+    if (a as i16) < (b as i16) { a } else { b }
+}
+
+pub fn max_signed(a: u16, b: u16) -> u16 {
+    if (a as i16) > (b as i16) { a } else { b }
+}
+
+// Multi-precision helpers: carry in/out chain through the carry flag,
+// which holds the borrow after a subtraction (as in `sub`)
+pub fn add_with_carry(a: u16, b: u16, carry: bool) -> (u16, bool) {
+    let sum = a as u32 + b as u32 + carry as u32;
+    (sum as u16, sum > 0xFFFF)
+}
+
+pub fn sub_with_borrow(a: u16, b: u16, borrow: bool) -> (u16, bool) {
+    let diff = a as i32 - b as i32 - borrow as i32;
+    (diff as u16, diff < 0)
+}
+
+pub fn mult_wide(a: u16, b: u16) -> u32 {
+    a as u32 * b as u32
+}
+
+pub fn mult_wide_signed(a: u16, b: u16) -> u32 {
+    (a as i16 as i32 * b as i16 as i32) as u32
+}
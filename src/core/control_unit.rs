@@ -1,19 +1,52 @@
-use crate::core::CpuState;
+use crate::core::{CpuState, ExecutionMode, PipelineController};
+use crate::core::five_stage::{FiveStagePipeline, PipelineConfig};
+use crate::core::functional_units::UNIT_KINDS;
+use crate::core::memory_dependence::LoadSpeculation;
+use crate::core::mpu::Access;
+use crate::core::rename::RenameScheme;
+use crate::core::smt::{FetchPolicy, SmtCore};
+use crate::core::value_prediction::{ReuseBuffer, ValuePredictor, ValuePredictorKind};
 use crate::isa;
 use crate::memory::Memory;
 
-/// Main execution function that can switch between in-order and out-of-order execution
+/// Main execution function that dispatches to the selected execution mode
 pub fn step(cpu: &mut CpuState, mem: &mut Memory) -> bool {
-    if cpu.halted {
+    // Thread 0 halting does not stop the other SMT threads
+    if cpu.halted && cpu.execution_mode != ExecutionMode::Smt {
         return false;
     }
 
-    if cpu.out_of_order_enabled {
+    match cpu.execution_mode {
         // Use Tomasulo's algorithm (out-of-order execution)
-        cpu.pipeline.step(cpu, mem)
-    } else {
+        ExecutionMode::OutOfOrder => {
+            let mut pipeline = std::mem::replace(&mut cpu.pipeline, PipelineController::new());
+            let running = pipeline.step(cpu, mem);
+            cpu.pipeline = pipeline;
+            running
+        }
+        // One cycle of the five-stage pipeline
+        ExecutionMode::Pipelined => {
+            let mut pipeline = std::mem::replace(&mut cpu.five_stage, FiveStagePipeline::new(PipelineConfig::new()));
+            let running = pipeline.step(cpu, mem);
+            cpu.five_stage = pipeline;
+            running
+        }
+        // One cycle of the scoreboard
+        ExecutionMode::Scoreboard => {
+            let mut scoreboard = std::mem::take(&mut cpu.scoreboard);
+            let running = scoreboard.step(cpu, mem);
+            cpu.scoreboard = scoreboard;
+            running
+        }
         // Use original in-order execution
-        step_in_order(cpu, mem)
+        ExecutionMode::InOrder => step_in_order(cpu, mem),
+        // One cycle of every hardware thread
+        ExecutionMode::Smt => {
+            let mut smt = std::mem::take(&mut cpu.smt);
+            let running = smt.step(cpu, mem);
+            cpu.smt = smt;
+            running
+        }
     }
 }
 
@@ -22,14 +55,25 @@ pub fn step_in_order(cpu: &mut CpuState, mem: &mut Memory) -> bool {
     if cpu.halted {
         return false;
     }
+    cpu.tick_memory(); // One instruction per step
 
-    let raw = mem.fetch(cpu.pc);
+    if cpu.take_interrupt() {
+        return true;
+    }
+    if let Err(fault) = cpu.check_access(mem, cpu.pc, 4, Access::Execute) {
+        cpu.raise_exception(fault);
+        return true;
+    }
+
+    cpu.cache_stall(mem, cpu.pc, cpu.pc, 4, Access::Execute);
+    let raw = cpu.mmu.fetch(mem, cpu.pc);
     if let Some(inst) = isa::decode(raw) {
-        // Save PC before execution in case of jumps
-        let old_pc = cpu.pc;
+        // Save PC and code bank before execution in case of jumps
+        let (old_pc, old_bank) = (cpu.pc, mem.code_bank);
         crate::core::execute::execute(inst, cpu, mem);
-        // Only increment PC if it wasn't changed by a jump/branch
-        if cpu.pc == old_pc && !cpu.halted {
+        // Only increment PC if it wasn't changed by a jump/branch (a far jump may keep
+        // the address and only change the bank)
+        if cpu.pc == old_pc && mem.code_bank == old_bank && !cpu.halted {
             cpu.pc += 4; // 4-byte instructions
         }
         true
@@ -39,13 +83,9 @@ pub fn step_in_order(cpu: &mut CpuState, mem: &mut Memory) -> bool {
     }
 }
 
-/// Run the CPU until it halts
+/// Run the CPU until it halts (in SMT mode, until every thread has)
 pub fn run(cpu: &mut CpuState, mem: &mut Memory) {
-    while !cpu.halted {
-        if !step(cpu, mem) {
-            break;
-        }
-    }
+    while step(cpu, mem) {}
 }
 
 /// Run the CPU for a specific number of cycles
@@ -73,19 +113,167 @@ pub fn disable_out_of_order(cpu: &mut CpuState) {
     println!("Out-of-order execution disabled (in-order mode)");
 }
 
+/// Select how the out-of-order engine renames registers
+pub fn set_rename_scheme(cpu: &mut CpuState, scheme: RenameScheme) {
+    cpu.rename_scheme = scheme;
+}
+
+/// Select when the out-of-order engine lets loads go ahead of older stores
+pub fn set_load_speculation(cpu: &mut CpuState, policy: LoadSpeculation) {
+    cpu.memory_dependence.policy = policy;
+}
+
+/// Predict ALU and load results in the out-of-order engine (None turns prediction off)
+pub fn set_value_prediction(cpu: &mut CpuState, kind: Option<ValuePredictorKind>) {
+    cpu.pipeline.value_predictor = kind.map(|kind| ValuePredictor::new(kind, 64));
+}
+
+/// Reuse ALU results for repeated operands in the out-of-order engine
+pub fn set_instruction_reuse(cpu: &mut CpuState, enabled: bool) {
+    cpu.pipeline.reuse_buffer = enabled.then(|| ReuseBuffer::new(64));
+}
+
+/// Select the five-stage in-order pipeline
+pub fn enable_pipelined(cpu: &mut CpuState) {
+    cpu.set_execution_mode(ExecutionMode::Pipelined);
+}
+
+/// Select the scoreboard out-of-order engine
+pub fn enable_scoreboard(cpu: &mut CpuState) {
+    cpu.set_execution_mode(ExecutionMode::Scoreboard);
+}
+
+/// Run the out-of-order engine with one hardware thread per entry point
+pub fn enable_smt(cpu: &mut CpuState, entry_points: &[u16], policy: FetchPolicy) {
+    cpu.smt = SmtCore::new(cpu, entry_points, policy);
+    cpu.set_execution_mode(ExecutionMode::Smt);
+}
+
 /// Print CPU state for debugging
 pub fn print_cpu_state(cpu: &CpuState) {
     println!("=== CPU State ===");
     println!("PC: 0x{:04X}", cpu.pc);
     println!("Halted: {}", cpu.halted);
-    println!("Out-of-order enabled: {}", cpu.out_of_order_enabled);
-    println!("Cycles: {}", cpu.pipeline.cycles);
+    println!("Execution mode: {:?}", cpu.execution_mode);
+    println!("Cycles: {}", cpu.cycles());
+
+    if cpu.execution_mode == ExecutionMode::Pipelined {
+        let pipeline = &cpu.five_stage;
+        let stats = &pipeline.stats;
+        println!("Instructions: {} (CPI {:.2})", stats.instructions, pipeline.cpi());
+        println!("Stalls: {} data ({} load-use), {} fetch, {} memory",
+                 stats.data_stalls, stats.load_use_stalls, stats.fetch_stalls, stats.memory_stalls);
+        println!("Branch flushes: {} ({} instructions squashed)", stats.branch_flushes, stats.flushed);
+    }
+
+    if cpu.execution_mode == ExecutionMode::Scoreboard {
+        println!("Instructions: {} (CPI {:.2})", cpu.scoreboard.instructions, cpu.scoreboard.cpi());
+    }
 
-    if cpu.out_of_order_enabled {
+    if cpu.execution_mode == ExecutionMode::Smt {
+        let smt = &cpu.smt;
+        println!("SMT: {} threads, {:?} fetch, {} instructions (IPC {:.2})",
+                 smt.threads(), smt.policy, smt.instructions(cpu), smt.ipc(cpu));
+        for thread in 0..smt.threads() {
+            let (pc, halted, rob) = match thread {
+                0 => (cpu.pc, cpu.halted, &cpu.reorder_buffer),
+                _ => {
+                    let context = &smt.contexts[thread - 1];
+                    (context.pc, context.halted, &context.reorder_buffer)
+                }
+            };
+            let pipeline = smt.pipeline(cpu, thread);
+            println!("  Thread {}: PC 0x{:04X}, halted {}, ROB {}/{}, {} instructions (IPC {:.2}), {} mispredicted branches",
+                     thread, pc, halted, rob.count, rob.size, pipeline.instructions, pipeline.ipc(),
+                     pipeline.mispredicted_branches);
+        }
+    }
+
+    if matches!(cpu.execution_mode, ExecutionMode::OutOfOrder | ExecutionMode::Scoreboard | ExecutionMode::Smt) {
+        let stats = &cpu.unit_stats;
+        let issued: Vec<String> = UNIT_KINDS
+            .iter()
+            .map(|kind| format!("{:?} {}", kind, stats.issued[kind.index()]))
+            .collect();
+        println!("Issued: {}", issued.join(", "));
+        println!("Stalls: {} structural, {} RAW, {} WAR, {} WAW",
+                 stats.structural_stalls, stats.raw_stalls, stats.war_stalls, stats.waw_stalls);
+    }
+
+    if cpu.execution_mode == ExecutionMode::OutOfOrder {
+        println!("Instructions: {} (IPC {:.2})", cpu.pipeline.instructions, cpu.pipeline.ipc());
         println!("ROB entries: {}", cpu.reorder_buffer.count);
         println!("Instruction queue: {}", cpu.pipeline.instruction_queue.len());
+        let pipeline = &cpu.pipeline;
+        println!("Speculated branches: {}, {} mispredicted ({} instructions squashed)",
+                 pipeline.speculated_branches, pipeline.mispredicted_branches, pipeline.squashed);
+        println!("Renaming: {:?}", cpu.rename_scheme);
+        if cpu.rename_scheme == RenameScheme::PhysicalRegisters {
+            let prf = &cpu.physical_registers;
+            let stats = &prf.stats;
+            println!("Physical registers: {} free, {} in flight (peak {}), {} allocations, {} free list stalls",
+                     prf.free_registers(), prf.in_flight(), stats.peak_in_flight, stats.allocations, stats.free_list_stalls);
+            println!("Checkpoints: {} taken, {} restored, {} stalls; {} renames unwound",
+                     stats.checkpoints, stats.restores, stats.checkpoint_stalls, stats.unwound);
+        }
+        let memory = &cpu.memory_dependence;
+        let stats = &memory.stats;
+        println!("Load speculation: {:?}", memory.policy);
+        if memory.policy != LoadSpeculation::Conservative {
+            println!("  {} speculative loads, {} forwarded, {} predicted dependences",
+                     stats.speculative_loads, stats.forwarded, stats.predicted_dependences);
+            println!("  {} memory order violations ({:.1}% of speculative loads), {} instructions replayed",
+                     stats.violations, stats.violation_rate() * 100.0, stats.replayed);
+        }
+        if let Some(predictor) = &cpu.pipeline.value_predictor {
+            let stats = &predictor.stats;
+            println!("Value prediction ({:?}): {} predicted, {} correct, {} mispredicted ({} instructions squashed)",
+                     predictor.kind, stats.predicted, stats.correct, stats.mispredicted, stats.squashed);
+            println!("  coverage {:.1}% of {} results, accuracy {:.1}%",
+                     stats.coverage() * 100.0, stats.eligible, stats.accuracy() * 100.0);
+        }
+        if let Some(buffer) = &cpu.pipeline.reuse_buffer {
+            let stats = &buffer.stats;
+            println!("Instruction reuse: {} of {} lookups reused ({:.1}%)",
+                     stats.reused, stats.lookups, stats.reuse_rate() * 100.0);
+        }
+    }
+
+    if cpu.mmu.enabled {
+        let tlb = &cpu.mmu.stats;
+        println!("TLB: {} hits, {} misses ({:.1}% hit rate), {} page faults",
+                 tlb.hits, tlb.misses, tlb.hit_rate() * 100.0, tlb.page_faults);
+    }
+
+    let l2 = cpu.hierarchy.as_ref().and_then(|hierarchy| hierarchy.l2.as_ref());
+    for cache in [cpu.icache.as_ref(), cpu.dcache.as_ref(), l2].into_iter().flatten() {
+        let stats = &cache.stats;
+        println!("{}: {} reads ({} misses), {} writes ({} misses), {:.1}% hit rate, {} writebacks, {} memory writes",
+                 cache.name, stats.reads, stats.read_misses, stats.writes, stats.write_misses,
+                 stats.hit_rate() * 100.0, stats.writebacks, stats.memory_writes);
+    }
+
+    if let Some(hierarchy) = &cpu.hierarchy {
+        let dram = &hierarchy.dram.stats;
+        println!("DRAM: {} reads, {} writes, {} row hits ({:.1}%), {} empty, {} conflicts",
+                 dram.reads, dram.writes, dram.row_hits, dram.row_hit_rate() * 100.0,
+                 dram.row_empty, dram.row_conflicts);
+        let stats = &hierarchy.stats;
+        println!("MSHRs: {} outstanding of {}, peak {}, {} merges, {} full stalls, MLP {:.2}",
+                 hierarchy.mshrs.len(), hierarchy.max_mshrs, stats.peak_outstanding, stats.mshr_merges,
+                 stats.mshr_full_stalls, stats.memory_level_parallelism());
+    }
+
+    if let Some(prefetcher) = &cpu.prefetcher {
+        let stats = &prefetcher.stats;
+        println!("Prefetcher ({:?}, degree {}): {} issued, {} useful, {} late, {} evicted unused, {} redundant, {} dropped",
+                 prefetcher.policy, prefetcher.degree, stats.issued, stats.useful, stats.late,
+                 stats.evicted_unused, stats.redundant, stats.dropped);
+        println!("  accuracy {:.1}%, coverage {:.1}%, timeliness {:.1}%",
+                 stats.accuracy() * 100.0, stats.coverage() * 100.0, stats.timeliness() * 100.0);
     }
 
     println!("Flags: Zero={}, Carry={}, Negative={}, Overflow={}", 
              cpu.flags.zero, cpu.flags.carry, cpu.flags.negative, cpu.flags.overflow);
+    print!("{}", cpu.regs.dump());
 }
\ No newline at end of file
@@ -1,11 +1,43 @@
-use crate::isa::Instruction;
-use crate::core::{CpuState, alu};
-use crate::memory::Memory;
+use crate::isa::{self, Instruction};
+use crate::core::{CpuState, Exception, alu};
+use crate::core::mpu::Access;
+use crate::memory::{Memory, NUM_MEMORY_BANKS};
+
+// `cpu.pc` must hold the address of `instruction` (traps use it as the return address).
+// Returns the cycles a load or store spends beyond its single memory cycle (page table
+// walk and cache misses), 0 for everything else.
+pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) -> u32 {
+    if isa::is_privileged(&instruction) && !cpu.is_supervisor() {
+        cpu.raise_exception(Exception::PrivilegedInstruction);
+        return 0;
+    }
+
+    // Check the whole access up front so a fault leaves no partial side effects
+    let mut stall = 0;
+    if let Some((addr, len, access)) = memory_access(&instruction, cpu) {
+        match cpu.check_access(mem, addr, len, access) {
+            Ok(walked) => {
+                if walked {
+                    stall = cpu.mmu.walk_latency * 2;
+                }
+            }
+            Err(fault) => {
+                cpu.raise_exception(fault);
+                return 0;
+            }
+        }
+        stall += cpu.cache_stall(mem, cpu.pc, addr, len, access);
+    }
+
+    // User code may call around its own code bank, but only the kernel switches banks
+    if !cpu.is_supervisor() && target_bank(&instruction, cpu, mem).is_some_and(|bank| bank != mem.code_bank) {
+        cpu.raise_exception(Exception::PrivilegedInstruction);
+        return 0;
+    }
 
-pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
     match instruction {
         Instruction::Load { dst, addr } => {
-            let value = mem.load_u16(addr);
+            let value = cpu.mmu.read_u16(mem, addr);
             cpu.regs.write(dst, value);
         }
 
@@ -13,9 +45,70 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.regs.write(dst, value as u16);
         }
 
+        Instruction::LoadUpperImm { dst, value } => {
+            cpu.regs.write(dst, (value as u16) << 8);
+        }
+
         Instruction::Store { src, addr } => {
             let value = cpu.regs.read(src);
-            mem.store_u16(addr, value);
+            cpu.mmu.write_u16(mem, addr, value);
+        }
+
+        Instruction::LoadIndexed { dst, base, offset } => {
+            let addr = isa::effective_address(cpu.regs.read(base), offset);
+            cpu.regs.write(dst, cpu.mmu.read_u16(mem, addr));
+        }
+
+        Instruction::StoreIndexed { src, base, offset } => {
+            let addr = isa::effective_address(cpu.regs.read(base), offset);
+            cpu.mmu.write_u16(mem, addr, cpu.regs.read(src));
+        }
+
+        Instruction::LoadByte { dst, base, offset } => {
+            let addr = isa::effective_address(cpu.regs.read(base), offset);
+            cpu.regs.write(dst, cpu.mmu.read_u8(mem, addr) as u16);
+        }
+
+        Instruction::LoadByteSigned { dst, base, offset } => {
+            let addr = isa::effective_address(cpu.regs.read(base), offset);
+            cpu.regs.write(dst, cpu.mmu.read_u8(mem, addr) as i8 as u16);
+        }
+
+        Instruction::StoreByte { src, base, offset } => {
+            let addr = isa::effective_address(cpu.regs.read(base), offset);
+            cpu.mmu.write_u8(mem, addr, cpu.regs.read(src) as u8);
+        }
+
+        // Post-increment forms update the base after the access, so with dst == base
+        // the base ends up as the loaded value plus the access size
+        Instruction::LoadPostInc { dst, base } => {
+            let value = cpu.mmu.read_u16(mem, cpu.regs.read(base));
+            cpu.regs.write(dst, value);
+            cpu.regs.write(base, cpu.regs.read(base).wrapping_add(2));
+        }
+
+        Instruction::StorePostInc { src, base } => {
+            let addr = cpu.regs.read(base);
+            cpu.mmu.write_u16(mem, addr, cpu.regs.read(src));
+            cpu.regs.write(base, addr.wrapping_add(2));
+        }
+
+        Instruction::LoadBytePostInc { dst, base } => {
+            let value = cpu.mmu.read_u8(mem, cpu.regs.read(base)) as u16;
+            cpu.regs.write(dst, value);
+            cpu.regs.write(base, cpu.regs.read(base).wrapping_add(1));
+        }
+
+        Instruction::LoadByteSignedPostInc { dst, base } => {
+            let value = cpu.mmu.read_u8(mem, cpu.regs.read(base)) as i8 as u16;
+            cpu.regs.write(dst, value);
+            cpu.regs.write(base, cpu.regs.read(base).wrapping_add(1));
+        }
+
+        Instruction::StoreBytePostInc { src, base } => {
+            let addr = cpu.regs.read(base);
+            cpu.mmu.write_u8(mem, addr, cpu.regs.read(src) as u8);
+            cpu.regs.write(base, addr.wrapping_add(1));
         }
 
         Instruction::Move { dst, src } => {
@@ -90,6 +183,32 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.set_flags_from_result(result);
         }
 
+        Instruction::Mult { dst, src1, src2 } => {
+            let (result, overflow) = alu::mult(cpu.regs.read(src1), cpu.regs.read_10bit(src2));
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = overflow;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::MultImm { dst, src, imm } => {
+            let (result, overflow) = alu::mult(cpu.regs.read(src), imm as u16);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = overflow;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Div { dst, src1, src2 } => {
+            let result = alu::div(cpu.regs.read(src1), cpu.regs.read_10bit(src2));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Mod { dst, src1, src2 } => {
+            let result = alu::modulo(cpu.regs.read(src1), cpu.regs.read_10bit(src2));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
         Instruction::And { dst, src1, src2 } => {
             let val1 = cpu.regs.read(src1);
             let val2 = cpu.regs.read_10bit(src2);
@@ -114,6 +233,27 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.set_flags_from_result(result);
         }
 
+        Instruction::AndImm { dst, src, imm } => {
+            let val = cpu.regs.read(src);
+            let result = alu::and(val, imm);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::OrImm { dst, src, imm } => {
+            let val = cpu.regs.read(src);
+            let result = alu::or(val, imm);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::XorImm { dst, src, imm } => {
+            let val = cpu.regs.read(src);
+            let result = alu::xor(val, imm);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
         Instruction::Not { dst, src } => {
             let val = cpu.regs.read(src);
             let result = alu::not(val);
@@ -121,10 +261,12 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.set_flags_from_result(result);
         }
 
+        // Shifts leave the last bit shifted out in carry
         Instruction::ShiftLeft { dst, src, amount } => {
             let val = cpu.regs.read(src);
             let result = alu::shift_left(val, amount);
             cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_left_carry(val, amount);
             cpu.set_flags_from_result(result);
         }
 
@@ -132,6 +274,236 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             let val = cpu.regs.read(src);
             let result = alu::shift_right(val, amount);
             cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_right_carry(val, amount, false);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::ShiftRightArith { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let result = alu::shift_right_arith(val, amount);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_right_carry(val, amount, true);
+            cpu.set_flags_from_result(result);
+        }
+
+        // Plain rotates copy the bit that wrapped around into carry
+        Instruction::RotateLeft { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let result = alu::rotate_left(val, amount);
+            cpu.regs.write(dst, result);
+            if amount != 0 {
+                cpu.flags.carry = result & 1 != 0;
+            }
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::RotateRight { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let result = alu::rotate_right(val, amount);
+            cpu.regs.write(dst, result);
+            if amount != 0 {
+                cpu.flags.carry = (result as i16) < 0;
+            }
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::RotateLeftCarry { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let (result, carry) = alu::rotate_left_carry(val, amount, cpu.flags.carry);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = carry;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::RotateRightCarry { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let (result, carry) = alu::rotate_right_carry(val, amount, cpu.flags.carry);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = carry;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::ShiftLeftReg { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let amount = cpu.regs.read(amount);
+            let result = alu::shift_left(val, amount);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_left_carry(val, amount);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::ShiftRightReg { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let amount = cpu.regs.read(amount);
+            let result = alu::shift_right(val, amount);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_right_carry(val, amount, false);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::ShiftRightArithReg { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let amount = cpu.regs.read(amount);
+            let result = alu::shift_right_arith(val, amount);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = alu::shift_right_carry(val, amount, true);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::RotateLeftReg { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let amount = cpu.regs.read(amount);
+            let result = alu::rotate_left(val, amount);
+            cpu.regs.write(dst, result);
+            if !amount.is_multiple_of(16) {
+                cpu.flags.carry = result & 1 != 0;
+            }
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::RotateRightReg { dst, src, amount } => {
+            let val = cpu.regs.read(src);
+            let amount = cpu.regs.read(amount);
+            let result = alu::rotate_right(val, amount);
+            cpu.regs.write(dst, result);
+            if !amount.is_multiple_of(16) {
+                cpu.flags.carry = (result as i16) < 0;
+            }
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::CountLeadingZeros { dst, src } => {
+            let result = alu::count_leading_zeros(cpu.regs.read(src));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::CountTrailingZeros { dst, src } => {
+            let result = alu::count_trailing_zeros(cpu.regs.read(src));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Popcount { dst, src } => {
+            let result = alu::popcount(cpu.regs.read(src));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::ByteSwap { dst, src } => {
+            let result = alu::byte_swap(cpu.regs.read(src));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::BitSet { dst, src, index } => {
+            let result = alu::bit_set(cpu.regs.read(src), index as u16);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::BitClear { dst, src, index } => {
+            let result = alu::bit_clear(cpu.regs.read(src), index as u16);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::BitTest { dst, src, index } => {
+            let result = alu::bit_test(cpu.regs.read(src), index as u16);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::BitFieldExtract { dst, src, pos, width } => {
+            let result = alu::bit_field_extract(cpu.regs.read(src), pos as u16, width as u16);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::BitFieldInsert { dst, src, pos, width } => {
+            let result = alu::bit_field_insert(cpu.regs.read(dst), cpu.regs.read(src), pos as u16, width as u16);
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::AddCarry { dst, src1, src2 } => {
+            let (result, carry) = alu::add_with_carry(cpu.regs.read(src1), cpu.regs.read(src2), cpu.flags.carry);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = carry;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::SubBorrow { dst, src1, src2 } => {
+            let (result, borrow) = alu::sub_with_borrow(cpu.regs.read(src1), cpu.regs.read(src2), cpu.flags.carry);
+            cpu.regs.write(dst, result);
+            cpu.flags.carry = borrow;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::MultWide { dst, src1, src2 } => {
+            let result = alu::mult_wide(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result as u16);
+            cpu.regs.write(dst.wrapping_add(1), (result >> 16) as u16);
+            cpu.flags.zero = result == 0;
+            cpu.flags.negative = (result as i32) < 0;
+        }
+
+        Instruction::MultWideSigned { dst, src1, src2 } => {
+            let result = alu::mult_wide_signed(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result as u16);
+            cpu.regs.write(dst.wrapping_add(1), (result >> 16) as u16);
+            cpu.flags.zero = result == 0;
+            cpu.flags.negative = (result as i32) < 0;
+        }
+
+        // Saturating operations report saturation in the overflow flag
+        Instruction::SatAdd { dst, src1, src2 } => {
+            let (result, saturated) = alu::saturating_add(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result);
+            cpu.flags.overflow = saturated;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::SatSub { dst, src1, src2 } => {
+            let (result, saturated) = alu::saturating_sub(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result);
+            cpu.flags.overflow = saturated;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Q15Mult { dst, src1, src2 } => {
+            let (result, saturated) = alu::q15_mul(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result);
+            cpu.flags.overflow = saturated;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::MultAcc { acc, src1, src2 } => {
+            let old = cpu.regs.read_pair(acc);
+            let (sum, saturated) = alu::mult_acc(old, cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(acc, sum as u16);
+            cpu.regs.write(acc.wrapping_add(1), (sum >> 16) as u16);
+            cpu.flags.overflow = saturated;
+            cpu.flags.zero = sum == 0;
+            cpu.flags.negative = (sum as i32) < 0;
+        }
+
+        Instruction::Abs { dst, src } => {
+            let (result, saturated) = alu::abs_saturating(cpu.regs.read(src));
+            cpu.regs.write(dst, result);
+            cpu.flags.overflow = saturated;
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Min { dst, src1, src2 } => {
+            let result = alu::min_signed(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result);
+            cpu.set_flags_from_result(result);
+        }
+
+        Instruction::Max { dst, src1, src2 } => {
+            let result = alu::max_signed(cpu.regs.read(src1), cpu.regs.read(src2));
+            cpu.regs.write(dst, result);
             cpu.set_flags_from_result(result);
         }
 
@@ -143,6 +515,10 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.pc = cpu.regs.read_10bit(reg);
         }
 
+        Instruction::JumpRel { offset } => {
+            cpu.pc = isa::relative_target(cpu.pc, offset);
+        }
+
         Instruction::BranchEqual { src1, src2, addr } => {
             let val1 = cpu.regs.read(src1);
             let val2 = cpu.regs.read_10bit(src2);
@@ -175,6 +551,62 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             }
         }
 
+        Instruction::BranchEqual32 { src1, src2, offset } => {
+            if cpu.regs.read_pair(src1) == cpu.regs.read_pair(src2) {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchNotEqual32 { src1, src2, offset } => {
+            if cpu.regs.read_pair(src1) != cpu.regs.read_pair(src2) {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchLessThan32 { src1, src2, offset } => {
+            if (cpu.regs.read_pair(src1) as i32) < (cpu.regs.read_pair(src2) as i32) {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchGreaterThan32 { src1, src2, offset } => {
+            if (cpu.regs.read_pair(src1) as i32) > (cpu.regs.read_pair(src2) as i32) {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchEqualRel { src1, src2, offset } => {
+            let val1 = cpu.regs.read(src1);
+            let val2 = cpu.regs.read_10bit(src2);
+            if val1 == val2 {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchNotEqualRel { src1, src2, offset } => {
+            let val1 = cpu.regs.read(src1);
+            let val2 = cpu.regs.read_10bit(src2);
+            if val1 != val2 {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchLessThanRel { src1, src2, offset } => {
+            let val1 = cpu.regs.read(src1) as i16;
+            let val2 = cpu.regs.read_10bit(src2) as i16;
+            if val1 < val2 {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
+        Instruction::BranchGreaterThanRel { src1, src2, offset } => {
+            let val1 = cpu.regs.read(src1) as i16;
+            let val2 = cpu.regs.read_10bit(src2) as i16;
+            if val1 > val2 {
+                cpu.pc = isa::relative_target(cpu.pc, offset);
+            }
+        }
+
         Instruction::Cmp { src1, src2 } => {
             let val1 = cpu.regs.read(src1);
             let val2 = cpu.regs.read_10bit(src2);
@@ -192,13 +624,90 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
 
         Instruction::Push { src } => {
             let value = cpu.regs.read_10bit(src);
-            // Implement stack push (requires stack pointer)
-            // For now, just decrement PC and store
+            push(cpu, mem, value);
         }
 
         Instruction::Pop { dst } => {
-            // Implement stack pop (requires stack pointer)
-            // For now, just load from memory
+            let value = pop(cpu, mem);
+            cpu.regs.write_10bit(dst, value);
+        }
+
+        Instruction::PushMultiple { first, last } => {
+            // Highest register first, so the range lies in ascending order in memory
+            for reg in (first..=last).rev() {
+                let value = cpu.regs.read(reg);
+                push(cpu, mem, value);
+            }
+        }
+
+        Instruction::PopMultiple { first, last } => {
+            for reg in first..=last {
+                let value = pop(cpu, mem);
+                cpu.regs.write(reg, value);
+            }
+        }
+
+        Instruction::BankSelect { bank } => {
+            cpu.regs.select_bank(bank);
+        }
+
+        Instruction::ReturnFromException => {
+            cpu.return_from_exception();
+        }
+
+        Instruction::SystemCall { code } => {
+            cpu.raise_exception(Exception::SystemCall(code));
+        }
+
+        Instruction::EnableInterrupts => {
+            cpu.interrupts_enabled = true;
+        }
+
+        Instruction::DisableInterrupts => {
+            cpu.interrupts_enabled = false;
+        }
+
+        Instruction::MoveToControl { cr, src } => {
+            let value = cpu.regs.read(src);
+            cpu.write_control(cr, value);
+        }
+
+        Instruction::MoveFromControl { dst, cr } => {
+            let value = cpu.read_control(cr);
+            cpu.regs.write(dst, value);
+        }
+
+        Instruction::FlushTlb => {
+            cpu.mmu.flush_tlb();
+        }
+
+        Instruction::FlushTlbPage { reg } => {
+            let addr = cpu.regs.read(reg);
+            cpu.mmu.flush_page(addr);
+        }
+
+        Instruction::FarJump { bank, target } => {
+            mem.select_code_bank(bank);
+            cpu.pc = cpu.regs.read(target);
+        }
+
+        Instruction::FarCall { bank, target } => {
+            let return_pc = cpu.pc.wrapping_add(4);
+            push(cpu, mem, mem.code_bank as u16);
+            push(cpu, mem, return_pc);
+            mem.select_code_bank(bank);
+            cpu.pc = cpu.regs.read(target);
+        }
+
+        Instruction::FarReturn => {
+            let return_pc = pop(cpu, mem);
+            let bank = pop(cpu, mem);
+            mem.select_code_bank(bank as u8);
+            cpu.pc = return_pc;
+        }
+
+        Instruction::SetDataBank { src } => {
+            mem.select_data_bank(cpu.regs.read(src) as u8);
         }
 
         Instruction::Nop => {
@@ -209,4 +718,67 @@ pub fn execute(instruction: Instruction, cpu: &mut CpuState, mem: &mut Memory) {
             cpu.halted = true;
         }
     }
+    stall
+}
+
+// Code bank a far transfer goes to (FarReturn's is on the stack, above the return pc)
+fn target_bank(instruction: &Instruction, cpu: &CpuState, mem: &Memory) -> Option<u8> {
+    match *instruction {
+        Instruction::FarJump { bank, .. } | Instruction::FarCall { bank, .. } => Some(bank % NUM_MEMORY_BANKS as u8),
+        Instruction::FarReturn => {
+            let sp = cpu.regs.read(isa::STACK_POINTER);
+            Some(cpu.mmu.read_u16(mem, sp.wrapping_add(2)) as u8 % NUM_MEMORY_BANKS as u8)
+        }
+        _ => None,
+    }
+}
+
+// Full descending stack: the stack pointer holds the (virtual) address of the last pushed word
+fn push(cpu: &mut CpuState, mem: &mut Memory, value: u16) {
+    let sp = cpu.regs.read(isa::STACK_POINTER).wrapping_sub(2);
+    cpu.mmu.write_u16(mem, sp, value);
+    cpu.regs.write(isa::STACK_POINTER, sp);
+}
+
+fn pop(cpu: &mut CpuState, mem: &Memory) -> u16 {
+    let sp = cpu.regs.read(isa::STACK_POINTER);
+    cpu.regs.write(isa::STACK_POINTER, sp.wrapping_add(2));
+    cpu.mmu.read_u16(mem, sp)
+}
+
+// Bytes touched by a load or store: (first address, length, kind of access)
+pub fn memory_access(instruction: &Instruction, cpu: &CpuState) -> Option<(u16, u16, Access)> {
+    let sp = cpu.regs.read(isa::STACK_POINTER);
+    match *instruction {
+        Instruction::Load { addr, .. } => Some((addr, 2, Access::Read)),
+        Instruction::Store { addr, .. } => Some((addr, 2, Access::Write)),
+        Instruction::LoadIndexed { base, offset, .. } => {
+            Some((isa::effective_address(cpu.regs.read(base), offset), 2, Access::Read))
+        }
+        Instruction::StoreIndexed { base, offset, .. } => {
+            Some((isa::effective_address(cpu.regs.read(base), offset), 2, Access::Write))
+        }
+        Instruction::LoadByte { base, offset, .. } |
+        Instruction::LoadByteSigned { base, offset, .. } => {
+            Some((isa::effective_address(cpu.regs.read(base), offset), 1, Access::Read))
+        }
+        Instruction::StoreByte { base, offset, .. } => {
+            Some((isa::effective_address(cpu.regs.read(base), offset), 1, Access::Write))
+        }
+        Instruction::LoadPostInc { base, .. } => Some((cpu.regs.read(base), 2, Access::Read)),
+        Instruction::StorePostInc { base, .. } => Some((cpu.regs.read(base), 2, Access::Write)),
+        Instruction::LoadBytePostInc { base, .. } |
+        Instruction::LoadByteSignedPostInc { base, .. } => Some((cpu.regs.read(base), 1, Access::Read)),
+        Instruction::StoreBytePostInc { base, .. } => Some((cpu.regs.read(base), 1, Access::Write)),
+        Instruction::Push { .. } => Some((sp.wrapping_sub(2), 2, Access::Write)),
+        Instruction::Pop { .. } => Some((sp, 2, Access::Read)),
+        Instruction::PushMultiple { first, last } => {
+            let len = 2 * ((last - first) as u16 + 1);
+            Some((sp.wrapping_sub(len), len, Access::Write))
+        }
+        Instruction::PopMultiple { first, last } => Some((sp, 2 * ((last - first) as u16 + 1), Access::Read)),
+        Instruction::FarCall { .. } => Some((sp.wrapping_sub(4), 4, Access::Write)),
+        Instruction::FarReturn => Some((sp, 4, Access::Read)),
+        _ => None,
+    }
 }
@@ -0,0 +1,259 @@
+use crate::core::{CpuState, Exception};
+use crate::core::mpu::Access;
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+// Classic five-stage in-order pipeline: IF, ID, EX, MEM, WB, one instruction per stage.
+// Instructions take effect (through execute::execute) as they enter EX, which happens in
+// program order, so the stages only decide timing:
+//   - ID holds an instruction until every source register can be read or forwarded
+//   - fetch predicts fall-through; an instruction that moves the pc in EX (taken
+//     branch, jump, trap) squashes the wrong-path instructions behind it
+//   - IF and MEM wait out TLB walks and cache misses, stalling everything behind them
+// Flags are produced and consumed in EX, so they never stall.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineConfig {
+    pub forward_ex: bool,          // EX/MEM latch back to the ALU inputs
+    pub forward_mem: bool,         // MEM/WB latch back to the ALU inputs (load results too)
+    pub split_register_file: bool, // WB writes in the first half cycle, ID reads in the second
+    pub branch_penalty: u32,       // Cycles lost to a redirect, at least 1; 2 resolves in EX
+}
+
+impl PipelineConfig {
+    pub fn new() -> Self {
+        Self {
+            forward_ex: true,
+            forward_mem: true,
+            split_register_file: true,
+            branch_penalty: 2,
+        }
+    }
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Slot {
+    pub instruction: Option<Instruction>, // None when the fetched word does not decode
+    pub pc: u16,
+    pub fault: Option<Exception>, // Fetch fault, taken when the slot reaches EX
+    pub sources: Vec<u16>,
+    pub dests: Vec<u16>,
+    pub is_load: bool,
+    pub stall: u32, // Cycles still to wait in IF (fetch) or MEM (data access)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PipelineStats {
+    pub instructions: u64,
+    pub data_stalls: u64,     // Cycles ID waited for an operand
+    pub load_use_stalls: u64, // ... of which on a load just ahead of it
+    pub fetch_stalls: u64,    // Cycles IF waited on the TLB or instruction cache
+    pub memory_stalls: u64,   // Cycles MEM waited on the TLB or data cache
+    pub branch_flushes: u64,
+    pub flushed: u64,         // Wrong-path instructions squashed
+}
+
+#[derive(Debug)]
+pub struct FiveStagePipeline {
+    pub config: PipelineConfig,
+    pub cycles: u64,
+    pub fetch: Option<Slot>,
+    pub decode: Option<Slot>,
+    pub execute: Option<Slot>,
+    pub memory: Option<Slot>,
+    pub writeback: Option<Slot>,
+    pub stats: PipelineStats,
+    fetch_stall: u32, // Fetch cycles left to lose after a redirect
+}
+
+impl FiveStagePipeline {
+    pub fn new(config: PipelineConfig) -> Self {
+        Self {
+            config,
+            cycles: 0,
+            fetch: None,
+            decode: None,
+            execute: None,
+            memory: None,
+            writeback: None,
+            stats: PipelineStats::default(),
+            fetch_stall: 0,
+        }
+    }
+
+    pub fn cpi(&self) -> f32 {
+        if self.stats.instructions == 0 { 0.0 } else { self.cycles as f32 / self.stats.instructions as f32 }
+    }
+
+    // One clock cycle. `cpu.pc` is the fetch address between cycles. Stages advance from
+    // the back so each one sees whether the one after it has room.
+    pub fn step(&mut self, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        cpu.tick_memory();
+        self.cycles += 1;
+
+        let retired = self.writeback.take();
+        if retired.is_some() {
+            self.stats.instructions += 1;
+        }
+
+        match self.memory.as_mut() {
+            Some(slot) if slot.stall > 0 => {
+                slot.stall -= 1;
+                self.stats.memory_stalls += 1;
+            }
+            _ => self.writeback = self.memory.take(),
+        }
+
+        if self.memory.is_none() {
+            self.memory = self.execute.take();
+        }
+
+        if self.execute.is_none() {
+            if let Some(slot) = self.decode.as_ref() {
+                match self.hazard(slot, retired.as_ref()) {
+                    Some(load_use) => {
+                        self.stats.data_stalls += 1;
+                        if load_use {
+                            self.stats.load_use_stalls += 1;
+                        }
+                    }
+                    None => {
+                        let slot = self.decode.take().unwrap();
+                        self.enter_execute(slot, cpu, mem);
+                        if cpu.halted {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+
+        match self.fetch.as_mut() {
+            Some(slot) if slot.stall > 0 => {
+                slot.stall -= 1;
+                self.stats.fetch_stalls += 1;
+            }
+            Some(_) if self.decode.is_none() => self.decode = self.fetch.take(),
+            _ => {}
+        }
+
+        // Nothing is fetched past a faulting fetch; the fault redirects fetch from EX
+        let faulted = self.decode.as_ref().is_some_and(|slot| slot.fault.is_some());
+        if self.fetch_stall > 0 {
+            self.fetch_stall -= 1;
+        } else if self.fetch.is_none() && !faulted {
+            self.fetch = Some(self.fetch_slot(cpu, mem));
+            cpu.pc = cpu.pc.wrapping_add(4);
+        }
+        true
+    }
+
+    // Whether `slot` must stay in ID this cycle, and if so whether it waits on a load.
+    // Producers are looked up youngest first, in the stage they moved to this cycle.
+    fn hazard(&self, slot: &Slot, retired: Option<&Slot>) -> Option<bool> {
+        let writes = |producer: &Option<Slot>, reg: &u16| producer.as_ref().is_some_and(|p| p.dests.contains(reg));
+        for reg in &slot.sources {
+            if writes(&self.memory, reg) {
+                // Result leaves EX at the end of this cycle; a load's only after MEM
+                let producer = self.memory.as_ref().unwrap();
+                if producer.is_load {
+                    return Some(true);
+                }
+                if !self.config.forward_ex {
+                    return Some(false);
+                }
+            } else if writes(&self.writeback, reg) {
+                if !self.config.forward_mem {
+                    return Some(false);
+                }
+            } else if retired.is_some_and(|p| p.dests.contains(reg)) && !self.config.split_register_file {
+                return Some(false);
+            }
+        }
+        None
+    }
+
+    fn fetch_slot(&self, cpu: &mut CpuState, mem: &Memory) -> Slot {
+        let pc = cpu.pc;
+        let mut slot = Slot {
+            instruction: None,
+            pc,
+            fault: None,
+            sources: Vec::new(),
+            dests: Vec::new(),
+            is_load: false,
+            stall: 0,
+        };
+        match cpu.check_access(mem, pc, 4, Access::Execute) {
+            Ok(walked) => {
+                if walked {
+                    slot.stall = cpu.mmu.walk_latency * 2;
+                }
+            }
+            Err(fault) => {
+                slot.fault = Some(fault);
+                return slot;
+            }
+        }
+        slot.stall += cpu.cache_stall(mem, pc, pc, 4, Access::Execute);
+
+        if let Some(instruction) = isa::decode(cpu.mmu.fetch(mem, pc)) {
+            slot.sources = isa::source_registers(&instruction);
+            slot.dests = isa::destination_registers(&instruction);
+            slot.is_load = loads_register(&instruction);
+            slot.instruction = Some(instruction);
+        }
+        slot
+    }
+
+    // Perform the instruction. The data access stall it reports is served in MEM. If the
+    // pc moved, everything fetched behind it is on the wrong path.
+    fn enter_execute(&mut self, mut slot: Slot, cpu: &mut CpuState, mem: &mut Memory) {
+        let fetch_pc = cpu.pc;
+        let bank = mem.code_bank;
+        cpu.pc = slot.pc;
+        slot.stall = 0;
+        match (slot.fault, slot.instruction) {
+            (Some(fault), _) => cpu.raise_exception(fault),
+            (None, Some(instruction)) => slot.stall = crate::core::execute::execute(instruction, cpu, mem),
+            (None, None) => cpu.halted = true,
+        }
+
+        if cpu.halted {
+            // The instructions ahead retire and the halt itself drains through MEM and WB
+            self.stats.instructions += 1 + self.memory.is_some() as u64 + self.writeback.is_some() as u64;
+            self.cycles += 2;
+            self.fetch = None;
+            self.decode = None;
+            self.memory = None;
+            self.writeback = None;
+            return;
+        }
+
+        // A far jump may land on the same address in another bank
+        if cpu.pc == slot.pc && mem.code_bank == bank {
+            cpu.pc = fetch_pc;
+        } else {
+            self.stats.branch_flushes += 1;
+            self.stats.flushed += self.fetch.take().is_some() as u64 + self.decode.take().is_some() as u64;
+            self.fetch_stall = self.config.branch_penalty.max(1) - 1;
+        }
+        self.execute = Some(slot);
+    }
+}
+
+// Instructions whose register results come from memory, available only after MEM
+fn loads_register(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Load { .. } | Instruction::LoadIndexed { .. } |
+        Instruction::LoadByte { .. } | Instruction::LoadByteSigned { .. } |
+        Instruction::LoadPostInc { .. } | Instruction::LoadBytePostInc { .. } | Instruction::LoadByteSignedPostInc { .. } |
+        Instruction::Pop { .. } | Instruction::PopMultiple { .. }
+    )
+}
@@ -0,0 +1,111 @@
+use crate::isa::Instruction;
+
+// Functional unit classes shared by the out-of-order engines (Tomasulo and the
+// scoreboard): which unit executes an instruction, how long it takes, and the issue
+// statistics both report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionalUnit {
+    Alu,
+    Mac, // Multiply and multiply-accumulate
+    Load,
+    Store,
+    Branch, // Jumps, branches and anything else that redirects fetch
+}
+
+pub const NUM_UNIT_KINDS: usize = 5;
+pub const UNIT_KINDS: [FunctionalUnit; NUM_UNIT_KINDS] = [
+    FunctionalUnit::Alu,
+    FunctionalUnit::Mac,
+    FunctionalUnit::Load,
+    FunctionalUnit::Store,
+    FunctionalUnit::Branch,
+];
+
+impl FunctionalUnit {
+    pub fn of(instruction: &Instruction) -> Self {
+        match instruction {
+            Instruction::Q15Mult { .. } | Instruction::MultAcc { .. } |
+            Instruction::MultWide { .. } | Instruction::MultWideSigned { .. } => FunctionalUnit::Mac,
+
+            Instruction::Load { .. } | Instruction::LoadIndexed { .. } |
+            Instruction::LoadByte { .. } | Instruction::LoadByteSigned { .. } |
+            Instruction::LoadPostInc { .. } | Instruction::LoadBytePostInc { .. } | Instruction::LoadByteSignedPostInc { .. } |
+            Instruction::Pop { .. } | Instruction::PopMultiple { .. } => FunctionalUnit::Load,
+
+            Instruction::Store { .. } | Instruction::StoreIndexed { .. } |
+            Instruction::StoreByte { .. } | Instruction::StorePostInc { .. } |
+            Instruction::StoreBytePostInc { .. } |
+            Instruction::Push { .. } | Instruction::PushMultiple { .. } => FunctionalUnit::Store,
+
+            Instruction::Jump { .. } | Instruction::JumpReg { .. } | Instruction::JumpRel { .. } |
+            Instruction::BranchEqual { .. } | Instruction::BranchNotEqual { .. } |
+            Instruction::BranchLessThan { .. } | Instruction::BranchGreaterThan { .. } |
+            Instruction::BranchEqual32 { .. } | Instruction::BranchNotEqual32 { .. } |
+            Instruction::BranchLessThan32 { .. } | Instruction::BranchGreaterThan32 { .. } |
+            Instruction::BranchEqualRel { .. } | Instruction::BranchNotEqualRel { .. } |
+            Instruction::BranchLessThanRel { .. } | Instruction::BranchGreaterThanRel { .. } |
+            Instruction::ReturnFromException | Instruction::SystemCall { .. } |
+            Instruction::FarJump { .. } | Instruction::FarCall { .. } | Instruction::FarReturn |
+            Instruction::Halt => FunctionalUnit::Branch,
+
+            _ => FunctionalUnit::Alu,
+        }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// Execution latency in cycles of each unit class
+#[derive(Debug, Clone, Copy)]
+pub struct UnitLatencies {
+    pub alu: u32,
+    pub mac: u32,
+    pub load: u32, // Address generation and a data cache hit; misses add to it
+    pub store: u32,
+    pub branch: u32,
+}
+
+impl UnitLatencies {
+    pub fn new() -> Self {
+        Self {
+            alu: 1,
+            mac: 3,
+            load: 2,
+            store: 1,
+            branch: 1,
+        }
+    }
+
+    pub fn latency(&self, instruction: &Instruction) -> u32 {
+        match FunctionalUnit::of(instruction) {
+            FunctionalUnit::Alu => self.alu,
+            FunctionalUnit::Mac => self.mac,
+            FunctionalUnit::Load => self.load,
+            FunctionalUnit::Store => self.store,
+            FunctionalUnit::Branch => self.branch,
+        }
+    }
+}
+
+impl Default for UnitLatencies {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UnitStats {
+    pub issued: [u64; NUM_UNIT_KINDS], // Per unit class
+    pub structural_stalls: u64, // Issue cycles lost to a busy unit (Tomasulo: no station or ROB entry)
+    pub raw_stalls: u64,        // Unit-cycles spent waiting for operands
+    pub war_stalls: u64,        // Unit-cycles a finished result waited for older readers
+    pub waw_stalls: u64,        // Issue cycles lost to a pending write of the same register
+}
+
+impl UnitStats {
+    pub fn record_issue(&mut self, instruction: &Instruction) {
+        self.issued[FunctionalUnit::of(instruction).index()] += 1;
+    }
+}
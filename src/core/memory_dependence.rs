@@ -0,0 +1,102 @@
+// When Tomasulo lets a load read memory while older stores are still in flight
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSpeculation {
+    Conservative, // Wait until every older store has committed
+    Blind,        // Go ahead of every older store whose address is not known yet
+    StoreSets,    // Go ahead unless the store set predictor names one of them
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemoryOrderStats {
+    pub speculative_loads: u64,     // Loads that read memory ahead of a store with an unknown address
+    pub forwarded: u64,             // Loads served from an older store still in the ROB
+    pub predicted_dependences: u64, // Loads told to wait for a store by the predictor
+    pub violations: u64,            // Loads that read memory before an overlapping older store
+    pub replayed: u64,              // ROB entries squashed to replay them
+}
+
+impl MemoryOrderStats {
+    // Fraction of speculative loads that had to be replayed
+    pub fn violation_rate(&self) -> f32 {
+        if self.speculative_loads == 0 { 0.0 } else { self.violations as f32 / self.speculative_loads as f32 }
+    }
+}
+
+// Store set memory dependence predictor (Chrysos and Emer). The store set identifier
+// table (SSIT), indexed by pc, gives the store set of loads and stores that have
+// conflicted before; the last fetched store table (LFST) holds the ROB entry of the most
+// recently issued store in each set. A load waits for the store its set names, and a
+// violation puts the store and the load in the same set, merging their sets if both
+// already have one.
+#[derive(Debug)]
+pub struct StoreSetPredictor {
+    pub ssit: Vec<Option<u16>>,
+    pub lfst: Vec<Option<usize>>,
+    next_set: u16,
+}
+
+impl StoreSetPredictor {
+    pub fn new(ssit_size: usize, store_sets: usize) -> Self {
+        Self {
+            ssit: vec![None; ssit_size.max(1)],
+            lfst: vec![None; store_sets.max(1)],
+            next_set: 0,
+        }
+    }
+
+    fn index(&self, pc: u16) -> usize {
+        (pc as usize >> 2) % self.ssit.len()
+    }
+
+    // ROB entry of the store a load at `pc` is predicted to depend on
+    pub fn dependence(&self, pc: u16) -> Option<usize> {
+        self.ssit[self.index(pc)].and_then(|set| self.lfst[set as usize])
+    }
+
+    pub fn store_issued(&mut self, pc: u16, tag: usize) {
+        if let Some(set) = self.ssit[self.index(pc)] {
+            self.lfst[set as usize] = Some(tag);
+        }
+    }
+
+    // Loads stop waiting for a store once it has its address
+    pub fn store_executed(&mut self, pc: u16, tag: usize) {
+        if let Some(set) = self.ssit[self.index(pc)] {
+            if self.lfst[set as usize] == Some(tag) {
+                self.lfst[set as usize] = None;
+            }
+        }
+    }
+
+    pub fn violation(&mut self, store_pc: u16, load_pc: u16) {
+        let (store, load) = (self.index(store_pc), self.index(load_pc));
+        let set = match (self.ssit[store], self.ssit[load]) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(set), None) | (None, Some(set)) => set,
+            (None, None) => {
+                let set = self.next_set;
+                self.next_set = (self.next_set + 1) % self.lfst.len() as u16;
+                set
+            }
+        };
+        self.ssit[store] = Some(set);
+        self.ssit[load] = Some(set);
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryDependence {
+    pub policy: LoadSpeculation,
+    pub store_sets: StoreSetPredictor,
+    pub stats: MemoryOrderStats,
+}
+
+impl MemoryDependence {
+    pub fn new(policy: LoadSpeculation) -> Self {
+        Self {
+            policy,
+            store_sets: StoreSetPredictor::new(64, 16),
+            stats: MemoryOrderStats::default(),
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::core::mpu::Access;
+use crate::core::PrivilegeMode;
+use crate::memory::Memory;
+
+// Paged virtual memory. A 16-bit virtual address splits into a 4-bit directory index,
+// a 4-bit table index and an 8-bit offset into a 256-byte page. Both levels live in
+// Memory as 16-entry tables of little endian u16 entries:
+//   directory entry: bits [15:5] address of a 32-byte aligned page table, bit 0 valid
+//   page table entry: bits [15:8] physical page, bit 4 user, bit 3 execute, bit 2 write,
+//                     bit 1 read, bit 0 valid
+// Supervisor mode may access every valid page, user mode only pages with the user bit.
+// The tables are read straight from physical memory (bank 0), whatever banks are selected.
+pub const PAGE_SIZE: u32 = 256;
+
+pub const PTE_VALID: u16 = 1 << 0;
+pub const PTE_READ: u16 = 1 << 1;
+pub const PTE_WRITE: u16 = 1 << 2;
+pub const PTE_EXECUTE: u16 = 1 << 3;
+pub const PTE_USER: u16 = 1 << 4;
+
+// Why a translation failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranslationFault {
+    NotMapped, // The directory or page table entry is invalid
+    Denied,    // The page is mapped but does not allow the access in this mode
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlbEntry {
+    pub valid: bool,
+    pub page: u8, // Virtual page number
+    pub pte: u16,
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TlbStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub page_faults: u64,
+}
+
+impl TlbStats {
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 { 0.0 } else { self.hits as f32 / lookups as f32 }
+    }
+}
+
+#[derive(Debug)]
+pub struct Mmu {
+    pub enabled: bool,
+    pub page_directory: u16,  // Physical address of the directory, 32-byte aligned
+    pub tlb: Vec<TlbEntry>,   // Fully associative, least recently used entry replaced
+    pub walk_latency: u32,    // Cycles per table level read by the walker
+    pub stats: TlbStats,
+    clock: u64,
+}
+
+impl Mmu {
+    pub fn new(tlb_entries: usize) -> Self {
+        Self {
+            enabled: false,
+            page_directory: 0,
+            tlb: vec![TlbEntry::default(); tlb_entries.max(1)],
+            walk_latency: 2,
+            stats: TlbStats::default(),
+            clock: 0,
+        }
+    }
+
+    pub fn flush_tlb(&mut self) {
+        self.tlb.iter_mut().for_each(|entry| entry.valid = false);
+    }
+
+    // Drop the translation of the page containing `vaddr`
+    pub fn flush_page(&mut self, vaddr: u16) {
+        let page = (vaddr >> 8) as u8;
+        self.tlb
+            .iter_mut()
+            .filter(|entry| entry.page == page)
+            .for_each(|entry| entry.valid = false);
+    }
+
+    // Translate for an access, counting TLB statistics and filling the TLB on a miss.
+    // Ok(true) when the walker had to run, otherwise why the access must page fault.
+    pub fn translate(&mut self, mem: &Memory, vaddr: u16, access: Access, mode: PrivilegeMode) -> Result<bool, TranslationFault> {
+        if !self.enabled {
+            return Ok(false);
+        }
+        self.clock += 1;
+        let page = (vaddr >> 8) as u8;
+
+        let (pte, walked) = match self.tlb.iter_mut().find(|entry| entry.valid && entry.page == page) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.stats.hits += 1;
+                (entry.pte, false)
+            }
+            None => {
+                self.stats.misses += 1;
+                let pte = self.walk(mem, vaddr);
+                if pte & PTE_VALID != 0 {
+                    self.fill(page, pte);
+                }
+                (pte, true)
+            }
+        };
+
+        match allows(pte, access, mode) {
+            Ok(()) => Ok(walked),
+            Err(fault) => {
+                self.stats.page_faults += 1;
+                Err(fault)
+            }
+        }
+    }
+
+    // Physical address of `vaddr` without counting statistics or filling the TLB
+    pub fn physical(&self, mem: &Memory, vaddr: u16) -> Result<u16, TranslationFault> {
+        if !self.enabled {
+            return Ok(vaddr);
+        }
+        let page = (vaddr >> 8) as u8;
+        let pte = match self.tlb.iter().find(|entry| entry.valid && entry.page == page) {
+            Some(entry) => entry.pte,
+            None => self.walk(mem, vaddr),
+        };
+        if pte & PTE_VALID != 0 {
+            Ok((pte & 0xFF00) | (vaddr & 0x00FF))
+        } else {
+            Err(TranslationFault::NotMapped)
+        }
+    }
+
+    // The accessors expect an access that translate() has already accepted; should the
+    // mapping have gone away since, reads return 0 and writes are dropped
+    pub fn read_u8(&self, mem: &Memory, vaddr: u16) -> u8 {
+        self.physical(mem, vaddr).map_or(0, |addr| mem.read(addr))
+    }
+
+    pub fn write_u8(&self, mem: &mut Memory, vaddr: u16, value: u8) {
+        if let Ok(addr) = self.physical(mem, vaddr) {
+            mem.write(addr, value);
+        }
+    }
+
+    // Word accesses translate each byte, so they may straddle a page boundary
+    pub fn read_u16(&self, mem: &Memory, vaddr: u16) -> u16 {
+        let low = self.read_u8(mem, vaddr);
+        let high = self.read_u8(mem, vaddr.wrapping_add(1));
+        (high as u16) << 8 | low as u16
+    }
+
+    pub fn write_u16(&self, mem: &mut Memory, vaddr: u16, value: u16) {
+        self.write_u8(mem, vaddr, value as u8);
+        self.write_u8(mem, vaddr.wrapping_add(1), (value >> 8) as u8);
+    }
+
+    pub fn fetch(&self, mem: &Memory, vaddr: u16) -> u32 {
+        self.physical(mem, vaddr).map_or(0, |addr| mem.fetch(addr))
+    }
+
+    // Two-level walk; returns the page table entry, invalid if either level is
+    fn walk(&self, mem: &Memory, vaddr: u16) -> u16 {
+        let dir_entry = read_table(mem, self.page_directory.wrapping_add((vaddr >> 12) * 2));
+        if dir_entry & PTE_VALID == 0 {
+            return 0;
+        }
+        let table = dir_entry & 0xFFE0;
+        read_table(mem, table.wrapping_add(((vaddr >> 8) & 0xF) * 2))
+    }
+
+    fn fill(&mut self, page: u8, pte: u16) {
+        let victim = match self.tlb.iter().position(|entry| !entry.valid) {
+            Some(i) => i,
+            None => self
+                .tlb
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(i, _)| i)
+                .unwrap_or(0),
+        };
+        self.tlb[victim] = TlbEntry { valid: true, page, pte, last_used: self.clock };
+    }
+}
+
+fn allows(pte: u16, access: Access, mode: PrivilegeMode) -> Result<(), TranslationFault> {
+    if pte & PTE_VALID == 0 {
+        return Err(TranslationFault::NotMapped);
+    }
+    let bit = match access {
+        Access::Read => PTE_READ,
+        Access::Write => PTE_WRITE,
+        Access::Execute => PTE_EXECUTE,
+    };
+    if (mode == PrivilegeMode::User && pte & PTE_USER == 0) || pte & bit == 0 {
+        return Err(TranslationFault::Denied);
+    }
+    Ok(())
+}
+
+// Little endian table entry at a physical address in bank 0
+fn read_table(mem: &Memory, addr: u16) -> u16 {
+    let low = mem.data[addr as usize];
+    let high = mem.data[addr.wrapping_add(1) as usize];
+    (high as u16) << 8 | low as u16
+}
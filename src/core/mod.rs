@@ -3,8 +3,20 @@ pub mod register_file;
 pub mod control_unit;
 pub mod execute;
 pub mod tomasulo;  // Add the new tomasulo module
+pub mod mpu;
+pub mod mmu;
+pub mod five_stage;
+pub mod functional_units;
+pub mod scoreboard;
+pub mod rename;
+pub mod memory_dependence;
+pub mod value_prediction;
+pub mod smt;
 
-use crate::isa::Instruction;
+use crate::memory::Memory;
+use crate::memory::cache::Cache;
+use crate::memory::hierarchy::MemoryHierarchy;
+use crate::memory::prefetch::Prefetcher;
 
 // Re-export the Tomasulo components for easier access
 pub use tomasulo::{
@@ -23,17 +35,74 @@ pub struct CpuState {
     pub halted: bool,
     pub flags: StatusFlags,
 
+    // Privilege state. Exception entry switches to supervisor mode and `exception_bank`
+    // with interrupts disabled; the interrupted pc and status are kept for
+    // ReturnFromException, which is also how the kernel drops to user mode.
+    pub mode: PrivilegeMode,
+    pub interrupts_enabled: bool,
+    pub exception_bank: u8,
+    pub vector_base: u16,
+    pub cause: u16,
+    pub fault_addr: u16,
+    pub saved_pc: u16,
+    pub saved_mode: PrivilegeMode,
+    pub saved_interrupts: bool,
+    pub saved_bank: u8,
+    // External interrupt request, taken before the next instruction once interrupts are
+    // enabled (by the in-order interpreter) and cleared when taken
+    pub interrupt_pending: bool,
+
+    pub mpu: mpu::Mpu,
+    pub mmu: mmu::Mmu,
+
+    // Optional first-level caches between the core and Memory (None = every access
+    // takes the base latency)
+    pub icache: Option<Cache>,
+    pub dcache: Option<Cache>,
+    // L2, DRAM and MSHRs behind them; when None a first-level miss simply takes the
+    // cache's own miss latency
+    pub hierarchy: Option<MemoryHierarchy>,
+    // Trains on data cache demand accesses and fills the data cache ahead of them
+    pub prefetcher: Option<Prefetcher>,
+
     // Tomasulo components
     pub reservation_stations: ReservationStationPool,
     pub reorder_buffer: ReorderBuffer,
     pub rename_table: RegisterRenameTable,
     pub common_data_bus: CommonDataBus,
+    // How Tomasulo renames registers, and the physical register file used by
+    // RenameScheme::PhysicalRegisters
+    pub rename_scheme: rename::RenameScheme,
+    pub physical_registers: rename::PhysicalRegisterFile,
+    // When loads may go ahead of older stores, and the store set predictor guiding them
+    pub memory_dependence: memory_dependence::MemoryDependence,
 
     // Pipeline controller
     pub pipeline: PipelineController,
 
-    // Execution mode flag
-    pub out_of_order_enabled: bool,
+    // Five-stage in-order pipeline
+    pub five_stage: five_stage::FiveStagePipeline,
+
+    // Scoreboard engine
+    pub scoreboard: scoreboard::Scoreboard,
+
+    // Hardware threads 1.. of the SMT mode, which runs thread 0 on the Tomasulo state above
+    pub smt: smt::SmtCore,
+
+    // Functional unit latencies and issue statistics shared by Tomasulo and the scoreboard
+    pub unit_latencies: functional_units::UnitLatencies,
+    pub unit_stats: functional_units::UnitStats,
+
+    pub execution_mode: ExecutionMode,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutionMode {
+    InOrder,    // One instruction per step, no timing model
+    Pipelined,  // Five-stage IF/ID/EX/MEM/WB pipeline
+    OutOfOrder, // Tomasulo's algorithm
+    Scoreboard, // CDC 6600 style scoreboard
+    Smt,        // Tomasulo shared by several hardware threads
 }
 
 #[derive(Debug)]
@@ -44,6 +113,52 @@ pub struct StatusFlags {
     pub overflow: bool,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivilegeMode {
+    User,
+    Supervisor,
+}
+
+// Synchronous exceptions. The handler for each lives at vector_base + 4 * cause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    SystemCall(u8),
+    PrivilegedInstruction,
+    ProtectionFault { addr: u16, access: mpu::Access },
+    PageFault { addr: u16, access: mpu::Access }, // addr is the virtual address
+}
+
+impl Exception {
+    pub fn cause(self) -> u16 {
+        match self {
+            Exception::SystemCall(_) => 0,
+            Exception::PrivilegedInstruction => 1,
+            Exception::ProtectionFault { .. } => 2,
+            Exception::PageFault { .. } => 3,
+        }
+    }
+}
+
+// Cause of an external interrupt; its handler follows the exception vectors
+pub const INTERRUPT_CAUSE: u16 = 4;
+
+// Control registers, accessed with MoveToControl / MoveFromControl in supervisor mode
+// (unknown registers read as 0 and ignore writes)
+pub const CR_STATUS: u8 = 0;         // bit 0 supervisor, bit 1 interrupts enabled, bits [3:2] register bank
+pub const CR_SAVED_STATUS: u8 = 1;   // status at exception entry, same layout
+pub const CR_SAVED_PC: u8 = 2;
+pub const CR_CAUSE: u8 = 3;          // bits [15:8] system call code, bits [7:0] exception cause
+pub const CR_VECTOR_BASE: u8 = 4;
+pub const CR_EXCEPTION_BANK: u8 = 5;
+pub const CR_FAULT_ADDR: u8 = 6;     // address of the last protection or page fault
+pub const CR_MPU_CONTROL: u8 = 7;    // bit 0 enable
+pub const CR_MPU_SELECT: u8 = 8;     // region accessed through the next three registers
+pub const CR_MPU_BASE: u8 = 9;
+pub const CR_MPU_SIZE: u8 = 10;      // in bytes, 0 = 64 KiB
+pub const CR_MPU_ATTRIBUTES: u8 = 11; // see mpu::Region::attributes
+pub const CR_MMU_CONTROL: u8 = 12;   // bit 0 enable
+pub const CR_PAGE_DIRECTORY: u8 = 13; // physical address of the page directory
+
 impl CpuState {
     pub fn new() -> Self {
         Self {
@@ -56,12 +171,38 @@ impl CpuState {
                 negative: false,
                 overflow: false,
             },
+            mode: PrivilegeMode::Supervisor, // Reset starts in the kernel
+            interrupts_enabled: false,
+            exception_bank: 1,
+            vector_base: 0xFFC0,
+            cause: 0,
+            fault_addr: 0,
+            saved_pc: 0,
+            saved_mode: PrivilegeMode::Supervisor,
+            saved_interrupts: false,
+            saved_bank: 0,
+            interrupt_pending: false,
+            mpu: mpu::Mpu::new(),
+            mmu: mmu::Mmu::new(8), // 8-entry TLB
+            icache: None,
+            dcache: None,
+            hierarchy: None,
+            prefetcher: None,
             reservation_stations: ReservationStationPool::new(),
             reorder_buffer: ReorderBuffer::new(16), // 16-entry ROB
-            rename_table: RegisterRenameTable::new(256), // 256 registers
+            rename_table: RegisterRenameTable::new(register_file::PHYSICAL_REGISTERS),
             common_data_bus: CommonDataBus::new(),
+            rename_scheme: rename::RenameScheme::RobTags,
+            // As many rename registers as ROB entries, four branch checkpoints
+            physical_registers: rename::PhysicalRegisterFile::new(register_file::PHYSICAL_REGISTERS, 16, 4),
+            memory_dependence: memory_dependence::MemoryDependence::new(memory_dependence::LoadSpeculation::Conservative),
             pipeline: PipelineController::new(),
-            out_of_order_enabled: false, // Start with in-order for compatibility
+            five_stage: five_stage::FiveStagePipeline::new(five_stage::PipelineConfig::new()),
+            scoreboard: scoreboard::Scoreboard::new(scoreboard::ScoreboardConfig::new()),
+            smt: smt::SmtCore::default(),
+            unit_latencies: functional_units::UnitLatencies::new(),
+            unit_stats: functional_units::UnitStats::default(),
+            execution_mode: ExecutionMode::InOrder, // Start with in-order for compatibility
         }
     }
 
@@ -70,13 +211,365 @@ impl CpuState {
         self.flags.negative = (result as i16) < 0;
     }
 
+    // Enter an exception or interrupt handler. `return_pc` is where
+    // ReturnFromException resumes.
+    pub fn enter_exception(&mut self, vector: u16, return_pc: u16) {
+        self.saved_pc = return_pc;
+        self.saved_mode = self.mode;
+        self.saved_interrupts = self.interrupts_enabled;
+        self.saved_bank = self.regs.bank;
+        self.mode = PrivilegeMode::Supervisor;
+        self.interrupts_enabled = false;
+        self.regs.select_bank(self.exception_bank);
+        self.pc = vector;
+    }
+
+    // Trap to the handler for `exception`. System calls resume after the
+    // instruction, faults re-execute it.
+    pub fn raise_exception(&mut self, exception: Exception) {
+        self.cause = match exception {
+            Exception::SystemCall(code) => (code as u16) << 8 | exception.cause(),
+            _ => exception.cause(),
+        };
+        let return_pc = match exception {
+            Exception::SystemCall(_) => self.pc.wrapping_add(4),
+            _ => self.pc,
+        };
+        if let Exception::ProtectionFault { addr, .. } | Exception::PageFault { addr, .. } = exception {
+            self.fault_addr = addr;
+        }
+        let vector = self.vector_base.wrapping_add(exception.cause() * 4);
+        self.enter_exception(vector, return_pc);
+    }
+
+    // Take a pending interrupt if interrupts are enabled. Nothing of the next instruction
+    // has run, so the handler returns to it.
+    pub fn take_interrupt(&mut self) -> bool {
+        if !self.interrupt_pending || !self.interrupts_enabled {
+            return false;
+        }
+        self.interrupt_pending = false;
+        self.cause = INTERRUPT_CAUSE;
+        self.enter_exception(self.vector_base.wrapping_add(INTERRUPT_CAUSE * 4), self.pc);
+        true
+    }
+
+    pub fn return_from_exception(&mut self) {
+        self.mode = self.saved_mode;
+        self.interrupts_enabled = self.saved_interrupts;
+        self.regs.select_bank(self.saved_bank);
+        self.pc = self.saved_pc;
+    }
+
+    pub fn is_supervisor(&self) -> bool {
+        self.mode == PrivilegeMode::Supervisor
+    }
+
+    // Translate `len` bytes at virtual address `addr` (one TLB lookup per page touched)
+    // and check the physical bytes against the MPU, in the current privilege mode.
+    // Ok(true) when a page table walk was needed, otherwise the fault to raise.
+    pub fn check_access(&mut self, mem: &Memory, addr: u16, len: u16, access: mpu::Access) -> Result<bool, Exception> {
+        let mut walked = false;
+        for i in 0..len {
+            let vaddr = addr.wrapping_add(i);
+            if i == 0 || (vaddr as u32).is_multiple_of(mmu::PAGE_SIZE) {
+                walked |= self
+                    .mmu
+                    .translate(mem, vaddr, access, self.mode)
+                    .map_err(|_| Exception::PageFault { addr: vaddr, access })?;
+            }
+            let paddr = self
+                .mmu
+                .physical(mem, vaddr)
+                .map_err(|_| Exception::PageFault { addr: vaddr, access })?;
+            self.mpu
+                .check(paddr, 1, access, self.mode)
+                .map_err(|addr| Exception::ProtectionFault { addr, access })?;
+        }
+        Ok(walked)
+    }
+
+    // Advance the memory system's notion of time by one cycle
+    pub fn tick_memory(&mut self) {
+        if let Some(hierarchy) = self.hierarchy.as_mut() {
+            hierarchy.tick();
+        }
+        if let Some(prefetcher) = self.prefetcher.as_mut() {
+            prefetcher.tick();
+        }
+    }
+
+    // Run an already translated access by the instruction at `pc` through the instruction
+    // or data cache, waiting for any miss to be filled. Returns the cycles it takes beyond
+    // the single cycle the pipelines assume for a memory access; an access straddling
+    // lines waits for the slowest of them. With a modelled hierarchy the fill is recorded so
+    // later accesses to the line wait for it: an instruction fill always, a data fill when
+    // an MSHR is free.
+    pub fn cache_stall(&mut self, mem: &Memory, pc: u16, addr: u16, len: u16, access: mpu::Access) -> u32 {
+        let mut latency = 0;
+        for far in self.lines_touched(mem, addr, len, access) {
+            let (cycles, filled) = self.line_access(far, pc, access);
+            let cache = match access {
+                mpu::Access::Execute => self.icache.as_ref(),
+                _ => self.dcache.as_ref(),
+            };
+            let line = cache.map_or(0, |cache| cache.line_address(far));
+            if let (true, Some(lower)) = (filled, self.hierarchy.as_mut()) {
+                if access == mpu::Access::Execute {
+                    lower.fill_fetch(line, cycles);
+                } else if lower.free_mshrs() > 0 {
+                    lower.allocate_mshr(line, cycles);
+                }
+            }
+            latency = latency.max(cycles);
+        }
+        latency.saturating_sub(1)
+    }
+
+    // Start a load in the non-blocking data cache. A miss holds an MSHR until its line
+    // arrives and later misses to that line wait on the same fill. None when the load
+    // would need more MSHRs than are free, in which case nothing has been touched. Without
+    // a modelled hierarchy the number of misses in flight is unbounded.
+    pub fn start_load(&mut self, mem: &Memory, pc: u16, addr: u16, len: u16) -> Option<u32> {
+        let lines = self.lines_touched(mem, addr, len, mpu::Access::Read);
+        let (Some(dcache), Some(lower)) = (self.dcache.as_ref(), self.hierarchy.as_mut()) else {
+            return Some(self.cache_stall(mem, pc, addr, len, mpu::Access::Read));
+        };
+
+        let new_misses = lines
+            .iter()
+            .filter(|&&far| lower.pending(dcache.line_address(far)).is_none() && !dcache.contains(far))
+            .count();
+        if new_misses > lower.free_mshrs() {
+            lower.stats.mshr_full_stalls += 1;
+            return None;
+        }
+
+        let mut latency = 0;
+        for far in lines {
+            let line = self.dcache.as_ref().map_or(0, |dcache| dcache.line_address(far));
+            let (cycles, filled) = self.line_access(far, pc, mpu::Access::Read);
+            if let (true, Some(lower)) = (filled, self.hierarchy.as_mut()) {
+                lower.allocate_mshr(line, cycles);
+            }
+            latency = latency.max(cycles);
+        }
+        Some(latency.saturating_sub(1))
+    }
+
+    // Far address of the first byte of each cache line an access touches (none when the
+    // access has no cache in front of it)
+    fn lines_touched(&self, mem: &Memory, addr: u16, len: u16, access: mpu::Access) -> Vec<u32> {
+        let cache = match access {
+            mpu::Access::Execute => self.icache.as_ref(),
+            _ => self.dcache.as_ref(),
+        };
+        let Some(cache) = cache else {
+            return Vec::new();
+        };
+
+        let mut lines: Vec<u32> = Vec::new();
+        for i in 0..len {
+            let Ok(paddr) = self.mmu.physical(mem, addr.wrapping_add(i)) else {
+                continue;
+            };
+            let far = mem.far_address(paddr);
+            if lines.last().is_none_or(|&last| cache.line_address(last) != cache.line_address(far)) {
+                lines.push(far);
+            }
+        }
+        lines
+    }
+
+    // One cache lookup, with the next level serving fills and taking writes. Data accesses
+    // also train the prefetcher. Returns the latency and whether a line had to be filled.
+    fn line_access(&mut self, far: u32, pc: u16, access: mpu::Access) -> (u32, bool) {
+        let cache = match access {
+            mpu::Access::Execute => &mut self.icache,
+            _ => &mut self.dcache,
+        };
+        let Some(cache) = cache.as_mut() else {
+            return (0, false);
+        };
+
+        let result = cache.lookup(far, access == mpu::Access::Write);
+        let (hit_latency, line_size) = (cache.config.hit_latency, cache.config.line_size as u32);
+        let mut latency = match self.hierarchy.as_mut() {
+            Some(lower) => {
+                if let Some(victim) = result.writeback {
+                    lower.write(victim);
+                }
+                if result.write_through {
+                    lower.write(far);
+                }
+                if result.filled { hit_latency + lower.read(far) } else { hit_latency }
+            }
+            None if result.hit => hit_latency,
+            None => cache.config.miss_latency,
+        };
+
+        // Tags are installed as a miss starts, so a lookup can hit a line whose fill is still
+        // in flight. It waits for that fill and counts as a miss. A prefetched line in flight
+        // is accounted for by the prefetcher below.
+        if let (true, false, Some(lower)) = (result.hit, result.prefetch_hit, self.hierarchy.as_mut()) {
+            let line = cache.line_address(far);
+            let pending = match access {
+                mpu::Access::Execute => lower.pending_fetch(line),
+                _ => lower.pending(line),
+            };
+            if let Some(ready_at) = pending {
+                lower.stats.mshr_merges += 1;
+                cache.count_as_miss(access == mpu::Access::Write);
+                latency = latency.max(ready_at.saturating_sub(lower.clock) as u32);
+            }
+        }
+
+        if access == mpu::Access::Execute {
+            return (latency, result.filled);
+        }
+        let Some(prefetcher) = self.prefetcher.as_mut() else {
+            return (latency, result.filled);
+        };
+        if result.evicted_prefetch {
+            prefetcher.stats.evicted_unused += 1;
+        }
+        if result.prefetch_hit {
+            latency += prefetcher.used(far / line_size);
+        } else if !result.hit {
+            prefetcher.stats.demand_misses += 1;
+        }
+        let targets = prefetcher.observe(far, line_size, pc, !result.hit || result.prefetch_hit);
+        for target in targets {
+            self.prefetch_line(target);
+        }
+        (latency, result.filled)
+    }
+
+    // Fill the data cache line holding `far` ahead of demand. With a modelled hierarchy the
+    // prefetch needs an MSHR like any other miss.
+    fn prefetch_line(&mut self, far: u32) {
+        let (Some(dcache), Some(prefetcher)) = (self.dcache.as_mut(), self.prefetcher.as_mut()) else {
+            return;
+        };
+        let line = dcache.line_address(far);
+        let pending = self.hierarchy.as_ref().is_some_and(|lower| lower.pending(line).is_some());
+        if pending || dcache.contains(far) {
+            prefetcher.stats.redundant += 1;
+            return;
+        }
+        if self.hierarchy.as_ref().is_some_and(|lower| lower.free_mshrs() == 0) {
+            prefetcher.stats.dropped += 1;
+            return;
+        }
+
+        let Some(result) = dcache.prefetch(far) else {
+            return;
+        };
+        if result.evicted_prefetch {
+            prefetcher.stats.evicted_unused += 1;
+        }
+        let latency = match self.hierarchy.as_mut() {
+            Some(lower) => {
+                if let Some(victim) = result.writeback {
+                    lower.write(victim);
+                }
+                let latency = lower.read(far);
+                lower.allocate_mshr(line, latency);
+                latency
+            }
+            None => dcache.config.miss_latency,
+        };
+        prefetcher.issued(line, latency);
+    }
+
+    pub fn read_control(&self, cr: u8) -> u16 {
+        match cr {
+            CR_STATUS => pack_status(self.mode, self.interrupts_enabled, self.regs.bank),
+            CR_SAVED_STATUS => pack_status(self.saved_mode, self.saved_interrupts, self.saved_bank),
+            CR_SAVED_PC => self.saved_pc,
+            CR_CAUSE => self.cause,
+            CR_VECTOR_BASE => self.vector_base,
+            CR_EXCEPTION_BANK => self.exception_bank as u16,
+            CR_FAULT_ADDR => self.fault_addr,
+            CR_MPU_CONTROL => self.mpu.enabled as u16,
+            CR_MPU_SELECT => self.mpu.selected as u16,
+            CR_MPU_BASE => self.mpu.regions[self.mpu.selected].base,
+            CR_MPU_SIZE => self.mpu.regions[self.mpu.selected].size as u16,
+            CR_MPU_ATTRIBUTES => self.mpu.regions[self.mpu.selected].attributes(),
+            CR_MMU_CONTROL => self.mmu.enabled as u16,
+            CR_PAGE_DIRECTORY => self.mmu.page_directory,
+            _ => 0,
+        }
+    }
+
+    pub fn write_control(&mut self, cr: u8, value: u16) {
+        let (mode, interrupts, bank) = unpack_status(value);
+        match cr {
+            CR_STATUS => {
+                self.mode = mode;
+                self.interrupts_enabled = interrupts;
+                self.regs.select_bank(bank);
+            }
+            CR_SAVED_STATUS => {
+                self.saved_mode = mode;
+                self.saved_interrupts = interrupts;
+                self.saved_bank = bank;
+            }
+            CR_SAVED_PC => self.saved_pc = value,
+            CR_CAUSE => self.cause = value,
+            CR_VECTOR_BASE => self.vector_base = value,
+            CR_EXCEPTION_BANK => self.exception_bank = value as u8 % register_file::NUM_BANKS as u8,
+            CR_FAULT_ADDR => self.fault_addr = value,
+            CR_MPU_CONTROL => self.mpu.enabled = value & 1 != 0,
+            CR_MPU_SELECT => self.mpu.selected = value as usize % mpu::NUM_REGIONS,
+            CR_MPU_BASE => self.mpu.regions[self.mpu.selected].base = value,
+            CR_MPU_SIZE => {
+                self.mpu.regions[self.mpu.selected].size = if value == 0 { 0x10000 } else { value as u32 };
+            }
+            CR_MPU_ATTRIBUTES => self.mpu.regions[self.mpu.selected].set_attributes(value),
+            CR_MMU_CONTROL => {
+                self.mmu.enabled = value & 1 != 0;
+                self.mmu.flush_tlb();
+            }
+            CR_PAGE_DIRECTORY => {
+                self.mmu.page_directory = value & 0xFFE0;
+                self.mmu.flush_tlb();
+            }
+            _ => {}
+        }
+    }
+
     // Method to enable out-of-order execution
     pub fn enable_out_of_order(&mut self) {
-        self.out_of_order_enabled = true;
+        self.execution_mode = ExecutionMode::OutOfOrder;
     }
 
     // Method to disable out-of-order execution (fallback to in-order)
     pub fn disable_out_of_order(&mut self) {
-        self.out_of_order_enabled = false;
+        self.execution_mode = ExecutionMode::InOrder;
+    }
+
+    pub fn set_execution_mode(&mut self, mode: ExecutionMode) {
+        self.execution_mode = mode;
+    }
+
+    // Cycles simulated by the current mode's pipeline (the plain in-order mode counts none)
+    pub fn cycles(&self) -> u64 {
+        match self.execution_mode {
+            ExecutionMode::InOrder => 0,
+            ExecutionMode::Pipelined => self.five_stage.cycles,
+            ExecutionMode::OutOfOrder => self.pipeline.cycles,
+            ExecutionMode::Scoreboard => self.scoreboard.cycles,
+            ExecutionMode::Smt => self.smt.cycles,
+        }
     }
-} 
\ No newline at end of file
+} 
+
+fn pack_status(mode: PrivilegeMode, interrupts: bool, bank: u8) -> u16 {
+    (mode == PrivilegeMode::Supervisor) as u16 | (interrupts as u16) << 1 | (bank as u16 & 0x3) << 2
+}
+
+fn unpack_status(value: u16) -> (PrivilegeMode, bool, u8) {
+    let mode = if value & 1 != 0 { PrivilegeMode::Supervisor } else { PrivilegeMode::User };
+    (mode, value & 0x2 != 0, ((value >> 2) & 0x3) as u8)
+}
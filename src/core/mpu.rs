@@ -0,0 +1,117 @@
+use crate::core::PrivilegeMode;
+
+// Memory protection unit. When enabled, every fetch, load and store is checked against
+// the regions; the highest-numbered enabled region containing a byte decides its
+// permissions. Bytes outside every region are accessible in supervisor mode only.
+pub const NUM_REGIONS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Permissions {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+impl Permissions {
+    // bit 0 read, bit 1 write, bit 2 execute
+    pub fn from_bits(bits: u16) -> Self {
+        Self {
+            read: bits & 0b001 != 0,
+            write: bits & 0b010 != 0,
+            execute: bits & 0b100 != 0,
+        }
+    }
+
+    pub fn to_bits(self) -> u16 {
+        self.read as u16 | (self.write as u16) << 1 | (self.execute as u16) << 2
+    }
+
+    pub fn allows(self, access: Access) -> bool {
+        match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Region {
+    pub enabled: bool,
+    pub base: u16,
+    pub size: u32, // In bytes, up to the full 64 KiB
+    pub user: Permissions,
+    pub supervisor: Permissions,
+}
+
+impl Region {
+    pub fn contains(&self, addr: u16) -> bool {
+        let addr = addr as u32;
+        self.enabled && addr >= self.base as u32 && addr < self.base as u32 + self.size
+    }
+
+    // Attribute register layout: bits [2:0] user rwx, bits [6:4] supervisor rwx, bit 15 enable
+    pub fn attributes(&self) -> u16 {
+        self.user.to_bits() | self.supervisor.to_bits() << 4 | (self.enabled as u16) << 15
+    }
+
+    pub fn set_attributes(&mut self, value: u16) {
+        self.user = Permissions::from_bits(value & 0x7);
+        self.supervisor = Permissions::from_bits((value >> 4) & 0x7);
+        self.enabled = value & 0x8000 != 0;
+    }
+}
+
+#[derive(Debug)]
+pub struct Mpu {
+    pub enabled: bool,
+    pub regions: [Region; NUM_REGIONS],
+    pub selected: usize, // Region addressed by the MPU control registers
+}
+
+impl Mpu {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            regions: [Region::default(); NUM_REGIONS],
+            selected: 0,
+        }
+    }
+
+    // Check `len` bytes starting at `addr`. Err holds the first byte that may not be accessed.
+    pub fn check(&self, addr: u16, len: u16, access: Access, mode: PrivilegeMode) -> Result<(), u16> {
+        if !self.enabled {
+            return Ok(());
+        }
+        for i in 0..len {
+            let byte = addr.wrapping_add(i);
+            if !self.allows(byte, access, mode) {
+                return Err(byte);
+            }
+        }
+        Ok(())
+    }
+
+    fn allows(&self, addr: u16, access: Access, mode: PrivilegeMode) -> bool {
+        match self.regions.iter().rev().find(|region| region.contains(addr)) {
+            Some(region) => match mode {
+                PrivilegeMode::User => region.user.allows(access),
+                PrivilegeMode::Supervisor => region.supervisor.allows(access),
+            },
+            None => mode == PrivilegeMode::Supervisor,
+        }
+    }
+}
+
+impl Default for Mpu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
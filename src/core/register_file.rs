@@ -1,38 +1,108 @@
+use std::fmt::Write;
+
+// Registers 0-255 are reachable from every 8-bit register field. Registers 256-1023
+// form the extended bank, reachable only through 10-bit register fields
+// (Move/MoveWide, Push/Pop, JumpReg and the register form of src2).
+pub const NUM_REGISTERS: usize = 1024;
+
+// Registers 0-15 are banked: each bank has its own copy, selected by `bank`.
+// Bank 0 uses the copy inside the main register space, banks 1.. are stored
+// after it, so physical indices are unique across banks.
+pub const NUM_BANKS: usize = 4;
+pub const BANKED_REGISTERS: usize = 16;
+pub const PHYSICAL_REGISTERS: usize = NUM_REGISTERS + (NUM_BANKS - 1) * BANKED_REGISTERS;
+
+#[derive(Debug)]
 pub struct RegisterFile {
-    pub regs: [u16; 256], // Support up to 256 registers for 8-bit addressing
+    pub regs: [u16; PHYSICAL_REGISTERS],
+    pub bank: u8,
 }
 
 impl RegisterFile {
     pub fn new() -> Self {
-        Self { regs: [0; 256] }
+        Self { regs: [0; PHYSICAL_REGISTERS], bank: 0 }
+    }
+
+    // Physical index of an architectural register in the current bank
+    pub fn physical(&self, idx: u16) -> u16 {
+        banked_physical(self.bank, idx)
+    }
+
+    pub fn select_bank(&mut self, bank: u8) {
+        self.bank = bank % NUM_BANKS as u8;
     }
 
     pub fn read(&self, idx: u8) -> u16 {
-        self.regs[idx as usize]
+        self.regs[self.physical(idx as u16) as usize]
     }
 
     pub fn read_10bit(&self, idx: u16) -> u16 {
-        if idx < 1024 {
-            // For 10-bit addressing, extend register file or map to memory
-            if idx < 256 {
-                self.regs[idx as usize]
-            } else {
-                // self.memory.read(idx (- 256))
-                0
-            }
+        if (idx as usize) < NUM_REGISTERS {
+            self.regs[self.physical(idx) as usize]
         } else {
             0
         }
     }
 
+    pub fn read_physical(&self, idx: u16) -> u16 {
+        self.regs.get(idx as usize).copied().unwrap_or(0)
+    }
+
+    // Register pair {idx+1:idx} as a 32-bit value
+    pub fn read_pair(&self, idx: u8) -> u32 {
+        (self.read(idx.wrapping_add(1)) as u32) << 16 | self.read(idx) as u32
+    }
+
     pub fn write(&mut self, idx: u8, value: u16) {
+        let idx = self.physical(idx as u16);
         self.regs[idx as usize] = value;
     }
 
     pub fn write_10bit(&mut self, idx: u16, value: u16) {
-        if idx < 256 {
+        if (idx as usize) < NUM_REGISTERS {
+            let idx = self.physical(idx);
             self.regs[idx as usize] = value;
         }
-        // For idx >= 256, could map to memory or extended registers
+    }
+
+    pub fn write_physical(&mut self, idx: u16, value: u16) {
+        if let Some(reg) = self.regs.get_mut(idx as usize) {
+            *reg = value;
+        }
+    }
+
+    // Contents for the state dump: r0-r15 of every bank, the selected one first, then
+    // r16-r1023 in rows of eight, leaving out rows that are all zero
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        let row = |out: &mut String, first: usize, values: &mut dyn Iterator<Item = u16>| {
+            let values: Vec<String> = values.map(|value| format!("{:04X}", value)).collect();
+            let _ = writeln!(out, "  {:<12} {}", format!("r{}-r{}:", first, first + 7), values.join(" "));
+        };
+        let banks = std::iter::once(self.bank).chain((0..NUM_BANKS as u8).filter(|&bank| bank != self.bank));
+        for bank in banks {
+            let selected = if bank == self.bank { " (selected)" } else { "" };
+            let _ = writeln!(out, "Register bank {}{}:", bank, selected);
+            for first in [0, 8] {
+                row(&mut out, first, &mut (first..first + 8).map(|idx| self.regs[banked_physical(bank, idx as u16) as usize]));
+            }
+        }
+        let _ = writeln!(out, "Registers r16-r{} (rows of zeros left out):", NUM_REGISTERS - 1);
+        for first in (BANKED_REGISTERS..NUM_REGISTERS).step_by(8) {
+            let values = &self.regs[first..first + 8];
+            if values.iter().any(|&value| value != 0) {
+                row(&mut out, first, &mut values.iter().copied());
+            }
+        }
+        out
+    }
+}
+
+// Physical index of an architectural register as seen from `bank`
+fn banked_physical(bank: u8, idx: u16) -> u16 {
+    if (idx as usize) < BANKED_REGISTERS && bank != 0 {
+        (NUM_REGISTERS + (bank as usize - 1) * BANKED_REGISTERS) as u16 + idx
+    } else {
+        idx
     }
 }
@@ -0,0 +1,233 @@
+use crate::core::register_file::RegisterFile;
+
+// How Tomasulo's issue stage renames destination registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameScheme {
+    RobTags,           // Map registers to the ROB entry producing them; results live in the ROB
+    PhysicalRegisters, // R10K style: map registers onto a unified physical register file
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PhysicalRegister {
+    pub value: u16,
+    pub ready: bool,
+    pub producer: Option<usize>, // ROB entry that will write it while not ready
+}
+
+// Free list as a circular buffer: allocation takes from the head and commit returns
+// registers at the tail, so restoring a checkpoint only has to move the head back
+#[derive(Debug, Clone)]
+pub struct FreeList {
+    pub slots: Vec<u16>,
+    pub head: usize,
+    pub count: usize,
+}
+
+impl FreeList {
+    fn new(capacity: usize) -> Self {
+        Self { slots: vec![0; capacity.max(1)], head: 0, count: 0 }
+    }
+
+    fn pop(&mut self) -> Option<u16> {
+        if self.count == 0 {
+            return None;
+        }
+        let reg = self.slots[self.head];
+        self.head = (self.head + 1) % self.slots.len();
+        self.count -= 1;
+        Some(reg)
+    }
+
+    fn push(&mut self, reg: u16) {
+        let tail = (self.head + self.count) % self.slots.len();
+        self.slots[tail] = reg;
+        self.count += 1;
+    }
+
+    // Give back every register allocated since the head was at `head`
+    fn rewind(&mut self, head: usize) {
+        let len = self.slots.len();
+        self.count += (self.head + len - head) % len;
+        self.head = head;
+    }
+}
+
+// Speculative map table and free list head as they were when a branch renamed
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub tag: usize, // ROB entry of the branch
+    pub map: Vec<u16>,
+    pub free_head: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenameStats {
+    pub allocations: u64,
+    pub free_list_stalls: u64,  // Issue attempts with too few free registers
+    pub checkpoints: u64,
+    pub checkpoint_stalls: u64, // Speculative branches held back with every checkpoint in use
+    pub restores: u64,          // Mispredictions recovered from a checkpoint
+    pub unwound: u64,           // Renames undone walking the ROB back (squashes without a checkpoint)
+    pub peak_in_flight: usize,  // Most registers allocated and not yet committed
+}
+
+// Unified physical register file in the style of the MIPS R10000. Every renamed result
+// gets a fresh physical register from the free list; the speculative map table follows
+// issue and the architectural map table follows commit. Committing an instruction frees
+// the register its destination was mapped to before, and a misprediction restores the
+// speculative map and free list from the checkpoint taken at the branch. Squashes that
+// do not start at a branch walk the ROB back from the youngest entry instead.
+//
+// Registers are indexed as in RegisterFile::physical, so banked copies rename
+// independently. The architectural register file itself is still written at commit,
+// because the in-order fallback path and exception entry work on it; `sync` picks up
+// their changes whenever nothing is in flight.
+#[derive(Debug)]
+pub struct PhysicalRegisterFile {
+    pub registers: Vec<PhysicalRegister>,
+    pub speculative_map: Vec<u16>,
+    pub architectural_map: Vec<u16>,
+    pub free_list: FreeList,
+    pub checkpoints: Vec<Checkpoint>, // Oldest branch first
+    pub max_checkpoints: usize,
+    pub stats: RenameStats,
+}
+
+impl PhysicalRegisterFile {
+    // `rename_registers` physical registers on top of one per architectural register
+    pub fn new(architectural: usize, rename_registers: usize, max_checkpoints: usize) -> Self {
+        let total = architectural + rename_registers;
+        let mut prf = Self {
+            registers: vec![PhysicalRegister { value: 0, ready: true, producer: None }; total],
+            speculative_map: (0..architectural as u16).collect(),
+            architectural_map: (0..architectural as u16).collect(),
+            free_list: FreeList::new(total),
+            checkpoints: Vec::new(),
+            max_checkpoints,
+            stats: RenameStats::default(),
+        };
+        prf.reset();
+        prf
+    }
+
+    pub fn free_registers(&self) -> usize {
+        self.free_list.count
+    }
+
+    // Registers holding results not yet committed
+    pub fn in_flight(&self) -> usize {
+        self.registers.len() - self.architectural_map.len() - self.free_list.count
+    }
+
+    // Current mapping of an architectural register: (value, None) when it has been
+    // written, otherwise (None, ROB entry that will write it)
+    pub fn lookup(&self, reg: u16) -> (Option<u16>, Option<usize>) {
+        match self.speculative_map.get(reg as usize) {
+            Some(&preg) => {
+                let register = &self.registers[preg as usize];
+                if register.ready { (Some(register.value), None) } else { (None, register.producer) }
+            }
+            None => (Some(0), None),
+        }
+    }
+
+    pub fn can_allocate(&self, count: usize) -> bool {
+        self.free_list.count >= count
+    }
+
+    // Map `reg` to a fresh register written by ROB entry `tag`; returns the new register
+    // and the one it replaces, freed when the instruction commits
+    pub fn rename(&mut self, reg: u16, tag: usize) -> Option<(u16, u16)> {
+        let old = *self.speculative_map.get(reg as usize)?;
+        let new = self.free_list.pop()?;
+        self.registers[new as usize] = PhysicalRegister { value: 0, ready: false, producer: Some(tag) };
+        self.speculative_map[reg as usize] = new;
+        self.stats.allocations += 1;
+        self.stats.peak_in_flight = self.stats.peak_in_flight.max(self.in_flight());
+        Some((new, old))
+    }
+
+    pub fn write(&mut self, preg: u16, value: u16) {
+        if let Some(register) = self.registers.get_mut(preg as usize) {
+            *register = PhysicalRegister { value, ready: true, producer: None };
+        }
+    }
+
+    pub fn commit(&mut self, reg: u16, new: u16, old: u16) {
+        if let Some(mapping) = self.architectural_map.get_mut(reg as usize) {
+            *mapping = new;
+            self.free_list.push(old);
+        }
+    }
+
+    pub fn can_checkpoint(&self) -> bool {
+        self.checkpoints.len() < self.max_checkpoints
+    }
+
+    pub fn checkpoint(&mut self, tag: usize) {
+        self.checkpoints.push(Checkpoint {
+            tag,
+            map: self.speculative_map.clone(),
+            free_head: self.free_list.head,
+        });
+        self.stats.checkpoints += 1;
+    }
+
+    // The branch was predicted correctly: its checkpoint is no longer needed
+    pub fn release(&mut self, tag: usize) {
+        self.checkpoints.retain(|checkpoint| checkpoint.tag != tag);
+    }
+
+    // The branch was mispredicted: undo every rename after it, dropping the checkpoints
+    // of the younger branches along the way. Returns false without a checkpoint for `tag`.
+    pub fn restore(&mut self, tag: usize) -> bool {
+        let Some(index) = self.checkpoints.iter().position(|checkpoint| checkpoint.tag == tag) else {
+            return false;
+        };
+        let checkpoint = self.checkpoints.remove(index);
+        self.checkpoints.truncate(index);
+        self.speculative_map = checkpoint.map;
+        self.free_list.rewind(checkpoint.free_head);
+        self.stats.restores += 1;
+        true
+    }
+
+    // Undo the youngest rename still in flight. Renames happen in program order, so
+    // walking squashed instructions youngest first hands each register back to the head
+    // of the free list it was taken from.
+    pub fn unwind(&mut self, reg: u16, new: u16, previous: u16) {
+        if let Some(mapping) = self.speculative_map.get_mut(reg as usize) {
+            *mapping = previous;
+        }
+        let len = self.free_list.slots.len();
+        self.free_list.rewind((self.free_list.head + len - 1) % len);
+        self.free_list.slots[self.free_list.head] = new;
+        self.stats.unwound += 1;
+    }
+
+    // Drop everything in flight (exception or full flush): back to the committed mapping
+    pub fn reset(&mut self) {
+        self.speculative_map.clone_from(&self.architectural_map);
+        self.checkpoints.clear();
+
+        let mut mapped = vec![false; self.registers.len()];
+        for &preg in &self.architectural_map {
+            mapped[preg as usize] = true;
+        }
+        self.free_list = FreeList::new(self.registers.len());
+        for (preg, register) in self.registers.iter_mut().enumerate() {
+            register.ready = true;
+            register.producer = None;
+            if !mapped[preg] {
+                self.free_list.push(preg as u16);
+            }
+        }
+    }
+
+    // Copy the architectural register file into the committed physical registers
+    pub fn sync(&mut self, regs: &RegisterFile) {
+        for (reg, &preg) in self.architectural_map.iter().enumerate() {
+            self.registers[preg as usize].value = regs.read_physical(reg as u16);
+        }
+    }
+}
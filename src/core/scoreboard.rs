@@ -0,0 +1,281 @@
+use crate::core::{CpuState, Exception};
+use crate::core::functional_units::{FunctionalUnit, NUM_UNIT_KINDS, UNIT_KINDS};
+use crate::core::mpu::Access;
+use crate::core::register_file::NUM_REGISTERS;
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+// CDC 6600 style scoreboard. Instructions issue in order to a free functional unit, read
+// their operands once no unit still has to write them (RAW), execute for the unit's
+// latency and write their result once no older instruction still has to read the old
+// value (WAR). There is no renaming, so an instruction whose destination already has a
+// write pending does not issue (WAW). As in the five-stage pipeline, instructions take
+// effect at issue, in program order, and the scoreboard decides the timing. Fetch does
+// not speculate: it waits for each branch to complete.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScoreboardConfig {
+    pub units: [usize; NUM_UNIT_KINDS], // Units of each class, indexed by FunctionalUnit::index
+}
+
+impl ScoreboardConfig {
+    pub fn new() -> Self {
+        Self { units: [2, 1, 1, 1, 1] } // Two ALUs, one of everything else
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitStage {
+    Idle,
+    ReadOperands, // Issued, waiting for its sources
+    Execute,
+    WriteResult,
+}
+
+// One row of the functional unit status table
+#[derive(Debug, Clone)]
+pub struct UnitStatus {
+    pub kind: FunctionalUnit,
+    pub stage: UnitStage,
+    pub instruction: Option<Instruction>,
+    pub pc: u16,
+    pub dests: Vec<u16>,               // Fi
+    pub sources: Vec<u16>,             // Fj, Fk, ...
+    pub producers: Vec<Option<usize>>, // Qj, Qk, ...: unit that will write each source
+    pub cycles_remaining: u32,
+}
+
+impl UnitStatus {
+    pub fn new(kind: FunctionalUnit) -> Self {
+        Self {
+            kind,
+            stage: UnitStage::Idle,
+            instruction: None,
+            pc: 0,
+            dests: Vec::new(),
+            sources: Vec::new(),
+            producers: Vec::new(),
+            cycles_remaining: 0,
+        }
+    }
+
+    pub fn busy(&self) -> bool {
+        self.stage != UnitStage::Idle
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fetched {
+    pub instruction: Option<Instruction>, // None when the fetched word does not decode
+    pub pc: u16,
+    pub fault: Option<Exception>,
+}
+
+#[derive(Debug, Default)]
+pub struct Scoreboard {
+    pub units: Vec<UnitStatus>,
+    pub register_status: Vec<Option<usize>>, // Unit that will write each register
+    pub cycles: u64,
+    pub instructions: u64,
+    pub next: Option<Fetched>,         // Fetched, waiting to issue
+    pub branch_pending: Option<usize>, // Unit holding the branch fetch waits for
+    fetch_stall: u32,
+}
+
+impl Scoreboard {
+    pub fn new(config: ScoreboardConfig) -> Self {
+        let units = UNIT_KINDS
+            .iter()
+            .flat_map(|&kind| std::iter::repeat_n(kind, config.units[kind.index()].max(1)))
+            .map(UnitStatus::new)
+            .collect();
+        Self {
+            units,
+            register_status: vec![None; NUM_REGISTERS],
+            cycles: 0,
+            instructions: 0,
+            next: None,
+            branch_pending: None,
+            fetch_stall: 0,
+        }
+    }
+
+    pub fn cpi(&self) -> f32 {
+        if self.instructions == 0 { 0.0 } else { self.cycles as f32 / self.instructions as f32 }
+    }
+
+    pub fn step(&mut self, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        cpu.tick_memory();
+        self.cycles += 1;
+
+        // Stages in reverse order so an instruction moves through one stage per cycle
+        self.write_results(cpu);
+        self.execute();
+        self.read_operands(cpu);
+        self.issue(cpu, mem);
+
+        if cpu.halted {
+            // Let everything issued before the halt finish
+            while self.units.iter().any(UnitStatus::busy) {
+                self.cycles += 1;
+                self.write_results(cpu);
+                self.execute();
+                self.read_operands(cpu);
+            }
+            return false;
+        }
+
+        self.fetch(cpu, mem);
+        true
+    }
+
+    fn write_results(&mut self, cpu: &mut CpuState) {
+        for unit in 0..self.units.len() {
+            if self.units[unit].stage != UnitStage::WriteResult {
+                continue;
+            }
+            if self.older_reader_pending(unit) {
+                cpu.unit_stats.war_stalls += 1;
+                continue;
+            }
+
+            for &reg in &self.units[unit].dests {
+                if self.register_status[reg as usize] == Some(unit) {
+                    self.register_status[reg as usize] = None;
+                }
+            }
+            for other in self.units.iter_mut() {
+                other.producers.iter_mut().filter(|producer| **producer == Some(unit)).for_each(|producer| *producer = None);
+            }
+            if self.branch_pending == Some(unit) {
+                self.branch_pending = None;
+            }
+            let kind = self.units[unit].kind;
+            self.units[unit] = UnitStatus::new(kind);
+            self.instructions += 1;
+        }
+    }
+
+    // Whether a unit that has not read its operands yet still needs the old value of one
+    // of `unit`'s destinations (its operand is ready, so it is not waiting on `unit`)
+    fn older_reader_pending(&self, unit: usize) -> bool {
+        let dests = &self.units[unit].dests;
+        self.units.iter().enumerate().any(|(other, status)| {
+            other != unit &&
+                status.stage == UnitStage::ReadOperands &&
+                status.sources.iter().zip(&status.producers).any(|(reg, producer)| producer.is_none() && dests.contains(reg))
+        })
+    }
+
+    fn execute(&mut self) {
+        for unit in self.units.iter_mut().filter(|unit| unit.stage == UnitStage::Execute) {
+            unit.cycles_remaining = unit.cycles_remaining.saturating_sub(1);
+            if unit.cycles_remaining == 0 {
+                unit.stage = UnitStage::WriteResult;
+            }
+        }
+    }
+
+    fn read_operands(&mut self, cpu: &mut CpuState) {
+        for unit in self.units.iter_mut().filter(|unit| unit.stage == UnitStage::ReadOperands) {
+            if unit.producers.iter().all(Option::is_none) {
+                unit.stage = UnitStage::Execute;
+            } else {
+                cpu.unit_stats.raw_stalls += 1;
+            }
+        }
+    }
+
+    fn issue(&mut self, cpu: &mut CpuState, mem: &mut Memory) {
+        // An instruction that missed in the instruction cache arrives once the fill is done
+        if self.fetch_stall > 0 {
+            return;
+        }
+        let Some(fetched) = self.next else {
+            return;
+        };
+        let instruction = match (fetched.fault, fetched.instruction) {
+            (Some(fault), _) => {
+                // Everything older has already taken effect
+                self.next = None;
+                cpu.pc = fetched.pc;
+                cpu.raise_exception(fault);
+                return;
+            }
+            (None, None) => {
+                self.next = None;
+                cpu.halted = true;
+                return;
+            }
+            (None, Some(instruction)) => instruction,
+        };
+
+        let kind = FunctionalUnit::of(&instruction);
+        let Some(unit) = self.units.iter().position(|unit| unit.kind == kind && !unit.busy()) else {
+            cpu.unit_stats.structural_stalls += 1;
+            return;
+        };
+        let dests = isa::destination_registers(&instruction);
+        if dests.iter().any(|&reg| self.register_status[reg as usize].is_some()) {
+            cpu.unit_stats.waw_stalls += 1;
+            return;
+        }
+        self.next = None;
+
+        let fetch_pc = cpu.pc;
+        let bank = mem.code_bank;
+        cpu.pc = fetched.pc;
+        let memory_stall = crate::core::execute::execute(instruction, cpu, mem);
+        // A far jump may land on the same address in another bank
+        let redirected = cpu.pc != fetched.pc || mem.code_bank != bank;
+        if !redirected {
+            cpu.pc = fetch_pc;
+        }
+
+        let sources = isa::source_registers(&instruction);
+        let producers = sources.iter().map(|&reg| self.register_status[reg as usize]).collect();
+        for &reg in &dests {
+            self.register_status[reg as usize] = Some(unit);
+        }
+        self.units[unit] = UnitStatus {
+            kind,
+            stage: UnitStage::ReadOperands,
+            instruction: Some(instruction),
+            pc: fetched.pc,
+            dests,
+            sources,
+            producers,
+            cycles_remaining: cpu.unit_latencies.latency(&instruction) + memory_stall,
+        };
+        if kind == FunctionalUnit::Branch || redirected {
+            self.branch_pending = Some(unit);
+        }
+        cpu.unit_stats.record_issue(&instruction);
+    }
+
+    // An instruction cache miss or page table walk holds the instruction back from issue,
+    // and the following fetch, for the rest of the latency, as in the Tomasulo front end
+    fn fetch(&mut self, cpu: &mut CpuState, mem: &Memory) {
+        if self.fetch_stall > 0 {
+            self.fetch_stall -= 1;
+            return;
+        }
+        if self.next.is_some() || self.branch_pending.is_some() {
+            return;
+        }
+
+        let pc = cpu.pc;
+        let mut fetched = Fetched { instruction: None, pc, fault: None };
+        match cpu.check_access(mem, pc, 4, Access::Execute) {
+            Ok(walked) => {
+                if walked {
+                    self.fetch_stall = cpu.mmu.walk_latency * 2;
+                }
+                self.fetch_stall += cpu.cache_stall(mem, pc, pc, 4, Access::Execute);
+                fetched.instruction = isa::decode(cpu.mmu.fetch(mem, pc));
+            }
+            Err(fault) => fetched.fault = Some(fault),
+        }
+        self.next = Some(fetched);
+        cpu.pc = pc.wrapping_add(4);
+    }
+}
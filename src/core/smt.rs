@@ -0,0 +1,257 @@
+use crate::core::{CpuState, StatusFlags};
+use crate::core::memory_dependence::MemoryDependence;
+use crate::core::register_file::{RegisterFile, PHYSICAL_REGISTERS};
+use crate::core::rename::PhysicalRegisterFile;
+use crate::core::tomasulo::{PipelineController, RegisterRenameTable, ReorderBuffer};
+use crate::core::value_prediction::{ReuseBuffer, ValuePredictor};
+use crate::memory::Memory;
+
+// Which thread the shared front end fetches for each cycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FetchPolicy {
+    #[default]
+    RoundRobin, // Take turns among the threads still running
+    ICount,     // The thread with the fewest instructions waiting to execute (Tullsen et al.)
+}
+
+// Architectural and per-thread speculative state of a hardware thread that is not
+// being stepped. Swapping it with the matching CpuState fields makes it the active one.
+#[derive(Debug)]
+pub struct ThreadContext {
+    pub pipeline: PipelineController, // Instruction queue, speculation state and statistics
+    pub pc: u16,
+    pub regs: RegisterFile,
+    pub flags: StatusFlags,
+    pub halted: bool,
+    pub reorder_buffer: ReorderBuffer,
+    pub rename_table: RegisterRenameTable,
+    pub physical_registers: PhysicalRegisterFile,
+    pub memory_dependence: MemoryDependence,
+}
+
+impl ThreadContext {
+    // A thread starting at `pc` with a copy of the active thread's registers and flags,
+    // the same speculation and reuse settings and a ROB partition of `rob_entries`
+    pub fn new(cpu: &CpuState, pc: u16, rob_entries: usize) -> Self {
+        let mut regs = RegisterFile::new();
+        regs.regs = cpu.regs.regs;
+        regs.bank = cpu.regs.bank;
+
+        let mut pipeline = PipelineController::new();
+        pipeline.value_predictor = cpu
+            .pipeline
+            .value_predictor
+            .as_ref()
+            .map(|predictor| ValuePredictor::new(predictor.kind, predictor.table.len()));
+        pipeline.reuse_buffer = cpu.pipeline.reuse_buffer.as_ref().map(|buffer| ReuseBuffer::new(buffer.table.len()));
+
+        let prf = &cpu.physical_registers;
+        let rename_registers = prf.registers.len() - prf.architectural_map.len();
+        Self {
+            pipeline,
+            pc,
+            regs,
+            flags: StatusFlags {
+                zero: cpu.flags.zero,
+                carry: cpu.flags.carry,
+                negative: cpu.flags.negative,
+                overflow: cpu.flags.overflow,
+            },
+            halted: false,
+            reorder_buffer: ReorderBuffer::new(rob_entries),
+            rename_table: RegisterRenameTable::new(PHYSICAL_REGISTERS),
+            physical_registers: PhysicalRegisterFile::new(PHYSICAL_REGISTERS, rename_registers, prf.max_checkpoints),
+            memory_dependence: MemoryDependence::new(cpu.memory_dependence.policy),
+        }
+    }
+
+    pub fn swap(&mut self, cpu: &mut CpuState) {
+        std::mem::swap(&mut self.pipeline, &mut cpu.pipeline);
+        std::mem::swap(&mut self.pc, &mut cpu.pc);
+        std::mem::swap(&mut self.regs, &mut cpu.regs);
+        std::mem::swap(&mut self.flags, &mut cpu.flags);
+        std::mem::swap(&mut self.halted, &mut cpu.halted);
+        std::mem::swap(&mut self.reorder_buffer, &mut cpu.reorder_buffer);
+        std::mem::swap(&mut self.rename_table, &mut cpu.rename_table);
+        std::mem::swap(&mut self.physical_registers, &mut cpu.physical_registers);
+        std::mem::swap(&mut self.memory_dependence, &mut cpu.memory_dependence);
+    }
+
+    // Still has work: running, or halted with instructions left to drain
+    pub fn running(&self) -> bool {
+        !self.halted || !self.reorder_buffer.is_empty()
+    }
+}
+
+// Simultaneous multithreading on the Tomasulo core. Thread 0 lives in CpuState as in the
+// single-threaded engine; threads 1.. are kept in `contexts` and swapped in while their
+// stages run. Each thread has its own pc, registers, flags, instruction queue, ROB
+// partition and rename state. The reservation stations, functional units, CDB, caches and
+// privilege/MMU state are shared: stations carry the thread they were issued for, and the
+// CDB goes to one thread per cycle in rotating priority order. One thread fetches each
+// cycle, chosen by the fetch policy, and one instruction issues, trying the thread that
+// fetched first.
+#[derive(Debug, Default)]
+pub struct SmtCore {
+    pub contexts: Vec<ThreadContext>, // Threads 1..
+    pub policy: FetchPolicy,
+    pub cycles: u64,
+    pub next_fetch: usize, // Round-robin position
+}
+
+impl SmtCore {
+    // Split the active thread's ROB between one thread per entry point
+    pub fn new(cpu: &mut CpuState, entry_points: &[u16], policy: FetchPolicy) -> Self {
+        let threads = entry_points.len().max(1);
+        let rob_entries = (cpu.reorder_buffer.size / threads).max(2);
+        cpu.reorder_buffer = ReorderBuffer::new(rob_entries);
+        if let Some(&pc) = entry_points.first() {
+            cpu.pc = pc;
+        }
+        let contexts = entry_points
+            .iter()
+            .skip(1)
+            .map(|&pc| ThreadContext::new(cpu, pc, rob_entries))
+            .collect();
+        Self { contexts, policy, cycles: 0, next_fetch: 0 }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.contexts.len() + 1
+    }
+
+    pub fn pipeline<'a>(&'a self, cpu: &'a CpuState, thread: usize) -> &'a PipelineController {
+        match thread {
+            0 => &cpu.pipeline,
+            _ => &self.contexts[thread - 1].pipeline,
+        }
+    }
+
+    fn pipeline_mut<'a>(&'a mut self, cpu: &'a mut CpuState, thread: usize) -> &'a mut PipelineController {
+        match thread {
+            0 => &mut cpu.pipeline,
+            _ => &mut self.contexts[thread - 1].pipeline,
+        }
+    }
+
+    fn running(&self, cpu: &CpuState, thread: usize) -> bool {
+        match thread {
+            0 => !cpu.halted || !cpu.reorder_buffer.is_empty(),
+            _ => self.contexts[thread - 1].running(),
+        }
+    }
+
+    fn halted(&self, cpu: &CpuState, thread: usize) -> bool {
+        match thread {
+            0 => cpu.halted,
+            _ => self.contexts[thread - 1].halted,
+        }
+    }
+
+    // Nothing fetched or in flight, so a latched fetch fault can be taken
+    fn drained(&self, cpu: &CpuState, thread: usize) -> bool {
+        let rob = match thread {
+            0 => &cpu.reorder_buffer,
+            _ => &self.contexts[thread - 1].reorder_buffer,
+        };
+        self.pipeline(cpu, thread).instruction_queue.is_empty() && rob.is_empty()
+    }
+
+    // Instructions fetched and not yet executed, the ICOUNT measure
+    fn icount(&self, cpu: &CpuState, thread: usize) -> usize {
+        self.pipeline(cpu, thread).instruction_queue.len() + cpu.reservation_stations.in_flight(thread)
+    }
+
+    pub fn instructions(&self, cpu: &CpuState) -> u64 {
+        (0..self.threads()).map(|thread| self.pipeline(cpu, thread).instructions).sum()
+    }
+
+    pub fn ipc(&self, cpu: &CpuState) -> f32 {
+        if self.cycles == 0 { 0.0 } else { self.instructions(cpu) as f32 / self.cycles as f32 }
+    }
+
+    // Run `stage` with `thread` as the active thread
+    fn with_thread<R>(&mut self, thread: usize, cpu: &mut CpuState, stage: impl FnOnce(&mut PipelineController, &mut CpuState) -> R) -> R {
+        if thread > 0 {
+            self.contexts[thread - 1].swap(cpu);
+        }
+        cpu.reservation_stations.thread = thread;
+        let mut pipeline = std::mem::replace(&mut cpu.pipeline, PipelineController::new());
+        let result = stage(&mut pipeline, cpu);
+        cpu.pipeline = pipeline;
+        cpu.reservation_stations.thread = 0;
+        if thread > 0 {
+            self.contexts[thread - 1].swap(cpu);
+        }
+        result
+    }
+
+    pub fn step(&mut self, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        cpu.tick_memory();
+        self.cycles += 1;
+        let threads = self.threads();
+
+        for thread in 0..threads {
+            self.with_thread(thread, cpu, |pipeline, cpu| {
+                pipeline.cycles += 1;
+                pipeline.commit_stage(cpu, mem);
+            });
+        }
+
+        // One result a cycle on the shared CDB: the first thread in priority order with one wins
+        let first = (self.cycles % threads as u64) as usize;
+        for i in 0..threads {
+            let thread = (first + i) % threads;
+            let broadcast = self.with_thread(thread, cpu, |pipeline, cpu| {
+                pipeline.writeback_stage(cpu, mem);
+                cpu.common_data_bus.valid
+            });
+            if broadcast {
+                break;
+            }
+        }
+
+        for thread in 0..threads {
+            self.with_thread(thread, cpu, |pipeline, cpu| pipeline.resolve_branch_stage(cpu));
+        }
+        self.with_thread(0, cpu, |pipeline, cpu| pipeline.execute_stage(cpu));
+
+        let fetching = self.fetch_thread(cpu);
+        if let Some(thread) = fetching {
+            self.with_thread(thread, cpu, |pipeline, cpu| pipeline.fetch_stage(cpu, mem));
+        }
+        let first = fetching.unwrap_or(0);
+        for i in 0..threads {
+            let thread = (first + i) % threads;
+            if !self.halted(cpu, thread) && self.with_thread(thread, cpu, |pipeline, cpu| pipeline.issue_stage(cpu, mem)) {
+                break;
+            }
+        }
+
+        (0..threads).any(|thread| self.running(cpu, thread))
+    }
+
+    // Threads waiting on an instruction cache miss, with a full queue or with a fetch fault
+    // they cannot take yet sit out the choice
+    fn fetch_thread(&mut self, cpu: &mut CpuState) -> Option<usize> {
+        let threads = self.threads();
+        for thread in 0..threads {
+            self.pipeline_mut(cpu, thread).fetch_wait();
+        }
+
+        let mut candidates = (0..threads)
+            .map(|i| (self.next_fetch + i) % threads)
+            .filter(|&thread| {
+                let pipeline = self.pipeline(cpu, thread);
+                let fault_pending = pipeline.fetch_fault.is_some() && !self.drained(cpu, thread);
+                !self.halted(cpu, thread) && !fault_pending && pipeline.fetch_stall == 0 && pipeline.instruction_queue.len() < 4
+            });
+        let thread = match self.policy {
+            FetchPolicy::RoundRobin => candidates.next(),
+            // Ties go to the thread next in round-robin order
+            FetchPolicy::ICount => candidates.min_by_key(|&thread| self.icount(cpu, thread)),
+        }?;
+        self.next_fetch = (thread + 1) % threads;
+        Some(thread)
+    }
+}
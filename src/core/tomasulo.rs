@@ -1,5 +1,9 @@
 use crate::isa::Instruction;
-use crate::core::CpuState;
+use crate::core::{CpuState, Exception, StatusFlags};
+use crate::core::memory_dependence::LoadSpeculation;
+use crate::core::mpu::Access;
+use crate::core::rename::RenameScheme;
+use crate::core::value_prediction::{ReuseBuffer, ValuePredictor};
 use crate::memory::Memory;
 
 #[derive(Debug, Clone)]
@@ -12,6 +16,10 @@ pub struct ReservationStation {
     pub qk: Option<usize>,  //tag of producer for operand K 
     pub tag: usize,         //tag of this station 
     pub cycles_remaining: u32, //execution countdown (0 means ready)
+    pub translated: bool,   //memory ops: address already went through the TLB
+    pub waiting_for_mshr: bool, //loads: translated, but the data cache had no free MSHR
+    pub store_dependence: Option<usize>, //loads: ROB entry of the store the store sets predict it depends on
+    pub thread: usize,      //hardware thread the instruction belongs to (SMT)
 }
 
 impl ReservationStation {
@@ -25,6 +33,10 @@ impl ReservationStation {
             qk: None,
             tag: 0,
             cycles_remaining: 0,
+            translated: false,
+            waiting_for_mshr: false,
+            store_dependence: None,
+            thread: 0,
         }
     }
 
@@ -41,6 +53,87 @@ impl ReservationStation {
         self.qk = None;
         self.tag = 0;
         self.cycles_remaining = 0;
+        self.translated = false;
+        self.waiting_for_mshr = false;
+        self.store_dependence = None;
+    }
+}
+
+// Station for the multi-cycle MAC unit. Multiply-accumulate reads four registers
+// (both factors and both accumulator halves); it and the widening multiplies write
+// a register pair, so the high half gets its own ROB entry right after the low half.
+// The unit has one result port on the CDB: the high half is held in the station and
+// broadcast the cycle after the low half.
+#[derive(Debug, Clone)]
+pub struct MacStation {
+    pub busy: bool,
+    pub op: Option<Instruction>,
+    pub values: [Option<u16>; 4],      // src1, src2, accumulator low, accumulator high
+    pub producers: [Option<usize>; 4], // tags of pending producers for each value
+    pub tag: usize,                    // ROB entry of the (low) result
+    pub tag_hi: Option<usize>,         // ROB entry of the high result for pair results
+    pub high: Option<u16>,             // High result waiting for the CDB
+    pub cycles_remaining: u32,
+    pub thread: usize,
+}
+
+impl MacStation {
+    pub fn new() -> Self {
+        Self {
+            busy: false,
+            op: None,
+            values: [None; 4],
+            producers: [None; 4],
+            tag: 0,
+            tag_hi: None,
+            high: None,
+            cycles_remaining: 0,
+            thread: 0,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.busy && self.values.iter().all(|v| v.is_some())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+// Station for a conditional branch that issued before its operands were ready. Issue
+// carries on down the fall-through path (predicted not taken) and the branch resolves
+// once the CDB has delivered its operands.
+#[derive(Debug, Clone)]
+pub struct BranchStation {
+    pub busy: bool,
+    pub op: Option<Instruction>,
+    pub pc: u16,
+    pub values: [Option<u16>; 4],      // src1, src2 and, for 32-bit compares, their high halves
+    pub producers: [Option<usize>; 4], // tags of pending producers for each value
+    pub tag: usize,                    // ROB entry of the branch
+    pub thread: usize,
+}
+
+impl BranchStation {
+    pub fn new() -> Self {
+        Self {
+            busy: false,
+            op: None,
+            pc: 0,
+            values: [None; 4],
+            producers: [None; 4],
+            tag: 0,
+            thread: 0,
+        }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.busy && self.values.iter().all(|v| v.is_some())
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
     }
 }
 
@@ -50,6 +143,11 @@ pub struct ReservationStationPool {
     pub alu_stations: Vec<ReservationStation>,
     pub load_stations: Vec<ReservationStation>,
     pub store_stations: Vec<ReservationStation>,
+    pub mac_stations: Vec<MacStation>,
+    pub branch_stations: Vec<BranchStation>,
+    // Hardware thread being stepped. Stations are shared between threads but ROB tags are
+    // per thread, so everything that finds stations by tag only sees this thread's.
+    pub thread: usize,
 }
 
 impl ReservationStationPool {
@@ -58,27 +156,111 @@ impl ReservationStationPool {
             alu_stations: vec![ReservationStation::new(); 4],   // 4 ALU reservation stations
             load_stations: vec![ReservationStation::new(); 2],  // 2 Load stations
             store_stations: vec![ReservationStation::new(); 2], // 2 Store stations
+            mac_stations: vec![MacStation::new(); 2],           // 2 MAC stations
+            branch_stations: vec![BranchStation::new(); 2],     // 2 unresolved branches
+            thread: 0,
         }
     }
 
+    // Clear the current thread's stations
+    pub fn flush(&mut self) {
+        self.squash(|_| false);
+    }
+
+    // Clear the current thread's stations of instructions squashed from its ROB
+    pub fn squash(&mut self, live: impl Fn(usize) -> bool) {
+        let thread = self.thread;
+        for rs in self.alu_stations.iter_mut().chain(&mut self.load_stations).chain(&mut self.store_stations) {
+            if rs.busy && rs.thread == thread && !live(rs.tag) {
+                rs.clear();
+            }
+        }
+        for rs in &mut self.mac_stations {
+            if rs.busy && rs.thread == thread && !live(rs.tag) {
+                rs.clear();
+            }
+        }
+        for rs in &mut self.branch_stations {
+            if rs.busy && rs.thread == thread && !live(rs.tag) {
+                rs.clear();
+            }
+        }
+    }
+
+    // Load or store station holding the instruction with ROB entry `tag`
+    pub fn memory_station(&mut self, tag: usize) -> Option<&mut ReservationStation> {
+        let thread = self.thread;
+        self.load_stations
+            .iter_mut()
+            .chain(self.store_stations.iter_mut())
+            .find(|rs| rs.busy && rs.thread == thread && rs.tag == tag)
+    }
+
+    // The free stations handed out belong to the current thread
     pub fn find_free_alu_station(&mut self) -> Option<&mut ReservationStation> {
-        self.alu_stations.iter_mut().find(|rs| !rs.busy)
+        let thread = self.thread;
+        self.alu_stations.iter_mut().find(|rs| !rs.busy).map(|rs| {
+            rs.thread = thread;
+            rs
+        })
     }
 
     pub fn find_free_load_station(&mut self) -> Option<&mut ReservationStation> {
-        self.load_stations.iter_mut().find(|rs| !rs.busy)
+        let thread = self.thread;
+        self.load_stations.iter_mut().find(|rs| !rs.busy).map(|rs| {
+            rs.thread = thread;
+            rs
+        })
     }
 
     pub fn find_free_store_station(&mut self) -> Option<&mut ReservationStation> {
-        self.store_stations.iter_mut().find(|rs| !rs.busy)
+        let thread = self.thread;
+        self.store_stations.iter_mut().find(|rs| !rs.busy).map(|rs| {
+            rs.thread = thread;
+            rs
+        })
+    }
+
+    pub fn find_free_mac_station(&mut self) -> Option<&mut MacStation> {
+        let thread = self.thread;
+        self.mac_stations.iter_mut().find(|rs| !rs.busy).map(|rs| {
+            rs.thread = thread;
+            rs
+        })
+    }
+
+    pub fn find_free_branch_station(&mut self) -> Option<&mut BranchStation> {
+        let thread = self.thread;
+        self.branch_stations.iter_mut().find(|rs| !rs.busy).map(|rs| {
+            rs.thread = thread;
+            rs
+        })
+    }
+
+    // Busy stations issued for `thread`
+    pub fn in_flight(&self, thread: usize) -> usize {
+        self.alu_stations.iter()
+            .chain(&self.load_stations)
+            .chain(&self.store_stations)
+            .filter(|rs| rs.busy && rs.thread == thread)
+            .count()
+            + self.mac_stations.iter().filter(|rs| rs.busy && rs.thread == thread).count()
+            + self.branch_stations.iter().filter(|rs| rs.busy && rs.thread == thread).count()
+    }
+
+    // Whether the current thread has a branch waiting to resolve
+    pub fn branch_pending(&self) -> bool {
+        self.branch_stations.iter().any(|rs| rs.busy && rs.thread == self.thread)
     }
 
+    // The current thread's stations with operands ready and execution finished
     pub fn get_ready_instructions(&mut self) -> Vec<(usize, Instruction, u16, u16)> {
         let mut ready = Vec::new();
+        let thread = self.thread;
 
         // Check ALU stations
-        for (i, rs) in self.alu_stations.iter_mut().enumerate() {
-            if rs.is_ready() && rs.cycles_remaining == 0 {
+        for rs in self.alu_stations.iter_mut() {
+            if rs.thread == thread && rs.is_ready() && rs.cycles_remaining == 0 {
                 if let (Some(inst), Some(vj), Some(vk)) = (rs.op, rs.vj, rs.vk) {
                     ready.push((rs.tag, inst, vj, vk));
                 }
@@ -86,8 +268,8 @@ impl ReservationStationPool {
         }
 
         // Check Load stations
-        for (i, rs) in self.load_stations.iter_mut().enumerate() {
-            if rs.is_ready() && rs.cycles_remaining == 0 {
+        for rs in self.load_stations.iter_mut() {
+            if rs.thread == thread && rs.is_ready() && rs.cycles_remaining == 0 {
                 if let (Some(inst), Some(vj), Some(vk)) = (rs.op, rs.vj, rs.vk) {
                     ready.push((rs.tag, inst, vj, vk));
                 }
@@ -95,8 +277,8 @@ impl ReservationStationPool {
         }
 
         // Check Store stations  
-        for (i, rs) in self.store_stations.iter_mut().enumerate() {
-            if rs.is_ready() && rs.cycles_remaining == 0 {
+        for rs in self.store_stations.iter_mut() {
+            if rs.thread == thread && rs.is_ready() && rs.cycles_remaining == 0 {
                 if let (Some(inst), Some(vj), Some(vk)) = (rs.op, rs.vj, rs.vk) {
                     ready.push((rs.tag, inst, vj, vk));
                 }
@@ -106,9 +288,14 @@ impl ReservationStationPool {
         ready
     }
 
+    // Tags are per thread: only the current thread's stations listen
     pub fn update_from_cdb(&mut self, tag: usize, value: u16) {
+        let thread = self.thread;
         // Update ALU stations
         for rs in &mut self.alu_stations {
+            if rs.thread != thread {
+                continue;
+            }
             if rs.qj == Some(tag) {
                 rs.vj = Some(value);
                 rs.qj = None;
@@ -121,6 +308,9 @@ impl ReservationStationPool {
 
         // Update Load stations
         for rs in &mut self.load_stations {
+            if rs.thread != thread {
+                continue;
+            }
             if rs.qj == Some(tag) {
                 rs.vj = Some(value);
                 rs.qj = None;
@@ -133,6 +323,9 @@ impl ReservationStationPool {
 
         // Update Store stations
         for rs in &mut self.store_stations {
+            if rs.thread != thread {
+                continue;
+            }
             if rs.qj == Some(tag) {
                 rs.vj = Some(value);
                 rs.qj = None;
@@ -142,6 +335,70 @@ impl ReservationStationPool {
                 rs.qk = None;
             }
         }
+
+        // Update MAC stations
+        for rs in &mut self.mac_stations {
+            if rs.thread != thread {
+                continue;
+            }
+            for i in 0..4 {
+                if rs.producers[i] == Some(tag) {
+                    rs.values[i] = Some(value);
+                    rs.producers[i] = None;
+                }
+            }
+        }
+
+        // Update branch stations
+        for rs in &mut self.branch_stations {
+            if rs.thread != thread {
+                continue;
+            }
+            for i in 0..4 {
+                if rs.producers[i] == Some(tag) {
+                    rs.values[i] = Some(value);
+                    rs.producers[i] = None;
+                }
+            }
+        }
+    }
+}
+
+// Flags written by an instruction that executed in a functional unit. They reach the
+// architectural flags when it commits, so an instruction that reads the flags (and
+// therefore waits for an empty ROB) sees them in program order. None leaves a flag as is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FlagUpdate {
+    pub zero: Option<bool>,
+    pub carry: Option<bool>,
+    pub negative: Option<bool>,
+    pub overflow: Option<bool>,
+}
+
+impl FlagUpdate {
+    // Zero and negative from a 16-bit result, as set_flags_from_result does
+    pub fn from_result(result: u16) -> Self {
+        Self { zero: Some(result == 0), negative: Some((result as i16) < 0), ..Self::default() }
+    }
+
+    // Zero and negative from a 32-bit register pair result
+    pub fn from_wide(result: u32) -> Self {
+        Self { zero: Some(result == 0), negative: Some((result as i32) < 0), ..Self::default() }
+    }
+
+    pub fn with_carry(self, carry: bool) -> Self {
+        Self { carry: Some(carry), ..self }
+    }
+
+    pub fn with_overflow(self, overflow: bool) -> Self {
+        Self { overflow: Some(overflow), ..self }
+    }
+
+    pub fn apply(&self, flags: &mut StatusFlags) {
+        flags.zero = self.zero.unwrap_or(flags.zero);
+        flags.carry = self.carry.unwrap_or(flags.carry);
+        flags.negative = self.negative.unwrap_or(flags.negative);
+        flags.overflow = self.overflow.unwrap_or(flags.overflow);
     }
 }
 
@@ -151,9 +408,17 @@ pub struct ReorderBufferEntry {
     pub valid: bool,                    // Entry is active
     pub ready: bool,                    // Result is available (complete bit)
     pub instruction: Option<Instruction>,
-    pub dest_reg: Option<u8>,           // Destination register
+    pub dest_reg: Option<u16>,          // Destination register (physical index)
+    pub prf_dest: Option<(u16, u16)>,   // Physical register file renaming: (new, previous) register
     pub result: Option<u16>,            // Computed result
-    pub exception: bool,                // Exception occurred
+    pub address: Option<u16>,           // Effective (virtual) address of memory accesses
+    pub location: Option<[usize; 2]>,   // Storage of its first and last byte, for dependence checks
+    pub exception: Option<Exception>,   // Exception occurred, raised when the entry commits
+    pub forwarded_from: Option<usize>,  // Loads: ROB entry of the store that supplied the value
+    pub predicted: Option<u16>,         // Value predicted at issue, verified at writeback
+    pub flags: Option<FlagUpdate>,      // Flags written by the instruction, applied at commit
+    pub cracked_with: Option<usize>,    // First entry of a cracked instruction: the entry of the rest
+    pub branches: u64,                  // Branches resolved at issue right behind it, retired with it
     pub pc: u16,                       // Program counter for this instruction
 }
 
@@ -164,8 +429,16 @@ impl ReorderBufferEntry {
             ready: false,
             instruction: None,
             dest_reg: None,
+            prf_dest: None,
             result: None,
-            exception: false,
+            address: None,
+            location: None,
+            exception: None,
+            forwarded_from: None,
+            predicted: None,
+            flags: None,
+            cracked_with: None,
+            branches: 0,
             pc: 0,
         }
     }
@@ -175,8 +448,16 @@ impl ReorderBufferEntry {
         self.ready = false;
         self.instruction = None;
         self.dest_reg = None;
+        self.prf_dest = None;
         self.result = None;
-        self.exception = false;
+        self.address = None;
+        self.location = None;
+        self.exception = None;
+        self.forwarded_from = None;
+        self.predicted = None;
+        self.cracked_with = None;
+        self.branches = 0;
+        self.flags = None;
         self.pc = 0;
     }
 }
@@ -210,7 +491,7 @@ impl ReorderBuffer {
         self.count == 0
     }
 
-    pub fn allocate(&mut self, instruction: Instruction, dest_reg: Option<u8>, pc: u16) -> Option<usize> {
+    pub fn allocate(&mut self, instruction: Instruction, dest_reg: Option<u16>, pc: u16) -> Option<usize> {
         if self.is_full() {
             return None;
         }
@@ -220,8 +501,16 @@ impl ReorderBuffer {
         entry.ready = false;
         entry.instruction = Some(instruction);
         entry.dest_reg = dest_reg;
+        entry.prf_dest = None;
         entry.result = None;
-        entry.exception = false;
+        entry.address = None;
+        entry.location = None;
+        entry.exception = None;
+        entry.forwarded_from = None;
+        entry.predicted = None;
+        entry.flags = None;
+        entry.cracked_with = None;
+        entry.branches = 0;
         entry.pc = pc;
 
         let tag = self.tail;
@@ -238,6 +527,53 @@ impl ReorderBuffer {
         }
     }
 
+    // `location` is None for an access that faulted in translation
+    pub fn set_address(&mut self, tag: usize, address: u16, location: Option<[usize; 2]>) {
+        if tag < self.entries.len() && self.entries[tag].valid {
+            self.entries[tag].address = Some(address);
+            self.entries[tag].location = location;
+        }
+    }
+
+    pub fn set_flags(&mut self, tag: usize, flags: Option<FlagUpdate>) {
+        if tag < self.entries.len() && self.entries[tag].valid {
+            self.entries[tag].flags = flags;
+        }
+    }
+
+    pub fn set_exception(&mut self, tag: usize, exception: Exception) {
+        if tag < self.entries.len() && self.entries[tag].valid {
+            self.entries[tag].exception = Some(exception);
+        }
+    }
+
+    // Discard every in-flight instruction
+    pub fn flush(&mut self) {
+        self.entries.iter_mut().for_each(ReorderBufferEntry::clear);
+        self.head = 0;
+        self.tail = 0;
+        self.count = 0;
+    }
+
+    // Position of entry `tag` counted from the oldest
+    pub fn age(&self, tag: usize) -> usize {
+        (tag + self.size - self.head) % self.size
+    }
+
+    // Keep the `keep` oldest entries and discard the rest; returns how many were dropped
+    pub fn truncate(&mut self, keep: usize) -> usize {
+        if keep >= self.count {
+            return 0;
+        }
+        let squashed = self.count - keep;
+        for i in keep..self.count {
+            self.entries[(self.head + i) % self.size].clear();
+        }
+        self.count = keep;
+        self.tail = (self.head + keep) % self.size;
+        squashed
+    }
+
     pub fn can_commit(&self) -> bool {
         !self.is_empty() && self.entries[self.head].valid && self.entries[self.head].ready
     }
@@ -276,7 +612,7 @@ impl RenameEntry {
     }
 }
 
-// Register Rename Table
+// Register Rename Table, indexed by physical register so banked registers rename independently
 #[derive(Debug)]
 pub struct RegisterRenameTable {
     pub entries: Vec<RenameEntry>,
@@ -289,14 +625,14 @@ impl RegisterRenameTable {
         }
     }
 
-    pub fn rename_register(&mut self, reg: u8, producer_tag: usize) {
+    pub fn rename_register(&mut self, reg: u16, producer_tag: usize) {
         if (reg as usize) < self.entries.len() {
             self.entries[reg as usize].producer_tag = Some(producer_tag);
             self.entries[reg as usize].ready = false;
         }
     }
 
-    pub fn get_register_info(&self, reg: u8) -> (bool, Option<usize>) {
+    pub fn get_register_info(&self, reg: u16) -> (bool, Option<usize>) {
         if (reg as usize) < self.entries.len() {
             let entry = &self.entries[reg as usize];
             (entry.ready, entry.producer_tag)
@@ -305,6 +641,28 @@ impl RegisterRenameTable {
         }
     }
 
+    pub fn reset(&mut self) {
+        self.entries.iter_mut().for_each(|entry| *entry = RenameEntry::new());
+    }
+
+    // Rename state left by the instructions still in the ROB once younger ones are squashed
+    pub fn rebuild(&mut self, rob: &ReorderBuffer) {
+        self.reset();
+        for i in 0..rob.count {
+            let tag = (rob.head + i) % rob.size;
+            let entry = &rob.entries[tag];
+            if let (true, Some(reg)) = (entry.valid, entry.dest_reg) {
+                if entry.ready || entry.predicted.is_some() {
+                    if let Some(rename) = self.entries.get_mut(reg as usize) {
+                        *rename = RenameEntry::new();
+                    }
+                } else {
+                    self.rename_register(reg, tag);
+                }
+            }
+        }
+    }
+
     pub fn update_from_cdb(&mut self, tag: usize) {
         for entry in &mut self.entries {
             if entry.producer_tag == Some(tag) {
@@ -350,6 +708,15 @@ impl CommonDataBus {
 pub struct PipelineController {
     pub cycles: u64,
     pub instruction_queue: Vec<(Instruction, u16)>, // (instruction, pc)
+    pub fetch_stall: u32, // Cycles until the instruction cache can deliver the next fetch
+    pub fetch_fault: Option<Exception>, // Fault at the fetch pc, taken once the ROB drains
+    pub fetching: Option<(Instruction, u16)>, // Missed in the instruction cache, queued when the fill is done
+    pub speculated_branches: u64,   // Branches issued before their operands were ready
+    pub mispredicted_branches: u64, // ... that turned out taken
+    pub squashed: u64,              // ROB entries discarded behind mispredicted branches
+    pub value_predictor: Option<ValuePredictor>, // Predicts ALU and load results when set
+    pub reuse_buffer: Option<ReuseBuffer>, // Reuses ALU results for repeated operands when set
+    pub instructions: u64,          // Instructions retired
 }
 
 impl PipelineController {
@@ -357,43 +724,115 @@ impl PipelineController {
         Self {
             cycles: 0,
             instruction_queue: Vec::new(),
+            fetch_stall: 0,
+            fetch_fault: None,
+            fetching: None,
+            speculated_branches: 0,
+            mispredicted_branches: 0,
+            squashed: 0,
+            value_predictor: None,
+            reuse_buffer: None,
+            instructions: 0,
         }
     }
 
+    pub fn ipc(&self) -> f32 {
+        if self.cycles == 0 { 0.0 } else { self.instructions as f32 / self.cycles as f32 }
+    }
+
     pub fn step(&mut self, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        cpu.tick_memory();
+
         // Execute stages in reverse order to avoid conflicts
         self.commit_stage(cpu, mem);
-        self.writeback_stage(cpu);
+        self.writeback_stage(cpu, mem);
+        self.resolve_branch_stage(cpu);
         self.execute_stage(cpu);
+        self.fetch_stage(cpu, mem);
         self.issue_stage(cpu, mem);
 
         self.cycles += 1;
         !cpu.halted
     }
 
-    // Issue Stage: Decode instructions, rename registers, allocate reservation stations
-    fn issue_stage(&mut self, cpu: &mut CpuState, mem: &mut Memory) {
-        // Fetch instruction if queue has space
-        if self.instruction_queue.len() < 4 && !cpu.halted { // Keep 4 instructions buffered
-            let raw = mem.fetch(cpu.pc);
-            if let Some(inst) = crate::isa::decode(raw) {
-                self.instruction_queue.push((inst, cpu.pc));
-                cpu.pc += 4; // 4-byte instructions
+    // Fetch Stage: fill the instruction queue. An instruction cache miss holds the
+    // instruction back, and fetch with it, for the rest of the miss latency.
+    pub fn fetch_stage(&mut self, cpu: &mut CpuState, mem: &mut Memory) {
+        if let Some(fault) = self.fetch_fault {
+            // Fetching stops at the fault, which is taken once everything older has committed
+            // and dropped if a redirect gets there first
+            if self.instruction_queue.is_empty() && cpu.reorder_buffer.is_empty() {
+                self.fetch_fault = None;
+                cpu.raise_exception(fault);
+            }
+        } else if self.fetch_stall > 0 {
+            self.fetch_wait();
+        } else if self.instruction_queue.len() < 4 && !cpu.halted { // Keep 4 instructions buffered
+            if let Err(fault) = cpu.check_access(mem, cpu.pc, 4, Access::Execute) {
+                self.fetch_fault = Some(fault);
             } else {
-                cpu.halted = true;
-                return;
+                self.fetch_stall = cpu.cache_stall(mem, cpu.pc, cpu.pc, 4, Access::Execute);
+                let raw = cpu.mmu.fetch(mem, cpu.pc);
+                if let Some(inst) = crate::isa::decode(raw) {
+                    if self.fetch_stall == 0 {
+                        self.instruction_queue.push((inst, cpu.pc));
+                    } else {
+                        self.fetching = Some((inst, cpu.pc));
+                    }
+                    cpu.pc += 4; // 4-byte instructions
+                } else if !cpu.reservation_stations.branch_pending() {
+                    cpu.halted = true;
+                }
+                // Past an unresolved branch the fall-through path may run off the program:
+                // wait for the branch instead of halting
             }
         }
+    }
+
+    // One cycle of an instruction cache miss. The instruction that missed joins the queue
+    // as the miss ends.
+    pub fn fetch_wait(&mut self) {
+        self.fetch_stall = self.fetch_stall.saturating_sub(1);
+        if self.fetch_stall == 0 {
+            if let Some(fetched) = self.fetching.take() {
+                self.instruction_queue.push(fetched);
+            }
+        }
+    }
+
+    // Fetch moved elsewhere: drop what was fetched past the `keep` oldest queued instructions
+    fn drop_fetched(&mut self, keep: usize) {
+        self.instruction_queue.truncate(keep);
+        self.fetching = None;
+        self.fetch_fault = None;
+    }
+
+    // Issue Stage: Decode instructions, rename registers, allocate reservation stations.
+    // Returns whether an instruction issued.
+    pub fn issue_stage(&mut self, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        if cpu.halted {
+            return false;
+        }
+
+        // Nothing in flight: the committed registers are the architectural register file,
+        // which the in-order fallback path and exception entry write directly
+        if cpu.rename_scheme == RenameScheme::PhysicalRegisters && cpu.reorder_buffer.is_empty() {
+            cpu.physical_registers.sync(&cpu.regs);
+        }
 
         // Try to issue the oldest instruction
         if let Some((instruction, pc)) = self.instruction_queue.first().cloned() {
-            if self.try_issue_instruction(instruction, pc, cpu) {
+            if self.try_issue_instruction(instruction, pc, cpu, mem) {
+                cpu.unit_stats.record_issue(&instruction);
                 self.instruction_queue.remove(0);
+                return true;
             }
+            cpu.unit_stats.structural_stalls += 1;
         }
+        false
     }
 
-    fn try_issue_instruction(&mut self, instruction: Instruction, pc: u16, cpu: &mut CpuState) -> bool {
+    fn try_issue_instruction(&mut self, instruction: Instruction, pc: u16, cpu: &mut CpuState, mem: &mut Memory) -> bool {
         use crate::isa::Instruction;
 
         match instruction {
@@ -403,78 +842,192 @@ impl PipelineController {
             Instruction::And { dst, src1, src2 } |
             Instruction::Or { dst, src1, src2 } |
             Instruction::Xor { dst, src1, src2 } => {
-                self.issue_alu_instruction(instruction, dst, Some(src1), Some(src2 as u8), pc, cpu)
+                self.issue_alu_instruction(instruction, dst.into(), Some(src1.into()), Some(src2), pc, cpu)
             },
 
-            Instruction::AddImm { dst, src, imm } |
-            Instruction::SubImm { dst, src, imm } => {
+            Instruction::AddImm { dst, src, .. } |
+            Instruction::SubImm { dst, src, .. } |
+            Instruction::AndImm { dst, src, .. } |
+            Instruction::OrImm { dst, src, .. } |
+            Instruction::XorImm { dst, src, .. } |
+            Instruction::ShiftLeft { dst, src, .. } |
+            Instruction::ShiftRight { dst, src, .. } |
+            Instruction::ShiftRightArith { dst, src, .. } |
+            Instruction::RotateLeft { dst, src, .. } |
+            Instruction::RotateRight { dst, src, .. } => {
                 // For immediate instructions, we treat the immediate as always ready
-                self.issue_alu_instruction(instruction, dst, Some(src), None, pc, cpu)
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), None, pc, cpu)
+            },
+
+            // Plain moves reach the whole 10-bit register space on one side
+            Instruction::Move { dst, src } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src), None, pc, cpu)
+            },
+
+            Instruction::MoveWide { dst, src } => {
+                self.issue_alu_instruction(instruction, dst, Some(src.into()), None, pc, cpu)
             },
 
             Instruction::Not { dst, src } => {
-                self.issue_alu_instruction(instruction, dst, Some(src), None, pc, cpu)
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), None, pc, cpu)
+            },
+
+            Instruction::CountLeadingZeros { dst, src } |
+            Instruction::CountTrailingZeros { dst, src } |
+            Instruction::Popcount { dst, src } |
+            Instruction::ByteSwap { dst, src } |
+            Instruction::BitSet { dst, src, .. } |
+            Instruction::BitClear { dst, src, .. } |
+            Instruction::BitTest { dst, src, .. } |
+            Instruction::BitFieldExtract { dst, src, .. } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), None, pc, cpu)
+            },
+
+            // Insert merges into the old destination value, which is the second operand
+            Instruction::BitFieldInsert { dst, src, .. } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), Some(dst.into()), pc, cpu)
+            },
+
+            Instruction::SatAdd { dst, src1, src2 } |
+            Instruction::SatSub { dst, src1, src2 } |
+            Instruction::Min { dst, src1, src2 } |
+            Instruction::Max { dst, src1, src2 } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src1.into()), Some(src2.into()), pc, cpu)
+            },
+
+            Instruction::Abs { dst, src } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), None, pc, cpu)
+            },
+
+            Instruction::Q15Mult { .. } |
+            Instruction::MultAcc { .. } |
+            Instruction::MultWide { .. } |
+            Instruction::MultWideSigned { .. } => {
+                self.issue_mac_instruction(instruction, pc, cpu)
+            },
+
+            Instruction::ShiftLeftReg { dst, src, amount } |
+            Instruction::ShiftRightReg { dst, src, amount } |
+            Instruction::ShiftRightArithReg { dst, src, amount } |
+            Instruction::RotateLeftReg { dst, src, amount } |
+            Instruction::RotateRightReg { dst, src, amount } => {
+                self.issue_alu_instruction(instruction, dst.into(), Some(src.into()), Some(amount.into()), pc, cpu)
+            },
+
+            // Constants have no register sources, the value travels as the immediate
+            Instruction::LoadImm { dst, .. } |
+            Instruction::LoadUpperImm { dst, .. } => {
+                self.issue_alu_instruction(instruction, dst.into(), None, None, pc, cpu)
             },
 
             Instruction::Load { dst, addr } => {
-                self.issue_load_instruction(instruction, dst, addr, pc, cpu)
+                self.issue_load_instruction(instruction, dst, None, addr, pc, cpu)
+            },
+
+            Instruction::LoadIndexed { dst, base, .. } |
+            Instruction::LoadByte { dst, base, .. } |
+            Instruction::LoadByteSigned { dst, base, .. } => {
+                self.issue_load_instruction(instruction, dst, Some(base), 0, pc, cpu)
             },
 
             Instruction::Store { src, addr } => {
-                self.issue_store_instruction(instruction, src, addr, pc, cpu)
+                self.issue_store_instruction(instruction, src, None, addr, pc, cpu)
+            },
+
+            Instruction::StoreIndexed { src, base, .. } |
+            Instruction::StoreByte { src, base, .. } => {
+                self.issue_store_instruction(instruction, src, Some(base), 0, pc, cpu)
+            },
+
+            Instruction::LoadPostInc { .. } |
+            Instruction::StorePostInc { .. } |
+            Instruction::LoadBytePostInc { .. } |
+            Instruction::LoadByteSignedPostInc { .. } |
+            Instruction::StoreBytePostInc { .. } => {
+                self.issue_post_increment_instruction(instruction, pc, cpu, mem)
+            },
+
+            // Branches and jumps are resolved by the branch unit
+            Instruction::Jump { .. } |
+            Instruction::JumpReg { .. } |
+            Instruction::JumpRel { .. } |
+            Instruction::BranchEqual { .. } |
+            Instruction::BranchNotEqual { .. } |
+            Instruction::BranchLessThan { .. } |
+            Instruction::BranchGreaterThan { .. } |
+            Instruction::BranchEqualRel { .. } |
+            Instruction::BranchNotEqualRel { .. } |
+            Instruction::BranchLessThanRel { .. } |
+            Instruction::BranchGreaterThanRel { .. } |
+            Instruction::BranchEqual32 { .. } |
+            Instruction::BranchNotEqual32 { .. } |
+            Instruction::BranchLessThan32 { .. } |
+            Instruction::BranchGreaterThan32 { .. } => {
+                self.issue_branch_instruction(instruction, pc, cpu)
             },
 
             _ => {
-                // For unsupported instructions, fall back to in-order execution
-                crate::core::execute::execute(instruction, cpu, &mut mem);
+                // No functional unit for this instruction (e.g. it reads the flags): execute it
+                // in-order at issue, once everything older has committed to architectural state
+                if !cpu.reorder_buffer.is_empty() {
+                    return false;
+                }
+                let fetch_pc = cpu.pc;
+                let bank = mem.code_bank;
+                cpu.pc = pc;
+                crate::core::execute::execute(instruction, cpu, mem);
+                if cpu.pc == pc && mem.code_bank == bank {
+                    cpu.pc = fetch_pc;
+                } else {
+                    // Control transfer (a trap, ReturnFromException or a far jump, which may
+                    // land on the same address in another bank): drop the fall-through fetches
+                    self.drop_fetched(1);
+                }
+                self.instructions += 1;
                 true
             }
         }
     }
 
-    fn issue_alu_instruction(&mut self, instruction: Instruction, dst: u8, src1: Option<u8>, src2: Option<u8>, pc: u16, cpu: &mut CpuState) -> bool {
+    fn issue_alu_instruction(&mut self, instruction: Instruction, dst: u16, src1: Option<u16>, src2: Option<u16>, pc: u16, cpu: &mut CpuState) -> bool {
+        // Sources are read before the destination is renamed, which may be one of them
+        let (vj, qj) = match src1 {
+            Some(src1) => self.source_operand(cpu, src1),
+            None => (Some(0), None), // No source needed
+        };
+        let (vk, qk) = match src2 {
+            Some(src2) => self.source_operand(cpu, src2),
+            // Handle immediate values or single-operand instructions
+            None => (Some(self.get_immediate_value(&instruction)), None),
+        };
+        if !self.registers_available(cpu, 1) {
+            return false;
+        }
+
+        if let (Some(vj), Some(vk)) = (vj, vk) {
+            let reused = self.reuse_buffer.as_mut().and_then(|buffer| buffer.lookup(pc, instruction, (vj, vk)));
+            if let Some(result) = reused {
+                return self.issue_reused(instruction, dst, (vj, vk), result, pc, cpu);
+            }
+        }
+
         // Check if we can allocate a reservation station
         if let Some(rs) = cpu.reservation_stations.find_free_alu_station() {
             // Check if we can allocate a ROB entry
+            let dst = cpu.regs.physical(dst);
             if let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, Some(dst), pc) {
                 rs.busy = true;
                 rs.op = Some(instruction);
                 rs.tag = rob_tag;
-                rs.cycles_remaining = self.get_execution_cycles(&instruction);
-
-                // Handle first source operand
-                if let Some(src1) = src1 {
-                    let (ready, producer_tag) = cpu.rename_table.get_register_info(src1);
-                    if ready {
-                        rs.vj = Some(cpu.regs.read(src1));
-                        rs.qj = None;
-                    } else {
-                        rs.vj = None;
-                        rs.qj = producer_tag;
-                    }
-                } else {
-                    rs.vj = Some(0); // No source needed
-                    rs.qj = None;
-                }
-
-                // Handle second source operand
-                if let Some(src2) = src2 {
-                    let (ready, producer_tag) = cpu.rename_table.get_register_info(src2);
-                    if ready {
-                        rs.vk = Some(cpu.regs.read(src2));
-                        rs.qk = None;
-                    } else {
-                        rs.vk = None;
-                        rs.qk = producer_tag;
-                    }
-                } else {
-                    // Handle immediate values or single-operand instructions
-                    rs.vk = Some(self.get_immediate_value(&instruction));
-                    rs.qk = None;
-                }
+                rs.cycles_remaining = cpu.unit_latencies.latency(&instruction);
+                rs.vj = vj;
+                rs.qj = qj;
+                rs.vk = vk;
+                rs.qk = qk;
 
                 // Rename the destination register
-                cpu.rename_table.rename_register(dst, rob_tag);
+                self.rename_destination(cpu, dst, rob_tag);
+                self.predict_value(cpu, dst, pc, rob_tag);
 
                 return true;
             }
@@ -482,53 +1035,571 @@ impl PipelineController {
         false
     }
 
-    fn issue_load_instruction(&mut self, instruction: Instruction, dst: u8, addr: u16, pc: u16, cpu: &mut CpuState) -> bool {
+    // An ALU instruction whose result is in the reuse buffer completes as it issues,
+    // without a reservation station
+    fn issue_reused(&mut self, instruction: Instruction, dst: u16, (vj, vk): (u16, u16), result: u16, pc: u16, cpu: &mut CpuState) -> bool {
+        let dst = cpu.regs.physical(dst);
+        let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, Some(dst), pc) else {
+            return false;
+        };
+        cpu.reorder_buffer.complete(rob_tag, Some(result));
+        cpu.reorder_buffer.set_flags(rob_tag, compute_flags(&instruction, vj, vk, result));
+        self.rename_destination(cpu, dst, rob_tag);
+        self.rename_complete(cpu, rob_tag, result);
+        true
+    }
+
+    // Address operand is either an absolute address or the value of a base register
+    fn issue_load_instruction(&mut self, instruction: Instruction, dst: u8, base: Option<u8>, addr: u16, pc: u16, cpu: &mut CpuState) -> bool {
+        let (vj, qj) = match base {
+            Some(base) => self.source_operand(cpu, base.into()),
+            None => (Some(addr), None),
+        };
+        if !self.registers_available(cpu, 1) {
+            return false;
+        }
+
         if let Some(rs) = cpu.reservation_stations.find_free_load_station() {
+            let dst = cpu.regs.physical(dst.into());
             if let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, Some(dst), pc) {
                 rs.busy = true;
                 rs.op = Some(instruction);
                 rs.tag = rob_tag;
-                rs.cycles_remaining = 2; // Load takes 2 cycles
-                rs.vj = Some(addr);
+                rs.cycles_remaining = cpu.unit_latencies.latency(&instruction);
+                rs.vj = vj;
+                rs.qj = qj;
                 rs.vk = Some(0); // Not used for loads
-                rs.qj = None;
                 rs.qk = None;
+                if cpu.memory_dependence.policy == LoadSpeculation::StoreSets {
+                    rs.store_dependence = cpu.memory_dependence.store_sets.dependence(pc);
+                    if rs.store_dependence.is_some() {
+                        cpu.memory_dependence.stats.predicted_dependences += 1;
+                    }
+                }
 
-                cpu.rename_table.rename_register(dst, rob_tag);
+                self.rename_destination(cpu, dst, rob_tag);
+                self.predict_value(cpu, dst, pc, rob_tag);
                 return true;
             }
         }
         false
     }
 
-    fn issue_store_instruction(&mut self, instruction: Instruction, src: u8, addr: u16, pc: u16, cpu: &mut CpuState) -> bool {
+    fn issue_store_instruction(&mut self, instruction: Instruction, src: u8, base: Option<u8>, addr: u16, pc: u16, cpu: &mut CpuState) -> bool {
+        let (vj, qj) = self.source_operand(cpu, src.into());
+        let (vk, qk) = match base {
+            Some(base) => self.source_operand(cpu, base.into()),
+            None => (Some(addr), None),
+        };
+
         if let Some(rs) = cpu.reservation_stations.find_free_store_station() {
             if let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, None, pc) {
                 rs.busy = true;
                 rs.op = Some(instruction);
                 rs.tag = rob_tag;
-                rs.cycles_remaining = 1; // Store takes 1 cycle to compute address
+                rs.cycles_remaining = cpu.unit_latencies.latency(&instruction);
+                rs.vj = vj;
+                rs.qj = qj;
+                rs.vk = vk;
+                rs.qk = qk;
 
-                let (ready, producer_tag) = cpu.rename_table.get_register_info(src);
-                if ready {
-                    rs.vj = Some(cpu.regs.read(src));
-                    rs.qj = None;
-                } else {
-                    rs.vj = None;
-                    rs.qj = producer_tag;
-                }
+                cpu.memory_dependence.store_sets.store_issued(pc, rob_tag);
+                return true;
+            }
+        }
+        false
+    }
 
-                rs.vk = Some(addr);
-                rs.qk = None;
+    fn issue_mac_instruction(&mut self, instruction: Instruction, pc: u16, cpu: &mut CpuState) -> bool {
+        use crate::isa::Instruction;
+
+        let (dst, src1, src2, acc, pair) = match instruction {
+            Instruction::Q15Mult { dst, src1, src2 } => (dst, src1, src2, None, false),
+            Instruction::MultAcc { acc, src1, src2 } => (acc, src1, src2, Some(acc), true),
+            Instruction::MultWide { dst, src1, src2 } |
+            Instruction::MultWideSigned { dst, src1, src2 } => (dst, src1, src2, None, true),
+            _ => return false,
+        };
+
+        let mut operands = [
+            self.source_operand(cpu, src1.into()),
+            self.source_operand(cpu, src2.into()),
+            (Some(0), None),
+            (Some(0), None),
+        ];
+        if let Some(acc) = acc {
+            operands[2] = self.source_operand(cpu, acc.into());
+            operands[3] = self.source_operand(cpu, acc.wrapping_add(1).into());
+        }
+
+        // Pair results need two consecutive ROB entries
+        let rob_entries = if pair { 2 } else { 1 };
+        if cpu.reorder_buffer.size - cpu.reorder_buffer.count < rob_entries || !self.registers_available(cpu, rob_entries) {
+            return false;
+        }
+        let cycles = cpu.unit_latencies.latency(&instruction);
 
+        let dst_lo = cpu.regs.physical(dst.into());
+        let dst_hi = cpu.regs.physical(dst.wrapping_add(1).into());
+        if let Some(rs) = cpu.reservation_stations.find_free_mac_station() {
+            if let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, Some(dst_lo), pc) {
+                rs.busy = true;
+                rs.op = Some(instruction);
+                rs.tag = rob_tag;
+                rs.cycles_remaining = cycles;
+                for (i, (value, producer)) in operands.iter().enumerate() {
+                    rs.values[i] = *value;
+                    rs.producers[i] = *producer;
+                }
+                let hi_tag = if pair { cpu.reorder_buffer.allocate(instruction, Some(dst_hi), pc) } else { None };
+                rs.tag_hi = hi_tag;
+                cpu.reorder_buffer.entries[rob_tag].cracked_with = hi_tag;
+
+                self.rename_destination(cpu, dst_lo, rob_tag);
+                if let Some(hi_tag) = hi_tag {
+                    self.rename_destination(cpu, dst_hi, hi_tag);
+                }
                 return true;
             }
         }
         false
     }
 
+    // Post-increment accesses are cracked into the access itself followed by an
+    // AddImm on the base register; both halves issue in the same cycle or not at all
+    fn issue_post_increment_instruction(&mut self, instruction: Instruction, pc: u16, cpu: &mut CpuState, mem: &mut Memory) -> bool {
+        use crate::isa::Instruction;
+
+        let (access, base, size) = match instruction {
+            Instruction::LoadPostInc { dst, base } => (Instruction::LoadIndexed { dst, base, offset: 0 }, base, 2),
+            Instruction::StorePostInc { src, base } => (Instruction::StoreIndexed { src, base, offset: 0 }, base, 2),
+            Instruction::LoadBytePostInc { dst, base } => (Instruction::LoadByte { dst, base, offset: 0 }, base, 1),
+            Instruction::LoadByteSignedPostInc { dst, base } => (Instruction::LoadByteSigned { dst, base, offset: 0 }, base, 1),
+            Instruction::StoreBytePostInc { src, base } => (Instruction::StoreByte { src, base, offset: 0 }, base, 1),
+            _ => return false,
+        };
+
+        let stations = &cpu.reservation_stations;
+        let access_station_free = if is_load(&access) {
+            stations.load_stations.iter().any(|rs| !rs.busy)
+        } else {
+            stations.store_stations.iter().any(|rs| !rs.busy)
+        };
+        let alu_station_free = stations.alu_stations.iter().any(|rs| !rs.busy);
+        let rob_space = cpu.reorder_buffer.size - cpu.reorder_buffer.count >= 2;
+        if !(access_station_free && alu_station_free && rob_space) {
+            return false;
+        }
+        // The loaded value and the new base each take a register
+        if !self.registers_available(cpu, if is_load(&access) { 2 } else { 1 }) {
+            return false;
+        }
+
+        let increment = Instruction::AddImm { dst: base, src: base, imm: size };
+        let first = cpu.reorder_buffer.tail;
+        if !(self.try_issue_instruction(access, pc, cpu, mem) && self.try_issue_instruction(increment, pc, cpu, mem)) {
+            return false;
+        }
+        let rob = &mut cpu.reorder_buffer;
+        rob.entries[first].cracked_with = Some((first + 1) % rob.size);
+        true
+    }
+
+    // Branch unit: resolve the branch once its operands are available, then redirect
+    // fetch and drop the instructions already fetched down the fall-through path.
+    // A conditional branch whose operands are still pending is predicted not taken.
+    fn issue_branch_instruction(&mut self, instruction: Instruction, pc: u16, cpu: &mut CpuState) -> bool {
+        let operands = self.branch_operands(cpu, &instruction);
+        if operands.iter().all(|(value, _)| value.is_some()) {
+            let values = operands.map(|(value, _)| value.unwrap_or(0));
+            if let Some(target) = branch_target(&instruction, pc, values) {
+                // Keep the branch itself at the head of the queue, the caller removes it
+                self.drop_fetched(1);
+                cpu.pc = target;
+            }
+            // Never takes a ROB entry, so it retires with the youngest one in flight, or
+            // here when there is none. A squash of that entry takes the branch with it.
+            let rob = &mut cpu.reorder_buffer;
+            if rob.is_empty() {
+                self.instructions += 1;
+            } else {
+                let youngest = (rob.tail + rob.size - 1) % rob.size;
+                rob.entries[youngest].branches += 1;
+            }
+            return true;
+        }
+
+        // A register jump is always taken, so there is nothing to predict
+        if matches!(instruction, Instruction::JumpReg { .. }) {
+            return false;
+        }
+        self.speculate_branch(instruction, pc, operands, cpu)
+    }
+
+    // Source operands of a branch as (value, producer) pairs in BranchStation order. 32-bit
+    // compares read register pairs: the high halves come from the next registers.
+    fn branch_operands(&self, cpu: &CpuState, instruction: &Instruction) -> [(Option<u16>, Option<usize>); 4] {
+        use crate::isa::Instruction;
+
+        let pair = matches!(
+            instruction,
+            Instruction::BranchEqual32 { .. } | Instruction::BranchNotEqual32 { .. } |
+            Instruction::BranchLessThan32 { .. } | Instruction::BranchGreaterThan32 { .. }
+        );
+
+        let (src1, src2) = match *instruction {
+            Instruction::JumpReg { reg } => (Some(reg), None),
+            Instruction::BranchEqual32 { src1, src2, .. } |
+            Instruction::BranchNotEqual32 { src1, src2, .. } |
+            Instruction::BranchLessThan32 { src1, src2, .. } |
+            Instruction::BranchGreaterThan32 { src1, src2, .. } => (Some(src1.into()), Some(src2.into())),
+            Instruction::BranchEqual { src1, src2, .. } |
+            Instruction::BranchNotEqual { src1, src2, .. } |
+            Instruction::BranchLessThan { src1, src2, .. } |
+            Instruction::BranchGreaterThan { src1, src2, .. } |
+            Instruction::BranchEqualRel { src1, src2, .. } |
+            Instruction::BranchNotEqualRel { src1, src2, .. } |
+            Instruction::BranchLessThanRel { src1, src2, .. } |
+            Instruction::BranchGreaterThanRel { src1, src2, .. } => (Some(src1.into()), Some(src2)),
+            _ => (None, None),
+        };
+
+        let mut operands = [(Some(0), None); 4];
+        if let Some(src1) = src1 {
+            operands[0] = self.source_operand(cpu, src1);
+            if pair {
+                operands[2] = self.source_operand(cpu, (src1 as u8).wrapping_add(1).into());
+            }
+        }
+        if let Some(src2) = src2 {
+            operands[1] = self.source_operand(cpu, src2);
+            if pair {
+                operands[3] = self.source_operand(cpu, (src2 as u8).wrapping_add(1).into());
+            }
+        }
+        operands
+    }
+
+    // Park the branch in a branch station with a ROB entry marking its place in program
+    // order, and keep issuing down the fall-through path. With physical registers the
+    // rename state is checkpointed so a misprediction can restore it at once.
+    fn speculate_branch(&mut self, instruction: Instruction, pc: u16, operands: [(Option<u16>, Option<usize>); 4], cpu: &mut CpuState) -> bool {
+        let checkpointed = cpu.rename_scheme == RenameScheme::PhysicalRegisters;
+        if checkpointed && !cpu.physical_registers.can_checkpoint() {
+            cpu.physical_registers.stats.checkpoint_stalls += 1;
+            return false;
+        }
+
+        let Some(rs) = cpu.reservation_stations.find_free_branch_station() else {
+            return false;
+        };
+        let Some(rob_tag) = cpu.reorder_buffer.allocate(instruction, None, pc) else {
+            return false;
+        };
+        rs.busy = true;
+        rs.op = Some(instruction);
+        rs.pc = pc;
+        rs.tag = rob_tag;
+        for (i, (value, producer)) in operands.iter().enumerate() {
+            rs.values[i] = *value;
+            rs.producers[i] = *producer;
+        }
+
+        if checkpointed {
+            cpu.physical_registers.checkpoint(rob_tag);
+        }
+        self.speculated_branches += 1;
+        true
+    }
+
+    // Resolve the oldest speculated branch whose operands have arrived. Taken means it was
+    // mispredicted: everything issued after it is squashed and fetch restarts at the target.
+    pub fn resolve_branch_stage(&mut self, cpu: &mut CpuState) {
+        let rob = &cpu.reorder_buffer;
+        let thread = cpu.reservation_stations.thread;
+        let Some(rs) = cpu
+            .reservation_stations
+            .branch_stations
+            .iter_mut()
+            .filter(|rs| rs.thread == thread && rs.is_ready())
+            .min_by_key(|rs| rob.age(rs.tag)) else {
+            return;
+        };
+        let (op, tag, pc) = (rs.op, rs.tag, rs.pc);
+        let values = rs.values.map(|v| v.unwrap_or(0));
+        rs.clear();
+        let Some(instruction) = op else {
+            return;
+        };
+
+        cpu.reorder_buffer.complete(tag, None);
+        match branch_target(&instruction, pc, values) {
+            Some(target) => {
+                self.mispredicted_branches += 1;
+                let keep = cpu.reorder_buffer.age(tag) + 1;
+                // Branches resolved at issue behind it were on the wrong path
+                cpu.reorder_buffer.entries[tag].branches = 0;
+                if cpu.physical_registers.restore(tag) {
+                    self.discard(cpu, keep);
+                } else {
+                    self.squash(cpu, keep);
+                }
+                cpu.pc = target;
+            }
+            None => cpu.physical_registers.release(tag),
+        }
+    }
+
+    // Drop every ROB entry but the `keep` oldest, walking the physical register file back
+    // through their renames youngest first; returns how many were dropped
+    fn squash(&mut self, cpu: &mut CpuState, keep: usize) -> usize {
+        if cpu.rename_scheme == RenameScheme::PhysicalRegisters {
+            let rob = &cpu.reorder_buffer;
+            for i in (keep..rob.count).rev() {
+                let tag = (rob.head + i) % rob.size;
+                let entry = &rob.entries[tag];
+                if let (Some(reg), Some((new, previous))) = (entry.dest_reg, entry.prf_dest) {
+                    cpu.physical_registers.unwind(reg, new, previous);
+                }
+                cpu.physical_registers.release(tag);
+            }
+        }
+        self.discard(cpu, keep)
+    }
+
+    // Drop every ROB entry but the `keep` oldest along with their stations and everything
+    // fetched after them. The ROB-tag table is rebuilt from the entries left; the physical
+    // register file has already been restored by the caller.
+    fn discard(&mut self, cpu: &mut CpuState, keep: usize) -> usize {
+        if let Some(predictor) = self.value_predictor.as_mut() {
+            let rob = &cpu.reorder_buffer;
+            for i in keep..rob.count {
+                let entry = &rob.entries[(rob.head + i) % rob.size];
+                if let Some(reg) = entry.dest_reg {
+                    predictor.squashed(entry.pc, reg);
+                }
+            }
+        }
+        let squashed = cpu.reorder_buffer.truncate(keep);
+        self.squashed += squashed as u64;
+        let rob = &cpu.reorder_buffer;
+        cpu.reservation_stations.squash(|tag| rob.entries[tag].valid);
+        self.drop_fetched(0);
+        if cpu.rename_scheme == RenameScheme::RobTags {
+            cpu.rename_table.rebuild(&cpu.reorder_buffer);
+        }
+        squashed
+    }
+
+    // A confident value prediction makes the result of ROB entry `tag` available to
+    // dependents as it issues, the same way the CDB would
+    fn predict_value(&mut self, cpu: &mut CpuState, reg: u16, pc: u16, tag: usize) {
+        let Some(value) = self.value_predictor.as_mut().and_then(|predictor| predictor.predict(pc, reg)) else {
+            return;
+        };
+        if let Some(entry) = cpu.reorder_buffer.entries.get_mut(tag) {
+            entry.predicted = Some(value);
+        }
+        self.rename_complete(cpu, tag, value);
+    }
+
+    // Train the value predictor on a result and check the prediction made for it. A wrong
+    // value may have reached anything younger, including branches resolved at issue, so
+    // all of it is squashed and fetch restarts after the instruction. The rest of a cracked
+    // instruction stays with it.
+    fn verify_value(&mut self, cpu: &mut CpuState, tag: usize, value: u16) {
+        let entry = &cpu.reorder_buffer.entries[tag];
+        let (Some(reg), pc, predicted) = (entry.dest_reg, entry.pc, entry.predicted) else {
+            return;
+        };
+        let Some(predictor) = self.value_predictor.as_mut() else {
+            return;
+        };
+        predictor.train(pc, reg, value);
+        match predicted {
+            Some(predicted) if predicted == value => predictor.stats.correct += 1,
+            Some(_) => {
+                predictor.stats.mispredicted += 1;
+                let rob = &mut cpu.reorder_buffer;
+                let last = rob.entries[tag].cracked_with.unwrap_or(tag);
+                let keep = rob.age(last) + 1;
+                // Branches resolved at issue behind it are fetched again
+                rob.entries[last].branches = 0;
+                let squashed = self.squash(cpu, keep);
+                if let Some(predictor) = self.value_predictor.as_mut() {
+                    predictor.stats.squashed += squashed as u64;
+                }
+                cpu.pc = pc.wrapping_add(4);
+            }
+            None => {}
+        }
+    }
+
+    // Whether there are registers to rename `count` results; only the physical register
+    // file can run out, the ROB-tag scheme is bounded by the ROB alone
+    fn registers_available(&self, cpu: &mut CpuState, count: usize) -> bool {
+        if cpu.rename_scheme == RenameScheme::PhysicalRegisters && !cpu.physical_registers.can_allocate(count) {
+            cpu.physical_registers.stats.free_list_stalls += 1;
+            return false;
+        }
+        true
+    }
+
+    // Make ROB entry `tag` the producer of (physical index) register `reg`
+    fn rename_destination(&self, cpu: &mut CpuState, reg: u16, tag: usize) {
+        match cpu.rename_scheme {
+            RenameScheme::RobTags => cpu.rename_table.rename_register(reg, tag),
+            RenameScheme::PhysicalRegisters => {
+                let mapping = cpu.physical_registers.rename(reg, tag);
+                if let Some(entry) = cpu.reorder_buffer.entries.get_mut(tag) {
+                    entry.prf_dest = mapping;
+                }
+            }
+        }
+    }
+
+    // A result on the CDB: mark the register ready, writing it to the physical register
+    // file when results live there
+    fn rename_complete(&self, cpu: &mut CpuState, tag: usize, value: u16) {
+        match cpu.rename_scheme {
+            RenameScheme::RobTags => cpu.rename_table.update_from_cdb(tag),
+            RenameScheme::PhysicalRegisters => {
+                if let Some((preg, _)) = cpu.reorder_buffer.entries.get(tag).and_then(|entry| entry.prf_dest) {
+                    cpu.physical_registers.write(preg, value);
+                }
+            }
+        }
+    }
+
+    // Operand for a reservation station: (value, None) when available,
+    // otherwise (None, tag of the ROB entry that will produce it)
+    fn source_operand(&self, cpu: &CpuState, reg: u16) -> (Option<u16>, Option<usize>) {
+        if cpu.rename_scheme == RenameScheme::PhysicalRegisters {
+            return cpu.physical_registers.lookup(cpu.regs.physical(reg));
+        }
+        let (ready, producer_tag) = cpu.rename_table.get_register_info(cpu.regs.physical(reg));
+        if ready {
+            (self.operand_value(cpu, reg), None)
+        } else {
+            (None, producer_tag)
+        }
+    }
+
+    // Conservative loads may not bypass stores: they wait until every older store has committed
+    fn older_store_pending(&self, cpu: &CpuState, tag: usize) -> bool {
+        let rob = &cpu.reorder_buffer;
+        (0..rob.age(tag)).any(|i| {
+            let entry = &rob.entries[(rob.head + i) % rob.size];
+            entry.valid && entry.instruction.as_ref().is_some_and(is_store)
+        })
+    }
+
+    // Where the load in ROB entry `tag` can get its value from this cycle, and whether
+    // that is speculative. Older stores are checked youngest first: one with a known
+    // address that overlaps forwards its value when it wrote exactly what the load reads
+    // and holds the load back otherwise; one whose address is unknown is passed unless the
+    // policy (or the store set predictor) says to wait for it.
+    fn load_source(&self, cpu: &CpuState, mem: &Memory, tag: usize, instruction: &Instruction, addr: u16) -> (LoadSource, bool) {
+        let policy = cpu.memory_dependence.policy;
+        if policy == LoadSpeculation::Conservative {
+            let source = if self.older_store_pending(cpu, tag) { LoadSource::Wait } else { LoadSource::Memory };
+            return (source, false);
+        }
+
+        let dependence = cpu
+            .reservation_stations
+            .load_stations
+            .iter()
+            .find(|rs| rs.busy && rs.thread == cpu.reservation_stations.thread && rs.tag == tag)
+            .and_then(|rs| rs.store_dependence);
+        // Virtual addresses alias across banks and page mappings, so compare the storage
+        // they reach. An unmapped load goes on to fault when it is translated.
+        let size = access_size(instruction);
+        let Some(location) = location(cpu, mem, addr, size) else {
+            return (LoadSource::Memory, false);
+        };
+        let rob = &cpu.reorder_buffer;
+        let mut speculative = false;
+        for i in (0..rob.age(tag)).rev() {
+            let store = (rob.head + i) % rob.size;
+            let entry = &rob.entries[store];
+            let Some(store_instruction) = entry.instruction.filter(|inst| entry.valid && is_store(inst)) else {
+                continue;
+            };
+            let Some(store_location) = entry.location else {
+                if policy == LoadSpeculation::StoreSets && dependence == Some(store) {
+                    return (LoadSource::Wait, speculative);
+                }
+                speculative = true;
+                continue;
+            };
+            let store_size = access_size(&store_instruction);
+            if overlaps(location, store_location) {
+                return match entry.result {
+                    Some(value) if store_location == location && store_size == size && entry.exception.is_none() => {
+                        (LoadSource::Forward { store, value }, speculative)
+                    }
+                    _ => (LoadSource::Wait, speculative),
+                };
+            }
+        }
+        (LoadSource::Memory, speculative)
+    }
+
+    // A store has just computed its address: any younger load that already read an
+    // overlapping location, and did not get its value from a store younger than this
+    // one, read stale data. The oldest such load is replayed with everything after it,
+    // and the store set predictor learns the pair.
+    fn check_memory_order(&mut self, cpu: &mut CpuState, store: usize) {
+        let rob = &cpu.reorder_buffer;
+        let entry = &rob.entries[store];
+        let (Some(_), Some(store_location)) = (entry.instruction, entry.location) else {
+            return;
+        };
+        let store_pc = entry.pc;
+        let store_age = rob.age(store);
+
+        let violation = (store_age + 1..rob.count).map(|i| (rob.head + i) % rob.size).find(|&load| {
+            let entry = &rob.entries[load];
+            match (entry.instruction, entry.location) {
+                (Some(instruction), Some(location)) => {
+                    entry.valid && entry.ready && entry.exception.is_none() && is_load(&instruction) &&
+                        overlaps(location, store_location) &&
+                        entry.forwarded_from.is_none_or(|source| rob.age(source) < store_age)
+                }
+                _ => false,
+            }
+        });
+        let Some(load) = violation else {
+            return;
+        };
+
+        let load_pc = rob.entries[load].pc;
+        let keep = rob.age(load);
+        cpu.memory_dependence.store_sets.violation(store_pc, load_pc);
+        cpu.memory_dependence.stats.violations += 1;
+        let replayed = self.squash(cpu, keep);
+        cpu.memory_dependence.stats.replayed += replayed as u64;
+        cpu.pc = load_pc;
+    }
+
+    // Latest value of an architectural register: the youngest in-flight ROB entry
+    // writing it if there is one, otherwise the register file. None while pending.
+    fn operand_value(&self, cpu: &CpuState, reg: u16) -> Option<u16> {
+        let reg = cpu.regs.physical(reg);
+        let rob = &cpu.reorder_buffer;
+        for i in (0..rob.count).rev() {
+            let entry = &rob.entries[(rob.head + i) % rob.size];
+            if entry.valid && entry.dest_reg == Some(reg) {
+                return if entry.ready { entry.result } else { entry.predicted };
+            }
+        }
+        Some(cpu.regs.read_physical(reg))
+    }
+
     // Execute Stage: Execute ready instructions in parallel functional units
-    fn execute_stage(&mut self, cpu: &mut CpuState) {
+    // Shared by every SMT thread: it only counts down the functional units
+    pub fn execute_stage(&mut self, cpu: &mut CpuState) {
         // Decrement cycles for all busy reservation stations
         for rs in &mut cpu.reservation_stations.alu_stations {
             if rs.busy && rs.cycles_remaining > 0 {
@@ -547,49 +1618,279 @@ impl PipelineController {
                 rs.cycles_remaining -= 1;
             }
         }
+
+        // The MAC unit only starts counting once all of its operands have arrived
+        for rs in &mut cpu.reservation_stations.mac_stations {
+            if rs.is_ready() && rs.cycles_remaining > 0 {
+                rs.cycles_remaining -= 1;
+            }
+        }
+
+        // Stations still waiting on the CDB for an operand
+        let stations = &cpu.reservation_stations;
+        let waiting = stations.alu_stations.iter()
+            .chain(&stations.load_stations)
+            .chain(&stations.store_stations)
+            .filter(|rs| rs.busy && !rs.is_ready())
+            .count()
+            + stations.mac_stations.iter().filter(|rs| rs.busy && !rs.is_ready()).count();
+        cpu.unit_stats.raw_stalls += waiting as u64;
     }
 
     // Write Result Stage: Broadcast completed results via Common Data Bus
-    fn writeback_stage(&mut self, cpu: &mut CpuState) {
+    pub fn writeback_stage(&mut self, cpu: &mut CpuState, mem: &Memory) {
         cpu.common_data_bus.clear();
 
+        // A pending high half goes ahead of newer results
+        if self.writeback_mac_high(cpu) {
+            let (tag, value) = (cpu.common_data_bus.tag, cpu.common_data_bus.value);
+            cpu.reservation_stations.update_from_cdb(tag, value);
+            self.rename_complete(cpu, tag, value);
+            return;
+        }
+
         // Find a completed instruction to write back
         let ready_instructions = cpu.reservation_stations.get_ready_instructions();
+        let mut load = (LoadSource::Memory, false);
+        let next = ready_instructions.iter().find(|(tag, inst, vj, _)| {
+            if !is_load(inst) {
+                return true;
+            }
+            load = self.load_source(cpu, mem, *tag, inst, self.memory_address(inst, *vj));
+            load.0 != LoadSource::Wait
+        });
+
+        let next = next
+            .copied()
+            .filter(|(tag, inst, vj, vk)| self.translate_memory_access(cpu, mem, *tag, inst, *vj, *vk));
+
+        let mut executed_store = None;
+        let mut verified = None;
+        if let Some((tag, instruction, vj, vk)) = next {
+            // Memory accesses carry their (virtual) address to commit through the ROB
+            if is_load(&instruction) || is_store(&instruction) {
+                let (addr, _) = self.access_of(&instruction, vj, vk);
+                let location = location(cpu, mem, addr, access_size(&instruction));
+                cpu.reorder_buffer.set_address(tag, addr, location);
+            }
 
-        if let Some((tag, instruction, vj, vk)) = ready_instructions.first() {
-            let result = self.compute_result(*instruction, *vj, *vk, cpu);
+            let result = if is_load(&instruction) {
+                let (source, speculative) = load;
+                if speculative {
+                    cpu.memory_dependence.stats.speculative_loads += 1;
+                }
+                match source {
+                    LoadSource::Forward { store, value } => {
+                        cpu.memory_dependence.stats.forwarded += 1;
+                        if let Some(entry) = cpu.reorder_buffer.entries.get_mut(tag) {
+                            entry.forwarded_from = Some(store);
+                        }
+                        forwarded_value(&instruction, value)
+                    }
+                    _ => self.load_value(&instruction, self.memory_address(&instruction, vj), cpu, mem),
+                }
+            } else {
+                let result = self.compute_result(instruction, vj, vk);
+                cpu.reorder_buffer.set_flags(tag, compute_flags(&instruction, vj, vk, result));
+                if let Some(buffer) = self.reuse_buffer.as_mut().filter(|_| !is_store(&instruction)) {
+                    buffer.record(cpu.reorder_buffer.entries[tag].pc, instruction, (vj, vk), result);
+                }
+                result
+            };
+            if is_store(&instruction) {
+                executed_store = Some(tag);
+            } else {
+                verified = Some((tag, result));
+            }
 
             // Broadcast on CDB
-            cpu.common_data_bus.broadcast(*tag, result);
+            cpu.common_data_bus.broadcast(tag, result);
 
             // Update ROB
-            cpu.reorder_buffer.complete(*tag, Some(result));
+            cpu.reorder_buffer.complete(tag, Some(result));
 
             // Clear the reservation station
-            self.clear_reservation_station_by_tag(cpu, *tag);
+            self.clear_reservation_station_by_tag(cpu, tag);
+        } else {
+            self.writeback_mac(cpu);
         }
 
         // Update all components from CDB
         if cpu.common_data_bus.valid {
-            cpu.reservation_stations.update_from_cdb(cpu.common_data_bus.tag, cpu.common_data_bus.value);
-            cpu.rename_table.update_from_cdb(cpu.common_data_bus.tag);
+            let (tag, value) = (cpu.common_data_bus.tag, cpu.common_data_bus.value);
+            cpu.reservation_stations.update_from_cdb(tag, value);
+            self.rename_complete(cpu, tag, value);
+        }
+
+        if let Some((tag, value)) = verified {
+            self.verify_value(cpu, tag, value);
+        }
+
+        if let Some(store) = executed_store {
+            let pc = cpu.reorder_buffer.entries[store].pc;
+            cpu.memory_dependence.store_sets.store_executed(pc, store);
+            if cpu.memory_dependence.policy != LoadSpeculation::Conservative {
+                self.check_memory_order(cpu, store);
+            }
         }
     }
 
+    // Memory accesses translate their address once, when their operands are ready. A page
+    // table walk keeps the access in its station for the walk latency, and a load that
+    // misses in the data cache for the miss latency; a load that finds every MSHR busy
+    // retries each cycle. A faulting access is marked in the ROB and raises the fault when
+    // it reaches the head. Returns whether the instruction can write back this cycle.
+    fn translate_memory_access(&self, cpu: &mut CpuState, mem: &Memory, tag: usize, instruction: &Instruction, vj: u16, vk: u16) -> bool {
+        let Some((translated, waiting)) = cpu
+            .reservation_stations
+            .memory_station(tag)
+            .map(|rs| (rs.translated, rs.waiting_for_mshr)) else {
+            return true;
+        };
+        if translated && !waiting {
+            return true;
+        }
+
+        let (addr, access) = self.access_of(instruction, vj, vk);
+        let len = access_size(instruction);
+        let mut stall = 0;
+        if !translated {
+            match cpu.check_access(mem, addr, len, access) {
+                Ok(walked) => {
+                    if walked {
+                        stall = cpu.mmu.walk_latency * 2; // One read per table level
+                    }
+                }
+                Err(fault) => {
+                    cpu.reorder_buffer.set_exception(tag, fault);
+                    if let Some(rs) = cpu.reservation_stations.memory_station(tag) {
+                        rs.translated = true;
+                    }
+                    return true;
+                }
+            }
+        }
+
+        let pc = cpu.reorder_buffer.entries.get(tag).map_or(0, |entry| entry.pc);
+        let started = if is_load(instruction) { cpu.start_load(mem, pc, addr, len) } else { Some(0) };
+        stall += started.unwrap_or(0);
+        if let Some(rs) = cpu.reservation_stations.memory_station(tag) {
+            rs.translated = true;
+            rs.waiting_for_mshr = started.is_none();
+            rs.cycles_remaining = stall;
+        }
+        stall == 0 && started.is_some()
+    }
+
+    // The MAC unit shares the CDB with the other stations and only writes back when they don't.
+    // The low half of a result pair goes out first; the station keeps the high half.
+    fn writeback_mac(&mut self, cpu: &mut CpuState) {
+        use crate::isa::Instruction;
+
+        let thread = cpu.reservation_stations.thread;
+        let station = cpu
+            .reservation_stations
+            .mac_stations
+            .iter_mut()
+            .find(|rs| rs.thread == thread && rs.is_ready() && rs.cycles_remaining == 0 && rs.high.is_none());
+        let Some(rs) = station else {
+            return;
+        };
+
+        let values = rs.values.map(|v| v.unwrap_or(0));
+        let (tag, tag_hi, op) = (rs.tag, rs.tag_hi, rs.op);
+
+        let (lo, hi, flags) = match op {
+            Some(Instruction::MultAcc { .. }) => {
+                let acc = (values[3] as u32) << 16 | values[2] as u32;
+                let (sum, saturated) = crate::core::alu::mult_acc(acc, values[0], values[1]);
+                (sum as u16, (sum >> 16) as u16, FlagUpdate::from_wide(sum).with_overflow(saturated))
+            },
+            Some(Instruction::MultWide { .. }) => {
+                let product = crate::core::alu::mult_wide(values[0], values[1]);
+                (product as u16, (product >> 16) as u16, FlagUpdate::from_wide(product))
+            },
+            Some(Instruction::MultWideSigned { .. }) => {
+                let product = crate::core::alu::mult_wide_signed(values[0], values[1]);
+                (product as u16, (product >> 16) as u16, FlagUpdate::from_wide(product))
+            },
+            _ => {
+                let (result, saturated) = crate::core::alu::q15_mul(values[0], values[1]);
+                (result, 0, FlagUpdate::from_result(result).with_overflow(saturated))
+            },
+        };
+
+        if tag_hi.is_some() {
+            rs.high = Some(hi);
+        } else {
+            rs.clear();
+        }
+
+        cpu.common_data_bus.broadcast(tag, lo);
+        cpu.reorder_buffer.complete(tag, Some(lo));
+        cpu.reorder_buffer.set_flags(tag, Some(flags));
+    }
+
+    // Broadcast the high half held back from a result pair; returns whether there was one
+    fn writeback_mac_high(&mut self, cpu: &mut CpuState) -> bool {
+        let thread = cpu.reservation_stations.thread;
+        let station = cpu
+            .reservation_stations
+            .mac_stations
+            .iter_mut()
+            .find(|rs| rs.busy && rs.thread == thread && rs.high.is_some());
+        let Some(rs) = station else {
+            return false;
+        };
+
+        let (tag_hi, hi) = (rs.tag_hi, rs.high);
+        rs.clear();
+        if let (Some(tag_hi), Some(hi)) = (tag_hi, hi) {
+            cpu.common_data_bus.broadcast(tag_hi, hi);
+            cpu.reorder_buffer.complete(tag_hi, Some(hi));
+        }
+        true
+    }
+
     // Commit Stage: Update architectural state in program order
-    fn commit_stage(&mut self, cpu: &mut CpuState, mem: &mut Memory) {
+    pub fn commit_stage(&mut self, cpu: &mut CpuState, mem: &mut Memory) {
         if let Some(entry) = cpu.reorder_buffer.commit() {
+            if let Some(exception) = entry.exception {
+                self.take_exception(cpu, entry.pc, exception);
+                return;
+            }
+            if let Some(flags) = entry.flags {
+                flags.apply(&mut cpu.flags);
+            }
+            // An instruction split over several entries retires with its last one
+            if entry.cracked_with.is_none() {
+                self.instructions += 1;
+            }
+            self.instructions += entry.branches;
             if let Some(instruction) = entry.instruction {
                 match instruction {
-                    crate::isa::Instruction::Store { src: _, addr } => {
-                        if let Some(value) = entry.result {
-                            mem.store_u16(addr, value);
+                    crate::isa::Instruction::Store { .. } |
+                    crate::isa::Instruction::StoreIndexed { .. } => {
+                        if let (Some(addr), Some(value)) = (entry.address, entry.result) {
+                            // Stores retire into the data cache without holding up commit
+                            cpu.cache_stall(mem, entry.pc, addr, 2, Access::Write);
+                            cpu.mmu.write_u16(mem, addr, value);
+                        }
+                    },
+                    crate::isa::Instruction::StoreByte { .. } => {
+                        if let (Some(addr), Some(value)) = (entry.address, entry.result) {
+                            cpu.cache_stall(mem, entry.pc, addr, 1, Access::Write);
+                            cpu.mmu.write_u8(mem, addr, value as u8);
                         }
                     },
                     _ => {
                         // Update register file for non-store instructions
                         if let (Some(reg), Some(value)) = (entry.dest_reg, entry.result) {
-                            cpu.regs.write(reg, value);
+                            cpu.regs.write_physical(reg, value);
+                        }
+                        // The previous mapping of the destination can no longer be read
+                        if let (Some(reg), Some((new, previous))) = (entry.dest_reg, entry.prf_dest) {
+                            cpu.physical_registers.commit(reg, new, previous);
                         }
                     }
                 }
@@ -597,52 +1898,102 @@ impl PipelineController {
         }
     }
 
+    // Precise exception: drop everything younger than the faulting instruction and
+    // enter the handler as if it had been the next instruction to execute
+    fn take_exception(&mut self, cpu: &mut CpuState, pc: u16, exception: Exception) {
+        self.flush(cpu);
+        cpu.pc = pc;
+        cpu.raise_exception(exception);
+    }
+
+    fn flush(&mut self, cpu: &mut CpuState) {
+        self.drop_fetched(0);
+        cpu.reorder_buffer.flush();
+        cpu.reservation_stations.flush();
+        cpu.rename_table.reset();
+        cpu.physical_registers.reset();
+        cpu.common_data_bus.clear();
+    }
+
     fn clear_reservation_station_by_tag(&self, cpu: &mut CpuState, tag: usize) {
+        let thread = cpu.reservation_stations.thread;
         for rs in &mut cpu.reservation_stations.alu_stations {
-            if rs.tag == tag && rs.busy {
+            if rs.tag == tag && rs.busy && rs.thread == thread {
                 rs.clear();
                 return;
             }
         }
 
         for rs in &mut cpu.reservation_stations.load_stations {
-            if rs.tag == tag && rs.busy {
+            if rs.tag == tag && rs.busy && rs.thread == thread {
                 rs.clear();
                 return;
             }
         }
 
         for rs in &mut cpu.reservation_stations.store_stations {
-            if rs.tag == tag && rs.busy {
+            if rs.tag == tag && rs.busy && rs.thread == thread {
                 rs.clear();
                 return;
             }
         }
     }
 
-    fn get_execution_cycles(&self, instruction: &Instruction) -> u32 {
+    fn get_immediate_value(&self, instruction: &Instruction) -> u16 {
         use crate::isa::Instruction;
         match instruction {
-            Instruction::Add { .. } | Instruction::Sub { .. } | 
-            Instruction::And { .. } | Instruction::Or { .. } | 
-            Instruction::Xor { .. } | Instruction::Not { .. } |
-            Instruction::AddImm { .. } | Instruction::SubImm { .. } => 1,
+            Instruction::AddImm { imm, .. } | Instruction::SubImm { imm, .. } => *imm as u16,
+            Instruction::AndImm { imm, .. } |
+            Instruction::OrImm { imm, .. } |
+            Instruction::XorImm { imm, .. } => *imm,
+            Instruction::ShiftLeft { amount, .. } |
+            Instruction::ShiftRight { amount, .. } |
+            Instruction::ShiftRightArith { amount, .. } |
+            Instruction::RotateLeft { amount, .. } |
+            Instruction::RotateRight { amount, .. } => *amount,
+            Instruction::BitSet { index, .. } |
+            Instruction::BitClear { index, .. } |
+            Instruction::BitTest { index, .. } => *index as u16,
+            Instruction::LoadImm { value, .. } => *value as u16,
+            Instruction::LoadUpperImm { value, .. } => (*value as u16) << 8,
+            _ => 0,
+        }
+    }
+
+    // Effective address of a load/store from its address operand
+    // (the absolute address itself, or the base register value)
+    fn memory_address(&self, instruction: &Instruction, operand: u16) -> u16 {
+        use crate::isa::{effective_address, Instruction};
+        match instruction {
+            Instruction::LoadIndexed { offset, .. } |
+            Instruction::LoadByte { offset, .. } |
+            Instruction::LoadByteSigned { offset, .. } |
+            Instruction::StoreIndexed { offset, .. } |
+            Instruction::StoreByte { offset, .. } => effective_address(operand, *offset),
+            _ => operand,
+        }
+    }
 
-            Instruction::Load { .. } => 2,
-            Instruction::Store { .. } => 1,
-            _ => 1,
+    // Operands: loads hold the address operand in vj, stores the value in vj and the
+    // address operand in vk
+    fn access_of(&self, instruction: &Instruction, vj: u16, vk: u16) -> (u16, Access) {
+        if is_store(instruction) {
+            (self.memory_address(instruction, vk), Access::Write)
+        } else {
+            (self.memory_address(instruction, vj), Access::Read)
         }
     }
 
-    fn get_immediate_value(&self, instruction: &Instruction) -> u16 {
+    fn load_value(&self, instruction: &Instruction, addr: u16, cpu: &CpuState, mem: &Memory) -> u16 {
         use crate::isa::Instruction;
         match instruction {
-            Instruction::AddImm { imm, .. } | Instruction::SubImm { imm, .. } => *imm as u16,
-            _ => 0,
+            Instruction::LoadByte { .. } => cpu.mmu.read_u8(mem, addr) as u16,
+            Instruction::LoadByteSigned { .. } => cpu.mmu.read_u8(mem, addr) as i8 as u16,
+            _ => cpu.mmu.read_u16(mem, addr),
         }
     }
 
-    fn compute_result(&self, instruction: Instruction, vj: u16, vk: u16, cpu: &CpuState) -> u16 {
+    fn compute_result(&self, instruction: Instruction, vj: u16, vk: u16) -> u16 {
         use crate::isa::Instruction;
         match instruction {
             Instruction::Add { .. } => crate::core::alu::add(vj, vk).0,
@@ -651,13 +2002,176 @@ impl PipelineController {
             Instruction::Or { .. } => crate::core::alu::or(vj, vk),
             Instruction::Xor { .. } => crate::core::alu::xor(vj, vk),
             Instruction::Not { .. } => crate::core::alu::not(vj),
+            Instruction::Move { .. } | Instruction::MoveWide { .. } => vj,
             Instruction::AddImm { imm, .. } => crate::core::alu::add(vj, imm as u16).0,
             Instruction::SubImm { imm, .. } => crate::core::alu::sub(vj, imm as u16).0,
-            Instruction::Load { addr, .. } => {
-                // For loads, vj contains the address
-                cpu.regs.read(0) // Placeholder - should read from memory
+            Instruction::AndImm { .. } => crate::core::alu::and(vj, vk),
+            Instruction::OrImm { .. } => crate::core::alu::or(vj, vk),
+            Instruction::XorImm { .. } => crate::core::alu::xor(vj, vk),
+            Instruction::ShiftLeft { .. } | Instruction::ShiftLeftReg { .. } => {
+                crate::core::alu::shift_left(vj, vk)
+            },
+            Instruction::ShiftRight { .. } | Instruction::ShiftRightReg { .. } => {
+                crate::core::alu::shift_right(vj, vk)
+            },
+            Instruction::ShiftRightArith { .. } | Instruction::ShiftRightArithReg { .. } => {
+                crate::core::alu::shift_right_arith(vj, vk)
+            },
+            Instruction::RotateLeft { .. } | Instruction::RotateLeftReg { .. } => {
+                crate::core::alu::rotate_left(vj, vk)
+            },
+            Instruction::RotateRight { .. } | Instruction::RotateRightReg { .. } => {
+                crate::core::alu::rotate_right(vj, vk)
             },
+            Instruction::CountLeadingZeros { .. } => crate::core::alu::count_leading_zeros(vj),
+            Instruction::CountTrailingZeros { .. } => crate::core::alu::count_trailing_zeros(vj),
+            Instruction::Popcount { .. } => crate::core::alu::popcount(vj),
+            Instruction::ByteSwap { .. } => crate::core::alu::byte_swap(vj),
+            Instruction::BitSet { .. } => crate::core::alu::bit_set(vj, vk),
+            Instruction::BitClear { .. } => crate::core::alu::bit_clear(vj, vk),
+            Instruction::BitTest { .. } => crate::core::alu::bit_test(vj, vk),
+            Instruction::BitFieldExtract { pos, width, .. } => {
+                crate::core::alu::bit_field_extract(vj, pos as u16, width as u16)
+            },
+            Instruction::BitFieldInsert { pos, width, .. } => {
+                crate::core::alu::bit_field_insert(vk, vj, pos as u16, width as u16)
+            },
+            Instruction::SatAdd { .. } => crate::core::alu::saturating_add(vj, vk).0,
+            Instruction::SatSub { .. } => crate::core::alu::saturating_sub(vj, vk).0,
+            Instruction::Min { .. } => crate::core::alu::min_signed(vj, vk),
+            Instruction::Max { .. } => crate::core::alu::max_signed(vj, vk),
+            Instruction::Abs { .. } => crate::core::alu::abs_saturating(vj).0,
+            Instruction::LoadImm { .. } | Instruction::LoadUpperImm { .. } => vk,
+            // For stores, vj holds the value to store
+            Instruction::Store { .. } | Instruction::StoreIndexed { .. } | Instruction::StoreByte { .. } => vj,
             _ => 0,
         }
     }
-}
\ No newline at end of file
+}
+
+// Where a load's value comes from, decided when it is ready to access memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoadSource {
+    Wait, // An older store has to execute or commit first
+    Memory,
+    Forward { store: usize, value: u16 }, // From an older store still in the ROB
+}
+
+// A load's value taken from a store of the same size at the same address
+fn forwarded_value(instruction: &Instruction, value: u16) -> u16 {
+    match instruction {
+        Instruction::LoadByte { .. } => value & 0xFF,
+        Instruction::LoadByteSigned { .. } => value as u8 as i8 as u16,
+        _ => value,
+    }
+}
+
+// Flags an ALU station instruction writes, mirroring execute(). Instructions that read
+// the flags never get here: they run on the in-order path.
+fn compute_flags(instruction: &Instruction, vj: u16, vk: u16, result: u16) -> Option<FlagUpdate> {
+    use crate::core::alu;
+
+    let flags = FlagUpdate::from_result(result);
+    match *instruction {
+        Instruction::Add { .. } | Instruction::AddImm { .. } => Some(flags.with_carry(alu::add(vj, vk).1)),
+        Instruction::Sub { .. } | Instruction::SubImm { .. } => Some(flags.with_carry(alu::sub(vj, vk).1)),
+        Instruction::ShiftLeft { .. } | Instruction::ShiftLeftReg { .. } => {
+            Some(flags.with_carry(alu::shift_left_carry(vj, vk)))
+        },
+        Instruction::ShiftRight { .. } | Instruction::ShiftRightReg { .. } => {
+            Some(flags.with_carry(alu::shift_right_carry(vj, vk, false)))
+        },
+        Instruction::ShiftRightArith { .. } | Instruction::ShiftRightArithReg { .. } => {
+            Some(flags.with_carry(alu::shift_right_carry(vj, vk, true)))
+        },
+        // Plain rotates copy the bit that wrapped around into carry
+        Instruction::RotateLeft { .. } | Instruction::RotateLeftReg { .. } if !vk.is_multiple_of(16) => {
+            Some(flags.with_carry(result & 1 != 0))
+        },
+        Instruction::RotateRight { .. } | Instruction::RotateRightReg { .. } if !vk.is_multiple_of(16) => {
+            Some(flags.with_carry((result as i16) < 0))
+        },
+        Instruction::SatAdd { .. } => Some(flags.with_overflow(alu::saturating_add(vj, vk).1)),
+        Instruction::SatSub { .. } => Some(flags.with_overflow(alu::saturating_sub(vj, vk).1)),
+        Instruction::Abs { .. } => Some(flags.with_overflow(alu::abs_saturating(vj).1)),
+        Instruction::Move { .. } | Instruction::MoveWide { .. } |
+        Instruction::LoadImm { .. } | Instruction::LoadUpperImm { .. } => None,
+        _ if is_load(instruction) || is_store(instruction) => None,
+        _ => Some(flags),
+    }
+}
+
+// Storage behind the first and last byte of a `size`-byte access at virtual address
+// `addr`, each translated on its own since a word may straddle pages or bank windows.
+// None when the access does not translate.
+fn location(cpu: &CpuState, mem: &Memory, addr: u16, size: u16) -> Option<[usize; 2]> {
+    let byte = |offset: u16| {
+        let paddr = cpu.mmu.physical(mem, addr.wrapping_add(offset)).ok()?;
+        mem.storage_index(mem.far_address(paddr))
+    };
+    Some([byte(0)?, byte(size - 1)?])
+}
+
+fn overlaps(a: [usize; 2], b: [usize; 2]) -> bool {
+    a.iter().any(|byte| b.contains(byte))
+}
+
+fn is_load(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Load { .. } | Instruction::LoadIndexed { .. } |
+        Instruction::LoadByte { .. } | Instruction::LoadByteSigned { .. }
+    )
+}
+
+fn access_size(instruction: &Instruction) -> u16 {
+    match instruction {
+        Instruction::LoadByte { .. } | Instruction::LoadByteSigned { .. } | Instruction::StoreByte { .. } => 1,
+        _ => 2,
+    }
+}
+
+fn is_store(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::Store { .. } | Instruction::StoreIndexed { .. } | Instruction::StoreByte { .. }
+    )
+}
+
+// Where a branch goes given its operands in BranchStation order, None when not taken
+fn branch_target(instruction: &Instruction, pc: u16, values: [u16; 4]) -> Option<u16> {
+    use crate::isa::relative_target;
+
+    let [val1, val2, hi1, hi2] = values;
+    let wide1 = ((hi1 as u32) << 16 | val1 as u32) as i32;
+    let wide2 = ((hi2 as u32) << 16 | val2 as u32) as i32;
+
+    match *instruction {
+        Instruction::Jump { addr } => Some(addr),
+        Instruction::BranchEqual32 { offset, .. } => (wide1 == wide2).then(|| relative_target(pc, offset)),
+        Instruction::BranchNotEqual32 { offset, .. } => (wide1 != wide2).then(|| relative_target(pc, offset)),
+        Instruction::BranchLessThan32 { offset, .. } => (wide1 < wide2).then(|| relative_target(pc, offset)),
+        Instruction::BranchGreaterThan32 { offset, .. } => {
+            (wide1 > wide2).then(|| relative_target(pc, offset))
+        },
+        Instruction::JumpReg { .. } => Some(val1),
+        Instruction::JumpRel { offset } => Some(relative_target(pc, offset)),
+        Instruction::BranchEqual { addr, .. } => (val1 == val2).then_some(addr),
+        Instruction::BranchNotEqual { addr, .. } => (val1 != val2).then_some(addr),
+        Instruction::BranchLessThan { addr, .. } => ((val1 as i16) < (val2 as i16)).then_some(addr),
+        Instruction::BranchGreaterThan { addr, .. } => ((val1 as i16) > (val2 as i16)).then_some(addr),
+        Instruction::BranchEqualRel { offset, .. } => {
+            (val1 == val2).then(|| relative_target(pc, offset))
+        },
+        Instruction::BranchNotEqualRel { offset, .. } => {
+            (val1 != val2).then(|| relative_target(pc, offset))
+        },
+        Instruction::BranchLessThanRel { offset, .. } => {
+            ((val1 as i16) < (val2 as i16)).then(|| relative_target(pc, offset))
+        },
+        Instruction::BranchGreaterThanRel { offset, .. } => {
+            ((val1 as i16) > (val2 as i16)).then(|| relative_target(pc, offset))
+        },
+        _ => None,
+    }
+}
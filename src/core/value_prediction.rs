@@ -0,0 +1,182 @@
+use crate::isa::Instruction;
+
+// Value predictors for Tomasulo's ALU and load results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuePredictorKind {
+    LastValue, // The value this instruction produced last time
+    Stride,    // The last value plus the difference between the last two
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValueEntry {
+    pub valid: bool,
+    pub pc: u16,
+    pub reg: u16, // Destination, so both halves of a cracked instruction get their own entry
+    pub last: u16,
+    pub stride: u16,
+    pub confidence: u8, // Saturates at 3
+    pub pending: u16,   // Instances issued since the last one wrote back
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValuePredictionStats {
+    pub eligible: u64,     // ALU and load results written back
+    pub predicted: u64,    // Instructions issued with a predicted result
+    pub correct: u64,
+    pub mispredicted: u64,
+    pub squashed: u64,     // ROB entries discarded after mispredictions
+}
+
+impl ValuePredictionStats {
+    // Fraction of results that were predicted (and verified)
+    pub fn coverage(&self) -> f32 {
+        if self.eligible == 0 { 0.0 } else { (self.correct + self.mispredicted) as f32 / self.eligible as f32 }
+    }
+
+    // Fraction of verified predictions that were right
+    pub fn accuracy(&self) -> f32 {
+        let verified = self.correct + self.mispredicted;
+        if verified == 0 { 0.0 } else { self.correct as f32 / verified as f32 }
+    }
+}
+
+// Direct-mapped table indexed by pc and destination register. Entries train on results as
+// they write back, which can be after later instances of the same instruction have issued,
+// so a stride prediction steps over the instances still pending. A prediction is only
+// made once the same outcome has repeated `threshold` times.
+#[derive(Debug)]
+pub struct ValuePredictor {
+    pub kind: ValuePredictorKind,
+    pub table: Vec<ValueEntry>,
+    pub threshold: u8,
+    pub stats: ValuePredictionStats,
+}
+
+impl ValuePredictor {
+    pub fn new(kind: ValuePredictorKind, entries: usize) -> Self {
+        Self {
+            kind,
+            table: vec![ValueEntry::default(); entries.max(1)],
+            threshold: 2,
+            stats: ValuePredictionStats::default(),
+        }
+    }
+
+    fn index(&self, pc: u16, reg: u16) -> usize {
+        ((pc as usize >> 2) ^ (reg as usize * 7)) % self.table.len()
+    }
+
+    // Called as an eligible instruction issues: the predicted result, if confident
+    pub fn predict(&mut self, pc: u16, reg: u16) -> Option<u16> {
+        let index = self.index(pc, reg);
+        let (kind, threshold) = (self.kind, self.threshold);
+        let entry = &mut self.table[index];
+        if !entry.valid || entry.pc != pc || entry.reg != reg {
+            return None;
+        }
+        entry.pending = entry.pending.saturating_add(1);
+        if entry.confidence < threshold {
+            return None;
+        }
+        self.stats.predicted += 1;
+        Some(match kind {
+            ValuePredictorKind::LastValue => entry.last,
+            ValuePredictorKind::Stride => entry.last.wrapping_add(entry.stride.wrapping_mul(entry.pending)),
+        })
+    }
+
+    pub fn train(&mut self, pc: u16, reg: u16, value: u16) {
+        self.stats.eligible += 1;
+        let index = self.index(pc, reg);
+        let kind = self.kind;
+        let entry = &mut self.table[index];
+        if !entry.valid || entry.pc != pc || entry.reg != reg {
+            *entry = ValueEntry { valid: true, pc, reg, last: value, ..ValueEntry::default() };
+            return;
+        }
+
+        let delta = value.wrapping_sub(entry.last);
+        let repeated = match kind {
+            ValuePredictorKind::LastValue => delta == 0,
+            ValuePredictorKind::Stride => delta == entry.stride,
+        };
+        if repeated {
+            entry.confidence = (entry.confidence + 1).min(3);
+        } else {
+            entry.confidence = 0;
+            entry.stride = delta;
+        }
+        entry.last = value;
+        entry.pending = entry.pending.saturating_sub(1);
+    }
+
+    // A squashed instance never writes back, so it stops counting as pending. Instances
+    // older than the squash are still in flight and keep their count.
+    pub fn squashed(&mut self, pc: u16, reg: u16) {
+        let index = self.index(pc, reg);
+        let entry = &mut self.table[index];
+        if entry.valid && entry.pc == pc && entry.reg == reg {
+            entry.pending = entry.pending.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReuseEntry {
+    pub valid: bool,
+    pub pc: u16,
+    pub instruction: Option<Instruction>, // Another bank can have something else at the pc
+    pub operands: (u16, u16),
+    pub result: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReuseStats {
+    pub lookups: u64, // ALU instructions issued with both operands known
+    pub reused: u64,  // ... whose result came from the buffer
+}
+
+impl ReuseStats {
+    pub fn reuse_rate(&self) -> f32 {
+        if self.lookups == 0 { 0.0 } else { self.reused as f32 / self.lookups as f32 }
+    }
+}
+
+// Instruction reuse (Sodani and Sohi's Sv scheme): ALU results written back are kept with
+// the operand values that produced them, direct-mapped by pc. An instruction issuing with
+// both operands known that finds itself at its pc with the same operands takes the result
+// from the buffer and completes without executing. Unlike a value prediction it needs no
+// verification, since the result is a function of the operands alone.
+#[derive(Debug)]
+pub struct ReuseBuffer {
+    pub table: Vec<ReuseEntry>,
+    pub stats: ReuseStats,
+}
+
+impl ReuseBuffer {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            table: vec![ReuseEntry::default(); entries.max(1)],
+            stats: ReuseStats::default(),
+        }
+    }
+
+    fn index(&self, pc: u16) -> usize {
+        (pc as usize >> 2) % self.table.len()
+    }
+
+    pub fn lookup(&mut self, pc: u16, instruction: Instruction, operands: (u16, u16)) -> Option<u16> {
+        self.stats.lookups += 1;
+        let entry = &self.table[self.index(pc)];
+        if !entry.valid || entry.pc != pc || entry.instruction != Some(instruction) || entry.operands != operands {
+            return None;
+        }
+        self.stats.reused += 1;
+        Some(entry.result)
+    }
+
+    pub fn record(&mut self, pc: u16, instruction: Instruction, operands: (u16, u16), result: u16) {
+        let index = self.index(pc);
+        self.table[index] = ReuseEntry { valid: true, pc, instruction: Some(instruction), operands, result };
+    }
+}
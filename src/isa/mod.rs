@@ -1,12 +1,32 @@
 
+use crate::utils::{sign_extend_10, sign_extend_8};
 
-#[derive(Debug, Clone, Copy)]
+// Register used as the stack pointer by Push/Pop. It lies in the banked window,
+// so every register bank has its own stack.
+pub const STACK_POINTER: u8 = 15;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Instruction {
     // Memory
     Load    { dst: u8, addr: u16 },
     LoadImm { dst: u8, value: i16 },
+    LoadUpperImm { dst: u8, value: u8 }, // dst = value << 8
     Store   { src: u8, addr: u16 },
 
+    // Base register + signed offset (register-indirect when offset is 0)
+    LoadIndexed    { dst: u8, base: u8, offset: i16 },
+    StoreIndexed   { src: u8, base: u8, offset: i16 },
+    LoadByte       { dst: u8, base: u8, offset: i16 }, // zero-extended
+    LoadByteSigned { dst: u8, base: u8, offset: i16 }, // sign-extended
+    StoreByte      { src: u8, base: u8, offset: i16 },
+
+    // Register-indirect with post-increment of the base by the access size
+    LoadPostInc      { dst: u8, base: u8 },
+    StorePostInc     { src: u8, base: u8 },
+    LoadBytePostInc  { dst: u8, base: u8 },
+    LoadByteSignedPostInc { dst: u8, base: u8 },
+    StoreBytePostInc { src: u8, base: u8 },
+
     // Moves
     Move          { dst: u8, src: u16 },
     MoveIfZero    { dst: u8, src: u16 },
@@ -29,15 +49,43 @@ pub enum Instruction {
     Div   { dst: u8, src1: u8, src2: u16 }, 
     Mod   { dst: u8, src1: u8, src2: u16 }, 
 
+    // Multi-precision: register pairs are {reg+1:reg}, high:low
+    AddCarry       { dst: u8, src1: u8, src2: u8 },
+    SubBorrow      { dst: u8, src1: u8, src2: u8 },
+    MultWide       { dst: u8, src1: u8, src2: u8 }, // {dst+1:dst} = src1 * src2 (unsigned)
+    MultWideSigned { dst: u8, src1: u8, src2: u8 },
+
+    // Q15 fixed point (signed, saturating)
+    SatAdd  { dst: u8, src1: u8, src2: u8 },
+    SatSub  { dst: u8, src1: u8, src2: u8 },
+    Q15Mult { dst: u8, src1: u8, src2: u8 },       // rounded
+    MultAcc { acc: u8, src1: u8, src2: u8 },       // {acc+1:acc} += src1 * src2
+    Abs     { dst: u8, src: u8 },
+    Min     { dst: u8, src1: u8, src2: u8 },
+    Max     { dst: u8, src1: u8, src2: u8 },
+
     // Jumps/branches
     Jump    { addr: u16 },
     JumpReg { reg: u16 },
+    JumpRel { offset: i16 }, // offset in instruction words from this instruction
 
     BranchEqual       { src1: u8, src2: u16, addr: u16 },
     BranchNotEqual    { src1: u8, src2: u16, addr: u16 },
     BranchLessThan    { src1: u8, src2: u16, addr: u16 },
     BranchGreaterThan { src1: u8, src2: u16, addr: u16 },
 
+    // 32-bit signed compare of register pairs, PC-relative like the branches below
+    BranchEqual32       { src1: u8, src2: u8, offset: i16 },
+    BranchNotEqual32    { src1: u8, src2: u8, offset: i16 },
+    BranchLessThan32    { src1: u8, src2: u8, offset: i16 },
+    BranchGreaterThan32 { src1: u8, src2: u8, offset: i16 },
+
+    // PC-relative branches, offset in instruction words from this instruction
+    BranchEqualRel       { src1: u8, src2: u16, offset: i16 },
+    BranchNotEqualRel    { src1: u8, src2: u16, offset: i16 },
+    BranchLessThanRel    { src1: u8, src2: u16, offset: i16 },
+    BranchGreaterThanRel { src1: u8, src2: u16, offset: i16 },
+
     // Comparison
     Cmp    { src1: u8, src2: u16 },
     CmpImm { src: u8, imm: i16 },
@@ -47,14 +95,60 @@ pub enum Instruction {
     Or  { dst: u8, src1: u8, src2: u16 },
     Xor { dst: u8, src1: u8, src2: u16 },
     Not { dst: u8, src: u8 },
+    // 10-bit zero-extended immediates
+    AndImm { dst: u8, src: u8, imm: u16 },
+    OrImm  { dst: u8, src: u8, imm: u16 },
+    XorImm { dst: u8, src: u8, imm: u16 },
 
     // Shifts
     ShiftLeft  { dst: u8, src: u8, amount: u16 },
     ShiftRight { dst: u8, src: u8, amount: u16 },
+    ShiftRightArith  { dst: u8, src: u8, amount: u16 },
+    RotateLeft       { dst: u8, src: u8, amount: u16 },
+    RotateRight      { dst: u8, src: u8, amount: u16 },
+    RotateLeftCarry  { dst: u8, src: u8, amount: u16 }, // through the carry flag
+    RotateRightCarry { dst: u8, src: u8, amount: u16 },
+
+    // Shift amount taken from a register
+    ShiftLeftReg       { dst: u8, src: u8, amount: u8 },
+    ShiftRightReg      { dst: u8, src: u8, amount: u8 },
+    ShiftRightArithReg { dst: u8, src: u8, amount: u8 },
+    RotateLeftReg      { dst: u8, src: u8, amount: u8 },
+    RotateRightReg     { dst: u8, src: u8, amount: u8 },
 
-    // Stack
+    // Bit manipulation
+    CountLeadingZeros  { dst: u8, src: u8 },
+    CountTrailingZeros { dst: u8, src: u8 },
+    Popcount           { dst: u8, src: u8 },
+    ByteSwap           { dst: u8, src: u8 },
+    BitSet   { dst: u8, src: u8, index: u8 },
+    BitClear { dst: u8, src: u8, index: u8 },
+    BitTest  { dst: u8, src: u8, index: u8 }, // dst = 1 if the bit is set, else 0
+    BitFieldExtract { dst: u8, src: u8, pos: u8, width: u8 }, // dst = src[pos +: width]
+    BitFieldInsert  { dst: u8, src: u8, pos: u8, width: u8 }, // dst[pos +: width] = src
+
+    // Stack (full descending, through STACK_POINTER)
     Push { src: u16 }, // 10-bit reg index
     Pop  { dst: u16 }, // 10-bit reg index
+    PushMultiple { first: u8, last: u8 }, // first ends up at the lowest address, never includes SP
+    PopMultiple  { first: u8, last: u8 },
+
+    // System (everything except SystemCall is privileged)
+    BankSelect { bank: u8 },
+    ReturnFromException, // restore the pc and status saved on exception entry
+    SystemCall { code: u8 },
+    EnableInterrupts,
+    DisableInterrupts,
+    MoveToControl   { cr: u8, src: u8 },
+    MoveFromControl { dst: u8, cr: u8 },
+    FlushTlb,
+    FlushTlbPage { reg: u8 }, // page containing the address held in reg
+
+    // Memory bank switching (see memory::CODE_WINDOW / DATA_WINDOW)
+    FarJump { bank: u8, target: u8 }, // select the code bank, jump to the address in target
+    FarCall { bank: u8, target: u8 }, // as FarJump, after pushing the code bank and return pc
+    FarReturn,
+    SetDataBank { src: u8 },
 
     Nop,
     Halt,
@@ -75,28 +169,46 @@ pub fn decode(raw: u32) -> Option<Instruction> {
         0xF => Some(Instruction::Nop),
         0xE => Some(Instruction::Halt),
 
-        0x0 => decode_memory(secondary_opcode, dst, src2_imm10),
+        0x0 => decode_memory(secondary_opcode, dst, src1, src2_imm10),
         0x1 => decode_arithmetic(secondary_opcode, dst, src1, src2_imm10),
         0x2 => decode_logical(secondary_opcode, dst, src1, src2_imm10),
         0x3 => decode_branch(secondary_opcode, src1, src2_imm10, dst), // dst repurposed as addr
         0x4 => decode_jump(secondary_opcode, src2_imm10, dst),
-        0x5 => decode_compare(secondary_opcode, src1, src2_imm10),
+        0x5 => decode_compare(secondary_opcode, dst, src1, src2_imm10),
         0x6 => decode_shift(secondary_opcode, dst, src1, src2_imm10),
-        0x7 => decode_stack(secondary_opcode, src2_imm10),
-        0x8 => decode_move(secondary_opcode, dst, src2_imm10),
+        0x7 => decode_stack(secondary_opcode, dst, src1, src2_imm10),
+        0x8 => decode_move(secondary_opcode, dst, src1, src2_imm10),
         0x9 => decode_complex_arithmetic(secondary_opcode, dst, src1, src2_imm10),
-        0xA => decode_move_wide(secondary_opcode, src2_imm10, src1), // MoveWide: SRC2_IMM10 IS 10-BIT DST IN THIS CASE
+        0xA => decode_move_wide(secondary_opcode, src2_imm10, src1, dst), // MoveWide: SRC2_IMM10 IS 10-BIT DST IN THIS CASE
+        0xB => decode_branch_relative(secondary_opcode, src1, src2_imm10, dst), // dst repurposed as signed offset
+        0xC => decode_memory_indexed(secondary_opcode, dst, src1, src2_imm10), // src1 is the base register
+        0xD => decode_logical_immediate(secondary_opcode, dst, src1, src2_imm10),
 
         _   => None,
     }
 }
 
 // Memory operations 0x0
-fn decode_memory(secondary: u8, dst: u8, addr10: u16) -> Option<Instruction> {
+fn decode_memory(secondary: u8, dst: u8, base: u8, addr10: u16) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::Load    { dst, addr: addr10 }),
         0b01 => Some(Instruction::LoadImm { dst, value: sign_extend_10(addr10) }),
         0b10 => Some(Instruction::Store   { src: dst, addr: addr10 }),
+        // The byte loads with an offset share 0xC, which is full, so the sign-extending
+        // one takes the last slot here. Its post-increment form sits with LoadBytePostInc.
+        0b11 => Some(Instruction::LoadByteSigned { dst, base, offset: sign_extend_10(addr10) }),
+        _    => None,
+    }
+}
+
+// Indexed memory operations 0xC: base register + signed 10-bit byte offset
+fn decode_memory_indexed(secondary: u8, dst: u8, base: u8, offset10: u16) -> Option<Instruction> {
+    let offset = sign_extend_10(offset10);
+    match secondary {
+        0b00 => Some(Instruction::LoadIndexed  { dst, base, offset }),
+        0b01 => Some(Instruction::StoreIndexed { src: dst, base, offset }),
+        0b10 => Some(Instruction::LoadByte     { dst, base, offset }),
+        0b11 => Some(Instruction::StoreByte    { src: dst, base, offset }),
         _    => None,
     }
 }
@@ -145,20 +257,353 @@ fn decode_branch(secondary: u8, src1: u8, src2_10: u16, addr8: u8) -> Option<Ins
     }
 }
 
+// Logical operations with a zero-extended immediate 0xD
+fn decode_logical_immediate(secondary: u8, dst: u8, src1: u8, imm10: u16) -> Option<Instruction> {
+    match secondary {
+        0b00 => Some(Instruction::AndImm { dst, src: src1, imm: imm10 }),
+        0b01 => Some(Instruction::OrImm  { dst, src: src1, imm: imm10 }),
+        0b10 => Some(Instruction::XorImm { dst, src: src1, imm: imm10 }),
+        0b11 => decode_bit_manipulation(dst, src1, imm10),
+        _    => None,
+    }
+}
+
+// Bit manipulation 0xD/0b11, bits [9:8] select the class:
+//   00 unary     bits [2:0] op (clz, ctz, popcount, byte swap, saturating abs)
+//   01 indexed   bits [5:4] op (set, clear, test), bits [3:0] bit index
+//   10 extract   bits [7:4] position, bits [3:0] width - 1
+//   11 insert    bits [7:4] position, bits [3:0] width - 1
+fn decode_bit_manipulation(dst: u8, src: u8, func10: u16) -> Option<Instruction> {
+    let pos = ((func10 >> 4) & 0xF) as u8;
+    let width = (func10 & 0xF) as u8 + 1;
+    match func10 >> 8 {
+        0b00 => match func10 & 0xFF {
+            0b000 => Some(Instruction::CountLeadingZeros  { dst, src }),
+            0b001 => Some(Instruction::CountTrailingZeros { dst, src }),
+            0b010 => Some(Instruction::Popcount           { dst, src }),
+            0b011 => Some(Instruction::ByteSwap           { dst, src }),
+            0b100 => Some(Instruction::Abs                { dst, src }),
+            _     => None,
+        },
+        0b01 => {
+            let index = (func10 & 0xF) as u8;
+            match (func10 >> 4) & 0xF {
+                0b00 => Some(Instruction::BitSet   { dst, src, index }),
+                0b01 => Some(Instruction::BitClear { dst, src, index }),
+                0b10 => Some(Instruction::BitTest  { dst, src, index }),
+                _    => None,
+            }
+        },
+        0b10 => Some(Instruction::BitFieldExtract { dst, src, pos, width }),
+        0b11 => Some(Instruction::BitFieldInsert  { dst, src, pos, width }),
+        _    => None,
+    }
+}
+
+// Q15 DSP operations 0x5/0b10, bits [9:8] select the operation, bits [7:0] hold src2
+fn decode_dsp(dst: u8, src1: u8, func10: u16) -> Option<Instruction> {
+    let src2 = func10 as u8;
+    match func10 >> 8 {
+        0b00 => Some(Instruction::SatAdd  { dst, src1, src2 }),
+        0b01 => Some(Instruction::SatSub  { dst, src1, src2 }),
+        0b10 => Some(Instruction::Q15Mult { dst, src1, src2 }),
+        0b11 => Some(Instruction::MultAcc { acc: dst, src1, src2 }),
+        _    => None,
+    }
+}
+
+// Multi-precision operations 0x5/0b11, bits [9:8] select the operation, bits [7:0] hold src2
+fn decode_multi_precision(dst: u8, src1: u8, func10: u16) -> Option<Instruction> {
+    let src2 = func10 as u8;
+    match func10 >> 8 {
+        0b00 => Some(Instruction::AddCarry       { dst, src1, src2 }),
+        0b01 => Some(Instruction::SubBorrow      { dst, src1, src2 }),
+        0b10 => Some(Instruction::MultWide       { dst, src1, src2 }),
+        0b11 => Some(Instruction::MultWideSigned { dst, src1, src2 }),
+        _    => None,
+    }
+}
+
+// Relative branch operations 0xB
+fn decode_branch_relative(secondary: u8, src1: u8, src2_10: u16, offset8: u8) -> Option<Instruction> {
+    let offset = sign_extend_8(offset8);
+    match secondary {
+        0b00 => Some(Instruction::BranchEqualRel      { src1, src2: src2_10, offset }),
+        0b01 => Some(Instruction::BranchNotEqualRel   { src1, src2: src2_10, offset }),
+        0b10 => Some(Instruction::BranchLessThanRel   { src1, src2: src2_10, offset }),
+        0b11 => Some(Instruction::BranchGreaterThanRel{ src1, src2: src2_10, offset }),
+        _    => None,
+    }
+}
+
 // Jump operations 0x4
 fn decode_jump(secondary: u8, addr10: u16, reg8: u8) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::Jump    { addr: addr10 }),
         0b01 => Some(Instruction::JumpReg { reg: reg8 as u16 }),
+        0b10 => Some(Instruction::JumpRel { offset: sign_extend_10(addr10) }),
+        0b11 => decode_system(addr10, reg8),
         _    => None,
     }
 }
 
+// System operations 0x4/0b11, bits [9:6] select the operation, bits [5:0] hold its
+// immediate (bank, call code or control register), the dst field its register
+fn decode_system(func10: u16, reg8: u8) -> Option<Instruction> {
+    let imm6 = (func10 & 0x3F) as u8;
+    match func10 >> 6 {
+        0b0000 => Some(Instruction::BankSelect { bank: imm6 }),
+        0b0001 => Some(Instruction::ReturnFromException),
+        0b0010 => Some(Instruction::SystemCall { code: imm6 }),
+        0b0011 => Some(Instruction::EnableInterrupts),
+        0b0100 => Some(Instruction::DisableInterrupts),
+        0b0101 => Some(Instruction::MoveToControl   { cr: imm6, src: reg8 }),
+        0b0110 => Some(Instruction::MoveFromControl { dst: reg8, cr: imm6 }),
+        0b0111 => Some(Instruction::FlushTlb),
+        0b1000 => Some(Instruction::FlushTlbPage { reg: reg8 }),
+        0b1001 => Some(Instruction::FarJump { bank: imm6, target: reg8 }),
+        0b1010 => Some(Instruction::FarCall { bank: imm6, target: reg8 }),
+        0b1011 => Some(Instruction::FarReturn),
+        0b1100 => Some(Instruction::SetDataBank { src: reg8 }),
+        _      => None,
+    }
+}
+
+// Instructions that raise PrivilegedInstruction outside supervisor mode. Far transfers
+// are also privileged when they leave the current code bank (see execute::execute).
+pub fn is_privileged(instruction: &Instruction) -> bool {
+    matches!(
+        instruction,
+        Instruction::BankSelect { .. } |
+        Instruction::SetDataBank { .. } |
+        Instruction::ReturnFromException |
+        Instruction::EnableInterrupts |
+        Instruction::DisableInterrupts |
+        Instruction::MoveToControl { .. } |
+        Instruction::MoveFromControl { .. } |
+        Instruction::FlushTlb |
+        Instruction::FlushTlbPage { .. }
+    )
+}
+
+// Registers an instruction reads, as 10-bit register indices (r0-r15 name the current
+// bank). Register pairs contribute both halves; flags and control registers are not
+// included. The pipeline models use these for hazard detection.
+pub fn source_registers(instruction: &Instruction) -> Vec<u16> {
+    let sp = STACK_POINTER as u16;
+    let pair = |reg: u8| [reg as u16, reg.wrapping_add(1) as u16];
+    match *instruction {
+        Instruction::Load { .. } | Instruction::LoadImm { .. } | Instruction::LoadUpperImm { .. } |
+        Instruction::Jump { .. } | Instruction::JumpRel { .. } |
+        Instruction::BankSelect { .. } | Instruction::ReturnFromException | Instruction::SystemCall { .. } |
+        Instruction::EnableInterrupts | Instruction::DisableInterrupts |
+        Instruction::MoveFromControl { .. } | Instruction::FlushTlb |
+        Instruction::Nop | Instruction::Halt => vec![],
+
+        Instruction::Store { src, .. } => vec![src as u16],
+        Instruction::LoadIndexed { base, .. } |
+        Instruction::LoadByte { base, .. } |
+        Instruction::LoadByteSigned { base, .. } |
+        Instruction::LoadPostInc { base, .. } |
+        Instruction::LoadBytePostInc { base, .. } |
+        Instruction::LoadByteSignedPostInc { base, .. } => vec![base as u16],
+        Instruction::StoreIndexed { src, base, .. } |
+        Instruction::StoreByte { src, base, .. } |
+        Instruction::StorePostInc { src, base } |
+        Instruction::StoreBytePostInc { src, base } => vec![src as u16, base as u16],
+
+        Instruction::Move { src, .. } => vec![src],
+        // A conditional move keeps the old destination when the condition fails
+        Instruction::MoveIfZero { dst, src } |
+        Instruction::MoveIfNotZero { dst, src } => vec![src, dst as u16],
+        Instruction::MoveWide { src, .. } => vec![src as u16],
+        Instruction::MoveWideIfZero { dst, src } |
+        Instruction::MoveWideIfNotZero { dst, src } => vec![src as u16, dst],
+
+        Instruction::Add { src1, src2, .. } |
+        Instruction::Sub { src1, src2, .. } |
+        Instruction::Mult { src1, src2, .. } |
+        Instruction::Div { src1, src2, .. } |
+        Instruction::Mod { src1, src2, .. } |
+        Instruction::And { src1, src2, .. } |
+        Instruction::Or { src1, src2, .. } |
+        Instruction::Xor { src1, src2, .. } |
+        Instruction::Cmp { src1, src2 } |
+        Instruction::BranchEqual { src1, src2, .. } |
+        Instruction::BranchNotEqual { src1, src2, .. } |
+        Instruction::BranchLessThan { src1, src2, .. } |
+        Instruction::BranchGreaterThan { src1, src2, .. } |
+        Instruction::BranchEqualRel { src1, src2, .. } |
+        Instruction::BranchNotEqualRel { src1, src2, .. } |
+        Instruction::BranchLessThanRel { src1, src2, .. } |
+        Instruction::BranchGreaterThanRel { src1, src2, .. } => vec![src1 as u16, src2],
+
+        Instruction::AddCarry { src1, src2, .. } |
+        Instruction::SubBorrow { src1, src2, .. } |
+        Instruction::MultWide { src1, src2, .. } |
+        Instruction::MultWideSigned { src1, src2, .. } |
+        Instruction::SatAdd { src1, src2, .. } |
+        Instruction::SatSub { src1, src2, .. } |
+        Instruction::Q15Mult { src1, src2, .. } |
+        Instruction::Min { src1, src2, .. } |
+        Instruction::Max { src1, src2, .. } => vec![src1 as u16, src2 as u16],
+        Instruction::MultAcc { acc, src1, src2 } => {
+            let mut regs = vec![src1 as u16, src2 as u16];
+            regs.extend(pair(acc));
+            regs
+        }
+
+        Instruction::BranchEqual32 { src1, src2, .. } |
+        Instruction::BranchNotEqual32 { src1, src2, .. } |
+        Instruction::BranchLessThan32 { src1, src2, .. } |
+        Instruction::BranchGreaterThan32 { src1, src2, .. } => {
+            let mut regs = pair(src1).to_vec();
+            regs.extend(pair(src2));
+            regs
+        }
+
+        Instruction::AddImm { src, .. } |
+        Instruction::SubImm { src, .. } |
+        Instruction::MultImm { src, .. } |
+        Instruction::CmpImm { src, .. } |
+        Instruction::Abs { src, .. } |
+        Instruction::Not { src, .. } |
+        Instruction::AndImm { src, .. } |
+        Instruction::OrImm { src, .. } |
+        Instruction::XorImm { src, .. } |
+        Instruction::ShiftLeft { src, .. } |
+        Instruction::ShiftRight { src, .. } |
+        Instruction::ShiftRightArith { src, .. } |
+        Instruction::RotateLeft { src, .. } |
+        Instruction::RotateRight { src, .. } |
+        Instruction::RotateLeftCarry { src, .. } |
+        Instruction::RotateRightCarry { src, .. } |
+        Instruction::CountLeadingZeros { src, .. } |
+        Instruction::CountTrailingZeros { src, .. } |
+        Instruction::Popcount { src, .. } |
+        Instruction::ByteSwap { src, .. } |
+        Instruction::BitSet { src, .. } |
+        Instruction::BitClear { src, .. } |
+        Instruction::BitTest { src, .. } |
+        Instruction::BitFieldExtract { src, .. } => vec![src as u16],
+        Instruction::BitFieldInsert { dst, src, .. } => vec![src as u16, dst as u16],
+
+        Instruction::ShiftLeftReg { src, amount, .. } |
+        Instruction::ShiftRightReg { src, amount, .. } |
+        Instruction::ShiftRightArithReg { src, amount, .. } |
+        Instruction::RotateLeftReg { src, amount, .. } |
+        Instruction::RotateRightReg { src, amount, .. } => vec![src as u16, amount as u16],
+
+        Instruction::JumpReg { reg } => vec![reg],
+        Instruction::Push { src } => vec![src, sp],
+        Instruction::Pop { .. } | Instruction::FarReturn => vec![sp],
+        Instruction::PushMultiple { first, last } => {
+            let mut regs: Vec<u16> = (first..=last).map(u16::from).collect();
+            regs.push(sp);
+            regs
+        }
+        Instruction::PopMultiple { .. } => vec![sp],
+
+        Instruction::MoveToControl { src, .. } | Instruction::SetDataBank { src } => vec![src as u16],
+        Instruction::FlushTlbPage { reg } => vec![reg as u16],
+        Instruction::FarJump { target, .. } => vec![target as u16],
+        Instruction::FarCall { target, .. } => vec![target as u16, sp],
+    }
+}
+
+// Registers an instruction writes, in the same numbering as source_registers
+pub fn destination_registers(instruction: &Instruction) -> Vec<u16> {
+    let sp = STACK_POINTER as u16;
+    let pair = |reg: u8| vec![reg as u16, reg.wrapping_add(1) as u16];
+    match *instruction {
+        Instruction::Load { dst, .. } |
+        Instruction::LoadImm { dst, .. } |
+        Instruction::LoadUpperImm { dst, .. } |
+        Instruction::LoadIndexed { dst, .. } |
+        Instruction::LoadByte { dst, .. } |
+        Instruction::LoadByteSigned { dst, .. } |
+        Instruction::Move { dst, .. } |
+        Instruction::MoveIfZero { dst, .. } |
+        Instruction::MoveIfNotZero { dst, .. } |
+        Instruction::Add { dst, .. } |
+        Instruction::Sub { dst, .. } |
+        Instruction::AddImm { dst, .. } |
+        Instruction::SubImm { dst, .. } |
+        Instruction::Mult { dst, .. } |
+        Instruction::MultImm { dst, .. } |
+        Instruction::Div { dst, .. } |
+        Instruction::Mod { dst, .. } |
+        Instruction::AddCarry { dst, .. } |
+        Instruction::SubBorrow { dst, .. } |
+        Instruction::SatAdd { dst, .. } |
+        Instruction::SatSub { dst, .. } |
+        Instruction::Q15Mult { dst, .. } |
+        Instruction::Abs { dst, .. } |
+        Instruction::Min { dst, .. } |
+        Instruction::Max { dst, .. } |
+        Instruction::And { dst, .. } |
+        Instruction::Or { dst, .. } |
+        Instruction::Xor { dst, .. } |
+        Instruction::Not { dst, .. } |
+        Instruction::AndImm { dst, .. } |
+        Instruction::OrImm { dst, .. } |
+        Instruction::XorImm { dst, .. } |
+        Instruction::ShiftLeft { dst, .. } |
+        Instruction::ShiftRight { dst, .. } |
+        Instruction::ShiftRightArith { dst, .. } |
+        Instruction::RotateLeft { dst, .. } |
+        Instruction::RotateRight { dst, .. } |
+        Instruction::RotateLeftCarry { dst, .. } |
+        Instruction::RotateRightCarry { dst, .. } |
+        Instruction::ShiftLeftReg { dst, .. } |
+        Instruction::ShiftRightReg { dst, .. } |
+        Instruction::ShiftRightArithReg { dst, .. } |
+        Instruction::RotateLeftReg { dst, .. } |
+        Instruction::RotateRightReg { dst, .. } |
+        Instruction::CountLeadingZeros { dst, .. } |
+        Instruction::CountTrailingZeros { dst, .. } |
+        Instruction::Popcount { dst, .. } |
+        Instruction::ByteSwap { dst, .. } |
+        Instruction::BitSet { dst, .. } |
+        Instruction::BitClear { dst, .. } |
+        Instruction::BitTest { dst, .. } |
+        Instruction::BitFieldExtract { dst, .. } |
+        Instruction::BitFieldInsert { dst, .. } |
+        Instruction::MoveFromControl { dst, .. } => vec![dst as u16],
+
+        Instruction::LoadPostInc { dst, base } |
+        Instruction::LoadBytePostInc { dst, base } |
+        Instruction::LoadByteSignedPostInc { dst, base } => vec![dst as u16, base as u16],
+        Instruction::StorePostInc { base, .. } |
+        Instruction::StoreBytePostInc { base, .. } => vec![base as u16],
+
+        Instruction::MoveWide { dst, .. } |
+        Instruction::MoveWideIfZero { dst, .. } |
+        Instruction::MoveWideIfNotZero { dst, .. } => vec![dst],
+
+        Instruction::MultWide { dst, .. } | Instruction::MultWideSigned { dst, .. } => pair(dst),
+        Instruction::MultAcc { acc, .. } => pair(acc),
+
+        Instruction::Push { .. } | Instruction::PushMultiple { .. } |
+        Instruction::FarCall { .. } | Instruction::FarReturn => vec![sp],
+        Instruction::Pop { dst } => vec![dst, sp],
+        Instruction::PopMultiple { first, last } => {
+            let mut regs: Vec<u16> = (first..=last).map(u16::from).collect();
+            regs.push(sp);
+            regs
+        }
+
+        _ => vec![],
+    }
+}
+
 // Compare operations 0x5
-fn decode_compare(secondary: u8, src1: u8, src2_imm10: u16) -> Option<Instruction> {
+fn decode_compare(secondary: u8, dst: u8, src1: u8, src2_imm10: u16) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::Cmp { src1, src2: src2_imm10 }),
         0b01 => Some(Instruction::CmpImm { src: src1, imm: sign_extend_10(src2_imm10) }),
+        0b10 => decode_dsp(dst, src1, src2_imm10),
+        0b11 => decode_multi_precision(dst, src1, src2_imm10),
         _    => None,
     }
 }
@@ -168,35 +613,317 @@ fn decode_shift(secondary: u8, dst: u8, src: u8, amt10: u16) -> Option<Instructi
     match secondary {
         0b00 => Some(Instruction::ShiftLeft  { dst, src, amount: amt10 }),
         0b01 => Some(Instruction::ShiftRight { dst, src, amount: amt10 }),
+        0b10 => decode_shift_extended(dst, src, amt10),
+        0b11 => decode_shift_register(dst, src, amt10),
+        _    => None,
+    }
+}
+
+// Extended immediate shifts 0x6/0b10: bits [6:4] select the operation, bits [3:0] the amount.
+// Bits [9:8] = 0b11 is RotateRightReg, with the amount register in bits [7:0] as in 0x6/0b11.
+fn decode_shift_extended(dst: u8, src: u8, func10: u16) -> Option<Instruction> {
+    if func10 >> 8 == 0b11 {
+        return Some(Instruction::RotateRightReg { dst, src, amount: func10 as u8 });
+    }
+    let amount = func10 & 0xF;
+    match func10 >> 4 {
+        0b000 => Some(Instruction::ShiftRightArith  { dst, src, amount }),
+        0b001 => Some(Instruction::RotateLeft       { dst, src, amount }),
+        0b010 => Some(Instruction::RotateRight      { dst, src, amount }),
+        0b011 => Some(Instruction::RotateLeftCarry  { dst, src, amount }),
+        0b100 => Some(Instruction::RotateRightCarry { dst, src, amount }),
+        _     => None,
+    }
+}
+
+// Register-amount shifts 0x6/0b11: bits [9:8] select the operation, bits [7:0] the amount register
+fn decode_shift_register(dst: u8, src: u8, func10: u16) -> Option<Instruction> {
+    let amount = func10 as u8;
+    match func10 >> 8 {
+        0b00 => Some(Instruction::ShiftLeftReg       { dst, src, amount }),
+        0b01 => Some(Instruction::ShiftRightReg      { dst, src, amount }),
+        0b10 => Some(Instruction::ShiftRightArithReg { dst, src, amount }),
+        0b11 => Some(Instruction::RotateLeftReg      { dst, src, amount }),
         _    => None,
     }
 }
 
 // Stack operations 0x7
-fn decode_stack(secondary: u8, reg10: u16) -> Option<Instruction> {
+fn decode_stack(secondary: u8, dst: u8, base: u8, reg10: u16) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::Push { src: reg10 }),
         0b01 => Some(Instruction::Pop  { dst: reg10 }),
+        0b10 => decode_post_increment(dst, base, reg10),
+        0b11 => decode_stack_multiple(dst, reg10),
+        _    => None,
+    }
+}
+
+// Register range transfers 0x7/0b11: dst is the first register, bits [7:0] the last,
+// bits [9:8] select the direction. The range may not include the stack pointer, which
+// the transfer itself moves.
+fn decode_stack_multiple(first: u8, func10: u16) -> Option<Instruction> {
+    let last = (func10 & 0xFF) as u8;
+    if last < first || (first..=last).contains(&STACK_POINTER) {
+        return None;
+    }
+    match func10 >> 8 {
+        0b00 => Some(Instruction::PushMultiple { first, last }),
+        0b01 => Some(Instruction::PopMultiple  { first, last }),
+        _    => None,
+    }
+}
+
+// Post-increment memory operations 0x7/0b10, bits [9:8] select the operation and
+// bit 0 sign-extends a byte load
+fn decode_post_increment(dst: u8, base: u8, func10: u16) -> Option<Instruction> {
+    match (func10 >> 8, func10 & 0xFF) {
+        (0b10, 1) => return Some(Instruction::LoadByteSignedPostInc { dst, base }),
+        (_, 0) => {}
+        _ => return None,
+    }
+    match func10 >> 8 {
+        0b00 => Some(Instruction::LoadPostInc      { dst, base }),
+        0b01 => Some(Instruction::StorePostInc     { src: dst, base }),
+        0b10 => Some(Instruction::LoadBytePostInc  { dst, base }),
+        0b11 => Some(Instruction::StoreBytePostInc { src: dst, base }),
         _    => None,
     }
 }
 
 // Move operations 0x8 (regular move, limited by 8-bit dst, 10-bit src)
-fn decode_move(secondary: u8, dst: u8, src10: u16) -> Option<Instruction> {
+fn decode_move(secondary: u8, dst: u8, src1: u8, src10: u16) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::Move          { dst, src: src10 }),
         0b01 => Some(Instruction::MoveIfZero    { dst, src: src10 }),
         0b10 => Some(Instruction::MoveIfNotZero { dst, src: src10 }),
+        0b11 => decode_move_select(dst, src1, src10),
+        _    => None,
+    }
+}
+
+// Move operations 0x8/0b11, bits [9:8] select the operation, bits [7:0] hold
+// the upper byte for LoadUpperImm or the second register for Min/Max
+fn decode_move_select(dst: u8, src1: u8, func10: u16) -> Option<Instruction> {
+    let src2 = func10 as u8;
+    match func10 >> 8 {
+        0b00 => Some(Instruction::LoadUpperImm { dst, value: func10 as u8 }),
+        0b01 => Some(Instruction::Min { dst, src1, src2 }),
+        0b10 => Some(Instruction::Max { dst, src1, src2 }),
         _    => None,
     }
 }
 
 // Wide moves: 10-bit destination, 8-bit source
-fn decode_move_wide(secondary: u8, dst10: u16, src8: u8) -> Option<Instruction> {
+fn decode_move_wide(secondary: u8, dst10: u16, src8: u8, offset8: u8) -> Option<Instruction> {
     match secondary {
         0b00 => Some(Instruction::MoveWide      { dst: dst10, src: src8 }),
         0b01 => Some(Instruction::MoveWideIfZero    { dst: dst10, src: src8 }),
         0b10 => Some(Instruction::MoveWideIfNotZero { dst: dst10, src: src8 }),
+        0b11 => decode_branch_32(src8, dst10, offset8),
         _    => None,
     }
 }
+
+// 32-bit compare-and-branch 0xA/0b11: bits [9:8] select the condition, bits [7:0]
+// hold src2, the dst field holds the signed word offset
+fn decode_branch_32(src1: u8, func10: u16, offset8: u8) -> Option<Instruction> {
+    let src2 = func10 as u8;
+    let offset = sign_extend_8(offset8);
+    match func10 >> 8 {
+        0b00 => Some(Instruction::BranchEqual32       { src1, src2, offset }),
+        0b01 => Some(Instruction::BranchNotEqual32    { src1, src2, offset }),
+        0b10 => Some(Instruction::BranchLessThan32    { src1, src2, offset }),
+        0b11 => Some(Instruction::BranchGreaterThan32 { src1, src2, offset }),
+        _    => None,
+    }
+}
+
+// Machine word for an instruction, the inverse of decode. Fields are truncated to their
+// width, so callers range-check operands first (the assembler does).
+pub fn encode(instruction: &Instruction) -> u32 {
+    // Same layout as decode: primary, secondary, the 8-bit dst and src1 fields, and the
+    // low 10 bits (register, immediate or function code)
+    fn word(primary: u32, secondary: u32, dst: u8, src1: u8, low10: u16) -> u32 {
+        (primary << 28) | (secondary << 26) | ((dst as u32) << 18) | ((src1 as u32) << 10) | (low10 as u32 & 0x3FF)
+    }
+    let imm = |value: i16| value as u16 & 0x3FF;
+    let func = |select: u16, low8: u8| (select << 8) | low8 as u16;
+    let system = |select: u16, imm6: u8| (select << 6) | (imm6 as u16 & 0x3F);
+
+    match *instruction {
+        Instruction::Nop => word(0xF, 0, 0, 0, 0),
+        Instruction::Halt => word(0xE, 0, 0, 0, 0),
+
+        Instruction::Load { dst, addr } => word(0x0, 0b00, dst, 0, addr),
+        Instruction::LoadImm { dst, value } => word(0x0, 0b01, dst, 0, imm(value)),
+        Instruction::Store { src, addr } => word(0x0, 0b10, src, 0, addr),
+        Instruction::LoadByteSigned { dst, base, offset } => word(0x0, 0b11, dst, base, imm(offset)),
+
+        Instruction::LoadIndexed { dst, base, offset } => word(0xC, 0b00, dst, base, imm(offset)),
+        Instruction::StoreIndexed { src, base, offset } => word(0xC, 0b01, src, base, imm(offset)),
+        Instruction::LoadByte { dst, base, offset } => word(0xC, 0b10, dst, base, imm(offset)),
+        Instruction::StoreByte { src, base, offset } => word(0xC, 0b11, src, base, imm(offset)),
+
+        Instruction::Add { dst, src1, src2 } => word(0x1, 0b00, dst, src1, src2),
+        Instruction::Sub { dst, src1, src2 } => word(0x1, 0b01, dst, src1, src2),
+        Instruction::AddImm { dst, src, imm: value } => word(0x1, 0b10, dst, src, imm(value)),
+        Instruction::SubImm { dst, src, imm: value } => word(0x1, 0b11, dst, src, imm(value)),
+
+        Instruction::Mult { dst, src1, src2 } => word(0x9, 0b00, dst, src1, src2),
+        Instruction::MultImm { dst, src, imm: value } => word(0x9, 0b01, dst, src, imm(value)),
+        Instruction::Div { dst, src1, src2 } => word(0x9, 0b10, dst, src1, src2),
+        Instruction::Mod { dst, src1, src2 } => word(0x9, 0b11, dst, src1, src2),
+
+        Instruction::And { dst, src1, src2 } => word(0x2, 0b00, dst, src1, src2),
+        Instruction::Or { dst, src1, src2 } => word(0x2, 0b01, dst, src1, src2),
+        Instruction::Xor { dst, src1, src2 } => word(0x2, 0b10, dst, src1, src2),
+        Instruction::Not { dst, src } => word(0x2, 0b11, dst, src, 0),
+
+        Instruction::BranchEqual { src1, src2, addr } => word(0x3, 0b00, addr as u8, src1, src2),
+        Instruction::BranchNotEqual { src1, src2, addr } => word(0x3, 0b01, addr as u8, src1, src2),
+        Instruction::BranchLessThan { src1, src2, addr } => word(0x3, 0b10, addr as u8, src1, src2),
+        Instruction::BranchGreaterThan { src1, src2, addr } => word(0x3, 0b11, addr as u8, src1, src2),
+
+        Instruction::Jump { addr } => word(0x4, 0b00, 0, 0, addr),
+        Instruction::JumpReg { reg } => word(0x4, 0b01, reg as u8, 0, 0),
+        Instruction::JumpRel { offset } => word(0x4, 0b10, 0, 0, imm(offset)),
+        Instruction::BankSelect { bank } => word(0x4, 0b11, 0, 0, system(0b0000, bank)),
+        Instruction::ReturnFromException => word(0x4, 0b11, 0, 0, system(0b0001, 0)),
+        Instruction::SystemCall { code } => word(0x4, 0b11, 0, 0, system(0b0010, code)),
+        Instruction::EnableInterrupts => word(0x4, 0b11, 0, 0, system(0b0011, 0)),
+        Instruction::DisableInterrupts => word(0x4, 0b11, 0, 0, system(0b0100, 0)),
+        Instruction::MoveToControl { cr, src } => word(0x4, 0b11, src, 0, system(0b0101, cr)),
+        Instruction::MoveFromControl { dst, cr } => word(0x4, 0b11, dst, 0, system(0b0110, cr)),
+        Instruction::FlushTlb => word(0x4, 0b11, 0, 0, system(0b0111, 0)),
+        Instruction::FlushTlbPage { reg } => word(0x4, 0b11, reg, 0, system(0b1000, 0)),
+        Instruction::FarJump { bank, target } => word(0x4, 0b11, target, 0, system(0b1001, bank)),
+        Instruction::FarCall { bank, target } => word(0x4, 0b11, target, 0, system(0b1010, bank)),
+        Instruction::FarReturn => word(0x4, 0b11, 0, 0, system(0b1011, 0)),
+        Instruction::SetDataBank { src } => word(0x4, 0b11, src, 0, system(0b1100, 0)),
+
+        Instruction::Cmp { src1, src2 } => word(0x5, 0b00, 0, src1, src2),
+        Instruction::CmpImm { src, imm: value } => word(0x5, 0b01, 0, src, imm(value)),
+        Instruction::SatAdd { dst, src1, src2 } => word(0x5, 0b10, dst, src1, func(0b00, src2)),
+        Instruction::SatSub { dst, src1, src2 } => word(0x5, 0b10, dst, src1, func(0b01, src2)),
+        Instruction::Q15Mult { dst, src1, src2 } => word(0x5, 0b10, dst, src1, func(0b10, src2)),
+        Instruction::MultAcc { acc, src1, src2 } => word(0x5, 0b10, acc, src1, func(0b11, src2)),
+        Instruction::AddCarry { dst, src1, src2 } => word(0x5, 0b11, dst, src1, func(0b00, src2)),
+        Instruction::SubBorrow { dst, src1, src2 } => word(0x5, 0b11, dst, src1, func(0b01, src2)),
+        Instruction::MultWide { dst, src1, src2 } => word(0x5, 0b11, dst, src1, func(0b10, src2)),
+        Instruction::MultWideSigned { dst, src1, src2 } => word(0x5, 0b11, dst, src1, func(0b11, src2)),
+
+        Instruction::ShiftLeft { dst, src, amount } => word(0x6, 0b00, dst, src, amount),
+        Instruction::ShiftRight { dst, src, amount } => word(0x6, 0b01, dst, src, amount),
+        Instruction::ShiftRightArith { dst, src, amount } => word(0x6, 0b10, dst, src, amount & 0xF),
+        Instruction::RotateLeft { dst, src, amount } => word(0x6, 0b10, dst, src, (0b001 << 4) | (amount & 0xF)),
+        Instruction::RotateRight { dst, src, amount } => word(0x6, 0b10, dst, src, (0b010 << 4) | (amount & 0xF)),
+        Instruction::RotateLeftCarry { dst, src, amount } => word(0x6, 0b10, dst, src, (0b011 << 4) | (amount & 0xF)),
+        Instruction::RotateRightCarry { dst, src, amount } => word(0x6, 0b10, dst, src, (0b100 << 4) | (amount & 0xF)),
+        Instruction::RotateRightReg { dst, src, amount } => word(0x6, 0b10, dst, src, func(0b11, amount)),
+        Instruction::ShiftLeftReg { dst, src, amount } => word(0x6, 0b11, dst, src, func(0b00, amount)),
+        Instruction::ShiftRightReg { dst, src, amount } => word(0x6, 0b11, dst, src, func(0b01, amount)),
+        Instruction::ShiftRightArithReg { dst, src, amount } => word(0x6, 0b11, dst, src, func(0b10, amount)),
+        Instruction::RotateLeftReg { dst, src, amount } => word(0x6, 0b11, dst, src, func(0b11, amount)),
+
+        Instruction::Push { src } => word(0x7, 0b00, 0, 0, src),
+        Instruction::Pop { dst } => word(0x7, 0b01, 0, 0, dst),
+        Instruction::LoadPostInc { dst, base } => word(0x7, 0b10, dst, base, func(0b00, 0)),
+        Instruction::StorePostInc { src, base } => word(0x7, 0b10, src, base, func(0b01, 0)),
+        Instruction::LoadBytePostInc { dst, base } => word(0x7, 0b10, dst, base, func(0b10, 0)),
+        Instruction::LoadByteSignedPostInc { dst, base } => word(0x7, 0b10, dst, base, func(0b10, 1)),
+        Instruction::StoreBytePostInc { src, base } => word(0x7, 0b10, src, base, func(0b11, 0)),
+        Instruction::PushMultiple { first, last } => word(0x7, 0b11, first, 0, func(0b00, last)),
+        Instruction::PopMultiple { first, last } => word(0x7, 0b11, first, 0, func(0b01, last)),
+
+        Instruction::Move { dst, src } => word(0x8, 0b00, dst, 0, src),
+        Instruction::MoveIfZero { dst, src } => word(0x8, 0b01, dst, 0, src),
+        Instruction::MoveIfNotZero { dst, src } => word(0x8, 0b10, dst, 0, src),
+        Instruction::LoadUpperImm { dst, value } => word(0x8, 0b11, dst, 0, func(0b00, value)),
+        Instruction::Min { dst, src1, src2 } => word(0x8, 0b11, dst, src1, func(0b01, src2)),
+        Instruction::Max { dst, src1, src2 } => word(0x8, 0b11, dst, src1, func(0b10, src2)),
+
+        Instruction::MoveWide { dst, src } => word(0xA, 0b00, 0, src, dst),
+        Instruction::MoveWideIfZero { dst, src } => word(0xA, 0b01, 0, src, dst),
+        Instruction::MoveWideIfNotZero { dst, src } => word(0xA, 0b10, 0, src, dst),
+        Instruction::BranchEqual32 { src1, src2, offset } => word(0xA, 0b11, offset as u8, src1, func(0b00, src2)),
+        Instruction::BranchNotEqual32 { src1, src2, offset } => word(0xA, 0b11, offset as u8, src1, func(0b01, src2)),
+        Instruction::BranchLessThan32 { src1, src2, offset } => word(0xA, 0b11, offset as u8, src1, func(0b10, src2)),
+        Instruction::BranchGreaterThan32 { src1, src2, offset } => word(0xA, 0b11, offset as u8, src1, func(0b11, src2)),
+
+        Instruction::BranchEqualRel { src1, src2, offset } => word(0xB, 0b00, offset as u8, src1, src2),
+        Instruction::BranchNotEqualRel { src1, src2, offset } => word(0xB, 0b01, offset as u8, src1, src2),
+        Instruction::BranchLessThanRel { src1, src2, offset } => word(0xB, 0b10, offset as u8, src1, src2),
+        Instruction::BranchGreaterThanRel { src1, src2, offset } => word(0xB, 0b11, offset as u8, src1, src2),
+
+        Instruction::AndImm { dst, src, imm: value } => word(0xD, 0b00, dst, src, value),
+        Instruction::OrImm { dst, src, imm: value } => word(0xD, 0b01, dst, src, value),
+        Instruction::XorImm { dst, src, imm: value } => word(0xD, 0b10, dst, src, value),
+        Instruction::CountLeadingZeros { dst, src } => word(0xD, 0b11, dst, src, 0),
+        Instruction::CountTrailingZeros { dst, src } => word(0xD, 0b11, dst, src, 0b001),
+        Instruction::Popcount { dst, src } => word(0xD, 0b11, dst, src, 0b010),
+        Instruction::ByteSwap { dst, src } => word(0xD, 0b11, dst, src, 0b011),
+        Instruction::Abs { dst, src } => word(0xD, 0b11, dst, src, 0b100),
+        Instruction::BitSet { dst, src, index } => word(0xD, 0b11, dst, src, (0b01 << 8) | (index as u16 & 0xF)),
+        Instruction::BitClear { dst, src, index } => word(0xD, 0b11, dst, src, (0b01 << 8) | (0b01 << 4) | (index as u16 & 0xF)),
+        Instruction::BitTest { dst, src, index } => word(0xD, 0b11, dst, src, (0b01 << 8) | (0b10 << 4) | (index as u16 & 0xF)),
+        Instruction::BitFieldExtract { dst, src, pos, width } => {
+            word(0xD, 0b11, dst, src, (0b10 << 8) | ((pos as u16 & 0xF) << 4) | (width.wrapping_sub(1) as u16 & 0xF))
+        }
+        Instruction::BitFieldInsert { dst, src, pos, width } => {
+            word(0xD, 0b11, dst, src, (0b11 << 8) | ((pos as u16 & 0xF) << 4) | (width.wrapping_sub(1) as u16 & 0xF))
+        }
+    }
+}
+
+// Whether the low 10 bits of `word` are sign-extended by the instruction, for patching
+// them with a value that has to fit
+pub fn imm10_is_signed(word: u32) -> bool {
+    matches!(
+        decode(word),
+        Some(
+            Instruction::LoadImm { .. }
+                | Instruction::LoadByteSigned { .. }
+                | Instruction::LoadIndexed { .. }
+                | Instruction::StoreIndexed { .. }
+                | Instruction::LoadByte { .. }
+                | Instruction::StoreByte { .. }
+                | Instruction::AddImm { .. }
+                | Instruction::SubImm { .. }
+                | Instruction::MultImm { .. }
+                | Instruction::CmpImm { .. }
+                | Instruction::JumpRel { .. }
+        )
+    )
+}
+
+// Address of a base + offset memory access
+pub fn effective_address(base: u16, offset: i16) -> u16 {
+    base.wrapping_add(offset as u16)
+}
+
+// Target of a PC-relative branch/jump located at `pc`
+pub fn relative_target(pc: u16, offset: i16) -> u16 {
+    pc.wrapping_add((offset as u16).wrapping_mul(4))
+}
+
+// Pseudo-instruction `li dst, value`: the shortest sequence that builds a 16-bit constant
+pub fn expand_load_immediate(dst: u8, value: u16) -> Vec<Instruction> {
+    let signed = value as i16;
+    if (-512..=511).contains(&signed) {
+        vec![Instruction::LoadImm { dst, value: signed }]
+    } else if value & 0xFF == 0 {
+        vec![Instruction::LoadUpperImm { dst, value: (value >> 8) as u8 }]
+    } else {
+        expand_load_address(dst, value)
+    }
+}
+
+// Pseudo-instruction `la dst, addr`: always two instructions so a linker can patch
+// the halves (Upper8 / Lower8 relocations) without changing the code size
+pub fn expand_load_address(dst: u8, addr: u16) -> Vec<Instruction> {
+    vec![
+        Instruction::LoadUpperImm { dst, value: (addr >> 8) as u8 },
+        Instruction::OrImm { dst, src: dst, imm: addr & 0xFF },
+    ]
+}
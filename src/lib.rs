@@ -1,6 +1,12 @@
+pub mod asm;
 pub mod core;
 pub mod isa;
+pub mod linker;
+pub mod loader;
 pub mod memory;
 pub mod peripherals;
 pub mod utils;
 
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,251 @@
+pub mod object;
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::isa;
+use crate::loader::{Image, Symbol};
+use object::{ObjectFile, RelocKind, SectionKind};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkError {
+    DuplicateSymbol(String),
+    UndefinedSymbol(String),
+    RelocationOverflow { symbol: String, kind: RelocKind, value: i32 },
+    BadRelocation { object: String, offset: u16 },
+    OutOfMemory, // Laid out sections run past the end of the address space
+    SectionOverlap { object: String, section: String, addr: u16 }, // Placed over an earlier section
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkerConfig {
+    pub text_base: u16,
+    pub data_base: Option<u16>, // None = place data right after text
+    pub entry_symbol: String,
+}
+
+impl LinkerConfig {
+    pub fn new() -> Self {
+        Self {
+            text_base: 0,
+            data_base: None,
+            entry_symbol: "_start".to_string(),
+        }
+    }
+}
+
+impl Default for LinkerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LinkOutput {
+    pub image: Image,
+    pub map: String,
+}
+
+// Where one input section ended up
+struct Placement {
+    object: usize,
+    section: usize,
+    addr: u16,
+}
+
+pub fn link(objects: &[ObjectFile], config: &LinkerConfig) -> Result<LinkOutput, LinkError> {
+    // Lay out all text sections first, then all data sections, in input order
+    let mut placements: Vec<Placement> = Vec::new();
+    let mut next = config.text_base as u32;
+    for kind in [SectionKind::Text, SectionKind::Data] {
+        if kind == SectionKind::Data {
+            if let Some(base) = config.data_base {
+                next = base as u32;
+            }
+        }
+        for (o, obj) in objects.iter().enumerate() {
+            for (s, section) in obj.sections.iter().enumerate() {
+                if section.kind != kind {
+                    continue;
+                }
+                let align = section.align as u32;
+                next = next.div_ceil(align) * align;
+                if next + section.data.len() as u32 > 0x10000 {
+                    return Err(LinkError::OutOfMemory);
+                }
+                placements.push(Placement { object: o, section: s, addr: next as u16 });
+                next += section.data.len() as u32;
+            }
+        }
+    }
+
+    // A fixed data_base can put data on top of text
+    let size = |p: &Placement| objects[p.object].sections[p.section].data.len() as u32;
+    let mut by_addr: Vec<&Placement> = placements.iter().filter(|p| size(p) > 0).collect();
+    by_addr.sort_by_key(|p| p.addr);
+    for pair in by_addr.windows(2) {
+        if pair[0].addr as u32 + size(pair[0]) > pair[1].addr as u32 {
+            let (obj, p) = (&objects[pair[1].object], pair[1]);
+            return Err(LinkError::SectionOverlap {
+                object: obj.name.clone(),
+                section: obj.sections[p.section].name.clone(),
+                addr: p.addr,
+            });
+        }
+    }
+
+    let section_addr = |object: usize, section: usize| {
+        placements
+            .iter()
+            .find(|p| p.object == object && p.section == section)
+            .map(|p| p.addr)
+    };
+
+    // Collect global definitions
+    let mut globals: HashMap<&str, u16> = HashMap::new();
+    for (o, obj) in objects.iter().enumerate() {
+        for symbol in obj.symbols.iter().filter(|s| s.global) {
+            if let Some(section) = symbol.section {
+                let base = section_addr(o, section).ok_or(LinkError::UndefinedSymbol(symbol.name.clone()))?;
+                if globals.insert(&symbol.name, base.wrapping_add(symbol.offset)).is_some() {
+                    return Err(LinkError::DuplicateSymbol(symbol.name.clone()));
+                }
+            }
+        }
+    }
+
+    // Copy section contents and apply relocations
+    let mut contents: Vec<Vec<u8>> = placements
+        .iter()
+        .map(|p| objects[p.object].sections[p.section].data.clone())
+        .collect();
+
+    for (o, obj) in objects.iter().enumerate() {
+        for reloc in &obj.relocations {
+            let bad = LinkError::BadRelocation { object: obj.name.clone(), offset: reloc.offset };
+            let symbol = obj.symbols.get(reloc.symbol).ok_or(bad.clone())?;
+            let target = match symbol.section {
+                Some(section) => section_addr(o, section).ok_or(bad.clone())?.wrapping_add(symbol.offset),
+                None => *globals
+                    .get(symbol.name.as_str())
+                    .ok_or(LinkError::UndefinedSymbol(symbol.name.clone()))?,
+            };
+            let value = target as i32 + reloc.addend as i32;
+
+            let index = placements
+                .iter()
+                .position(|p| p.object == o && p.section == reloc.section)
+                .ok_or(bad.clone())?;
+            let data = &mut contents[index];
+            let offset = reloc.offset as usize;
+
+            let overflow = LinkError::RelocationOverflow { symbol: symbol.name.clone(), kind: reloc.kind, value };
+            match reloc.kind {
+                RelocKind::BranchRel8 | RelocKind::JumpRel10 => {
+                    // Word offset relative to the address of the instruction being patched
+                    let place = placements[index].addr as i32 + offset as i32;
+                    let delta = value - place;
+                    let (min, max) = if reloc.kind == RelocKind::BranchRel8 { (-128, 127) } else { (-512, 511) };
+                    if delta % 4 != 0 || !(min..=max).contains(&(delta / 4)) {
+                        return Err(LinkError::RelocationOverflow {
+                            symbol: symbol.name.clone(),
+                            kind: reloc.kind,
+                            value: delta,
+                        });
+                    }
+                    let bytes = data.get_mut(offset..offset + 4).ok_or(bad)?;
+                    let mut word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    let words = (delta / 4) as u32;
+                    if reloc.kind == RelocKind::BranchRel8 {
+                        word = (word & !(0xFF << 18)) | ((words & 0xFF) << 18);
+                    } else {
+                        word = (word & !0x3FF) | (words & 0x3FF);
+                    }
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                RelocKind::Imm10 | RelocKind::Branch8 => {
+                    let bytes = data.get_mut(offset..offset + 4).ok_or(bad)?;
+                    let mut word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    if reloc.kind == RelocKind::Imm10 {
+                        // LoadImm and the other signed forms sign-extend the field
+                        let (min, max) = if isa::imm10_is_signed(word) { (-512, 511) } else { (0, 0x3FF) };
+                        if !(min..=max).contains(&value) {
+                            return Err(overflow);
+                        }
+                        word = (word & !0x3FF) | (value as u32 & 0x3FF);
+                    } else {
+                        if !(0..=0xFF).contains(&value) {
+                            return Err(overflow);
+                        }
+                        word = (word & !(0xFF << 18)) | ((value as u32) << 18);
+                    }
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                RelocKind::Upper8 | RelocKind::Lower8 => {
+                    if !(0..=0xFFFF).contains(&value) {
+                        return Err(overflow);
+                    }
+                    let bytes = data.get_mut(offset..offset + 4).ok_or(bad)?;
+                    let mut word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    let half = if reloc.kind == RelocKind::Upper8 { value >> 8 } else { value & 0xFF };
+                    word = (word & !0x3FF) | half as u32;
+                    bytes.copy_from_slice(&word.to_le_bytes());
+                }
+                RelocKind::Data16 => {
+                    if !(-0x8000..=0xFFFF).contains(&value) {
+                        return Err(overflow);
+                    }
+                    let bytes = data.get_mut(offset..offset + 2).ok_or(bad)?;
+                    bytes.copy_from_slice(&(value as u16).to_le_bytes());
+                }
+            }
+        }
+    }
+
+    // Build the image
+    let mut image = Image::new();
+    let mut order: Vec<usize> = (0..placements.len()).collect();
+    order.sort_by_key(|&i| placements[i].addr);
+    for &i in &order {
+        image
+            .add_data(placements[i].addr as u32, &contents[i])
+            .map_err(|_| LinkError::OutOfMemory)?;
+    }
+
+    let mut symbols: Vec<Symbol> = globals
+        .iter()
+        .map(|(name, addr)| Symbol { name: name.to_string(), addr: *addr })
+        .collect();
+    symbols.sort_by(|a, b| a.addr.cmp(&b.addr).then(a.name.cmp(&b.name)));
+    image.symbols = symbols;
+    image.entry = Some(globals.get(config.entry_symbol.as_str()).copied().unwrap_or(config.text_base));
+
+    let map = write_map(objects, &placements, &order, &image);
+    Ok(LinkOutput { image, map })
+}
+
+fn write_map(objects: &[ObjectFile], placements: &[Placement], order: &[usize], image: &Image) -> String {
+    let mut map = String::new();
+    let _ = writeln!(map, "Entry point: 0x{:04X}", image.entry.unwrap_or(0));
+    let _ = writeln!(map);
+    let _ = writeln!(map, "Sections:");
+    for &i in order {
+        let p = &placements[i];
+        let obj = &objects[p.object];
+        let section = &obj.sections[p.section];
+        let _ = writeln!(
+            map,
+            "  0x{:04X}  {:>6}  {:<10} {}",
+            p.addr,
+            section.data.len(),
+            section.name,
+            obj.name
+        );
+    }
+    let _ = writeln!(map);
+    let _ = writeln!(map, "Symbols:");
+    for symbol in &image.symbols {
+        let _ = writeln!(map, "  0x{:04X}  {}", symbol.addr, symbol.name);
+    }
+    map
+}
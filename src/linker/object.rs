@@ -0,0 +1,240 @@
+// Relocatable object format (all fields little endian)
+//
+// Header (12 bytes):
+//   0  magic "RROB"
+//   4  version           u16
+//   6  section count     u16
+//   8  symbol count      u16
+//  10  relocation count  u16
+// Sections:    kind u8, align u16, name (len u8 + bytes), size u16, data bytes
+// Symbols:     name (len u8 + bytes), section u16 (0xFFFF = undefined), offset u16, flags u8 (bit 0: global)
+// Relocations: section u16, offset u16, kind u8, symbol u16, addend i16
+use crate::loader::LoadError;
+
+pub const MAGIC: [u8; 4] = *b"RROB";
+pub const VERSION: u16 = 1;
+
+const UNDEFINED_SECTION: u16 = 0xFFFF;
+const FLAG_GLOBAL: u8 = 1 << 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Text,
+    Data,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub name: String,
+    pub kind: SectionKind,
+    pub align: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectSymbol {
+    pub name: String,
+    pub section: Option<usize>, // None = undefined, resolved by the linker
+    pub offset: u16,
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocKind {
+    Imm10,      // bits [9:0] of an instruction word (absolute address / immediate)
+    Branch8,    // bits [25:18] of an instruction word (absolute branch target)
+    Data16,     // 16-bit little endian data word
+    BranchRel8, // bits [25:18], signed word offset from the branch itself
+    JumpRel10,  // bits [9:0], signed word offset from the jump itself
+    Upper8,     // bits [7:0] = high byte of the address (LoadUpperImm of `la`)
+    Lower8,     // bits [9:0] = low byte of the address (OrImm of `la`)
+}
+
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub section: usize,
+    pub offset: u16,     // Byte offset of the patched word within the section
+    pub kind: RelocKind,
+    pub symbol: usize,   // Index into the object's symbol table
+    pub addend: i16,
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectFile {
+    pub name: String,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl RelocKind {
+    fn to_byte(self) -> u8 {
+        match self {
+            RelocKind::Imm10 => 0,
+            RelocKind::Branch8 => 1,
+            RelocKind::Data16 => 2,
+            RelocKind::BranchRel8 => 3,
+            RelocKind::JumpRel10 => 4,
+            RelocKind::Upper8 => 5,
+            RelocKind::Lower8 => 6,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(RelocKind::Imm10),
+            1 => Some(RelocKind::Branch8),
+            2 => Some(RelocKind::Data16),
+            3 => Some(RelocKind::BranchRel8),
+            4 => Some(RelocKind::JumpRel10),
+            5 => Some(RelocKind::Upper8),
+            6 => Some(RelocKind::Lower8),
+            _ => None,
+        }
+    }
+}
+
+impl ObjectFile {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sections: Vec::new(),
+            symbols: Vec::new(),
+            relocations: Vec::new(),
+        }
+    }
+
+    pub fn add_section(&mut self, name: &str, kind: SectionKind, align: u16, data: Vec<u8>) -> usize {
+        self.sections.push(Section { name: name.to_string(), kind, align: align.max(1), data });
+        self.sections.len() - 1
+    }
+
+    pub fn add_symbol(&mut self, name: &str, section: Option<usize>, offset: u16, global: bool) -> usize {
+        self.symbols.push(ObjectSymbol { name: name.to_string(), section, offset, global });
+        self.symbols.len() - 1
+    }
+
+    pub fn add_relocation(&mut self, section: usize, offset: u16, kind: RelocKind, symbol: usize, addend: i16) {
+        self.relocations.push(Relocation { section, offset, kind, symbol, addend });
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.sections.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(self.symbols.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(self.relocations.len() as u16).to_le_bytes());
+
+        for section in &self.sections {
+            out.push(match section.kind {
+                SectionKind::Text => 0,
+                SectionKind::Data => 1,
+            });
+            out.extend_from_slice(&section.align.to_le_bytes());
+            push_name(&mut out, &section.name);
+            out.extend_from_slice(&(section.data.len() as u16).to_le_bytes());
+            out.extend_from_slice(&section.data);
+        }
+
+        for symbol in &self.symbols {
+            push_name(&mut out, &symbol.name);
+            let section = symbol.section.map(|s| s as u16).unwrap_or(UNDEFINED_SECTION);
+            out.extend_from_slice(&section.to_le_bytes());
+            out.extend_from_slice(&symbol.offset.to_le_bytes());
+            out.push(if symbol.global { FLAG_GLOBAL } else { 0 });
+        }
+
+        for reloc in &self.relocations {
+            out.extend_from_slice(&(reloc.section as u16).to_le_bytes());
+            out.extend_from_slice(&reloc.offset.to_le_bytes());
+            out.push(reloc.kind.to_byte());
+            out.extend_from_slice(&(reloc.symbol as u16).to_le_bytes());
+            out.extend_from_slice(&reloc.addend.to_le_bytes());
+        }
+
+        out
+    }
+
+    pub fn parse(name: &str, bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err(LoadError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+        let section_count = reader.u16()?;
+        let symbol_count = reader.u16()?;
+        let reloc_count = reader.u16()?;
+
+        let mut object = Self::new(name);
+
+        for _ in 0..section_count {
+            let kind = match reader.u8()? {
+                0 => SectionKind::Text,
+                1 => SectionKind::Data,
+                _ => return Err(LoadError::Malformed(reader.pos - 1)),
+            };
+            let align = reader.u16()?;
+            let name = reader.name()?;
+            let size = reader.u16()? as usize;
+            let data = reader.take(size)?.to_vec();
+            object.add_section(&name, kind, align, data);
+        }
+
+        for _ in 0..symbol_count {
+            let name = reader.name()?;
+            let section = reader.u16()?;
+            let offset = reader.u16()?;
+            let flags = reader.u8()?;
+            let section = if section == UNDEFINED_SECTION { None } else { Some(section as usize) };
+            object.add_symbol(&name, section, offset, flags & FLAG_GLOBAL != 0);
+        }
+
+        for _ in 0..reloc_count {
+            let section = reader.u16()? as usize;
+            let offset = reader.u16()?;
+            let kind = RelocKind::from_byte(reader.u8()?).ok_or(LoadError::Malformed(reader.pos - 1))?;
+            let symbol = reader.u16()? as usize;
+            let addend = reader.u16()? as i16;
+            object.add_relocation(section, offset, kind, symbol, addend);
+        }
+
+        Ok(object)
+    }
+}
+
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    let bytes = &name.as_bytes()[..name.len().min(255)];
+    out.push(bytes.len() as u8);
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let slice = self.bytes.get(self.pos..self.pos + len).ok_or(LoadError::Truncated)?;
+        self.pos += len;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, LoadError> {
+        let b = self.take(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn name(&mut self) -> Result<String, LoadError> {
+        let len = self.u8()? as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
@@ -0,0 +1,142 @@
+// RRISC executable container (all fields little endian)
+//
+// Header (16 bytes):
+//   0  magic "RRSC"
+//   4  version          u16
+//   6  flags            u16  (bit 0: entry point valid)
+//   8  entry point      u16
+//  10  segment count    u16
+//  12  symbol count     u16  (0 = no symbol table)
+//  14  reserved         u16
+// Segment table, one entry per segment:
+//   version 1 (8 bytes):  load address u16, length u16, file offset of the data u32
+//   version 2 (12 bytes): far load address u32 (bits [23:16] memory bank), length u16,
+//                         reserved u16, file offset of the data u32
+// Symbol table, one entry per symbol:
+//   address u16, name length u8, name bytes (UTF-8)
+// Segment data follows the tables. A segment longer than a u16 length can describe is
+// written as several table entries.
+use super::{Image, LoadError, Symbol};
+
+pub const MAGIC: [u8; 4] = *b"RRSC";
+pub const VERSION: u16 = 2;
+
+const FLAG_ENTRY: u16 = 1 << 0;
+const HEADER_SIZE: usize = 16;
+const SEGMENT_ENTRY_SIZE_V1: usize = 8;
+const SEGMENT_ENTRY_SIZE: usize = 12;
+const MAX_SEGMENT_LEN: usize = 0xFFFF;
+
+pub fn parse(bytes: &[u8]) -> Result<Image, LoadError> {
+    if bytes.len() < HEADER_SIZE {
+        return Err(LoadError::Truncated);
+    }
+    if bytes[0..4] != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+
+    let version = read_u16(bytes, 4)?;
+    if version != 1 && version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+    let flags = read_u16(bytes, 6)?;
+    let entry = read_u16(bytes, 8)?;
+    let segment_count = read_u16(bytes, 10)? as usize;
+    let symbol_count = read_u16(bytes, 12)? as usize;
+
+    let mut image = Image::new();
+    if flags & FLAG_ENTRY != 0 {
+        image.entry = Some(entry);
+    }
+
+    let mut pos = HEADER_SIZE;
+    for _ in 0..segment_count {
+        let (addr, len, offset) = if version == 1 {
+            (read_u16(bytes, pos)? as u32, read_u16(bytes, pos + 2)?, read_u32(bytes, pos + 4)?)
+        } else {
+            (read_u32(bytes, pos)?, read_u16(bytes, pos + 4)?, read_u32(bytes, pos + 8)?)
+        };
+        let start = offset as usize;
+        let end = start.checked_add(len as usize).ok_or(LoadError::Truncated)?;
+        let data = bytes.get(start..end).ok_or(LoadError::Truncated)?;
+        image.add_data(addr, data)?;
+        pos += if version == 1 { SEGMENT_ENTRY_SIZE_V1 } else { SEGMENT_ENTRY_SIZE };
+    }
+
+    for _ in 0..symbol_count {
+        let addr = read_u16(bytes, pos)?;
+        let len = *bytes.get(pos + 2).ok_or(LoadError::Truncated)? as usize;
+        let name = bytes.get(pos + 3..pos + 3 + len).ok_or(LoadError::Truncated)?;
+        image.symbols.push(Symbol {
+            name: String::from_utf8_lossy(name).into_owned(),
+            addr,
+        });
+        pos += 3 + len;
+    }
+
+    Ok(image)
+}
+
+// Fails if a symbol name is too long for its u8 length field
+pub fn write(image: &Image) -> Result<Vec<u8>, LoadError> {
+    if let Some(index) = image.symbols.iter().position(|s| s.name.len() > u8::MAX as usize) {
+        return Err(LoadError::NameTooLong(index));
+    }
+    let symbols = &image.symbols;
+    let symbol_table_size: usize = symbols.iter().map(|s| 3 + s.name.len()).sum();
+    // (far address, data) of each segment table entry
+    let entries: Vec<(u32, &[u8])> = image
+        .segments
+        .iter()
+        .flat_map(|segment| {
+            segment
+                .data
+                .chunks(MAX_SEGMENT_LEN)
+                .enumerate()
+                .map(move |(i, chunk)| (segment.addr + (i * MAX_SEGMENT_LEN) as u32, chunk))
+        })
+        .collect();
+    let mut data_offset = HEADER_SIZE + entries.len() * SEGMENT_ENTRY_SIZE + symbol_table_size;
+
+    let mut out = Vec::with_capacity(data_offset + image.segments.iter().map(|s| s.data.len()).sum::<usize>());
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(if image.entry.is_some() { FLAG_ENTRY } else { 0 }).to_le_bytes());
+    out.extend_from_slice(&image.entry.unwrap_or(0).to_le_bytes());
+    out.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(symbols.len() as u16).to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+
+    for (addr, data) in &entries {
+        out.extend_from_slice(&addr.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&(data_offset as u32).to_le_bytes());
+        data_offset += data.len();
+    }
+
+    for symbol in symbols {
+        out.extend_from_slice(&symbol.addr.to_le_bytes());
+        out.push(symbol.name.len() as u8);
+        out.extend_from_slice(symbol.name.as_bytes());
+    }
+
+    for (_, data) in &entries {
+        out.extend_from_slice(data);
+    }
+    Ok(out)
+}
+
+fn read_u16(bytes: &[u8], pos: usize) -> Result<u16, LoadError> {
+    bytes
+        .get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or(LoadError::Truncated)
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Result<u32, LoadError> {
+    bytes
+        .get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or(LoadError::Truncated)
+}
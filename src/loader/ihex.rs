@@ -0,0 +1,106 @@
+// Intel HEX reader/writer
+//
+// :LLAAAATT[DD..]CC
+//  LL = byte count, AAAA = offset, TT = record type, CC = two's complement checksum
+use super::{decode_hex, push_hex, Image, LoadError};
+
+const DATA: u8 = 0x00;
+const END_OF_FILE: u8 = 0x01;
+const EXT_SEGMENT_ADDR: u8 = 0x02;
+const START_SEGMENT_ADDR: u8 = 0x03;
+const EXT_LINEAR_ADDR: u8 = 0x04;
+const START_LINEAR_ADDR: u8 = 0x05;
+
+const BYTES_PER_RECORD: usize = 16;
+
+pub fn parse(text: &str) -> Result<Image, LoadError> {
+    let mut image = Image::new();
+    let mut base: u32 = 0;
+
+    for (n, line) in text.lines().enumerate() {
+        let line_no = n + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let record = line
+            .strip_prefix(':')
+            .and_then(decode_hex)
+            .ok_or(LoadError::InvalidRecord(line_no))?;
+        if record.len() < 5 || record.len() != record[0] as usize + 5 {
+            return Err(LoadError::InvalidRecord(line_no));
+        }
+
+        let sum = record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        if sum != 0 {
+            return Err(LoadError::BadChecksum(line_no));
+        }
+
+        let offset = u16::from_be_bytes([record[1], record[2]]) as u32;
+        let data = &record[4..record.len() - 1];
+
+        match record[3] {
+            DATA => image.add_data(base + offset, data)?,
+            END_OF_FILE => return Ok(image),
+            EXT_SEGMENT_ADDR if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            EXT_LINEAR_ADDR if data.len() == 2 => {
+                base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            START_SEGMENT_ADDR if data.len() == 4 => {
+                let cs = u16::from_be_bytes([data[0], data[1]]) as u32;
+                let ip = u16::from_be_bytes([data[2], data[3]]) as u32;
+                image.set_entry((cs << 4) + ip)?;
+            }
+            START_LINEAR_ADDR if data.len() == 4 => {
+                image.set_entry(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))?;
+            }
+            EXT_SEGMENT_ADDR | EXT_LINEAR_ADDR | START_SEGMENT_ADDR | START_LINEAR_ADDR => {
+                return Err(LoadError::InvalidRecord(line_no));
+            }
+            _ => return Err(LoadError::UnsupportedRecord(line_no)),
+        }
+    }
+
+    Err(LoadError::MissingEnd)
+}
+
+pub fn write(image: &Image) -> String {
+    let mut out = String::new();
+    let mut base: u32 = 0;
+
+    for segment in &image.segments {
+        let mut addr = segment.addr;
+        for chunk in segment.data.chunks(BYTES_PER_RECORD) {
+            // Banked segments: the bank goes in the upper 16 bits of a linear address
+            if addr >> 16 != base {
+                base = addr >> 16;
+                write_record(&mut out, 0, EXT_LINEAR_ADDR, &(base as u16).to_be_bytes());
+            }
+            write_record(&mut out, addr as u16, DATA, chunk);
+            addr += chunk.len() as u32;
+        }
+    }
+
+    if let Some(entry) = image.entry {
+        write_record(&mut out, 0, START_LINEAR_ADDR, &(entry as u32).to_be_bytes());
+    }
+    write_record(&mut out, 0, END_OF_FILE, &[]);
+    out
+}
+
+fn write_record(out: &mut String, offset: u16, kind: u8, data: &[u8]) {
+    let mut record = Vec::with_capacity(data.len() + 5);
+    record.push(data.len() as u8);
+    record.extend_from_slice(&offset.to_be_bytes());
+    record.push(kind);
+    record.extend_from_slice(data);
+    let sum = record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    record.push(sum.wrapping_neg());
+
+    out.push(':');
+    push_hex(out, &record);
+    out.push('\n');
+}
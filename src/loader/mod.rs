@@ -0,0 +1,143 @@
+pub mod ihex;
+pub mod srec;
+pub mod exe;
+
+use crate::core::CpuState;
+use crate::memory::{self, Memory};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    InvalidRecord(usize),       // Malformed record on this line
+    BadChecksum(usize),         // Checksum mismatch on this line
+    UnsupportedRecord(usize),   // Record type we don't handle on this line
+    AddressOutOfRange(u32),     // Address is not in the 16-bit space or a memory bank window
+    MissingEnd,                 // No end-of-file / termination record
+    BadMagic,
+    UnsupportedVersion(u16),
+    Truncated,
+    Malformed(usize),           // Field with an invalid value at this byte offset of a binary file
+    NameTooLong(usize),         // Symbol name at this index does not fit the format's length field
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub addr: u32, // Far address: bits [23:16] memory bank, see memory::BANK_SIZE
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub addr: u16,
+}
+
+// A loadable program: memory contents, optional entry point and symbols
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub entry: Option<u16>,
+    pub segments: Vec<Segment>,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Image {
+    pub fn new() -> Self {
+        Self {
+            entry: None,
+            segments: Vec::new(),
+            symbols: Vec::new(),
+        }
+    }
+
+    // Raw binary: a single segment at `addr`, entry defaults to the load address
+    pub fn from_binary(data: &[u8], addr: u16, entry: Option<u16>) -> Result<Self, LoadError> {
+        let mut image = Self::new();
+        image.add_data(addr as u32, data)?;
+        image.entry = Some(entry.unwrap_or(addr));
+        Ok(image)
+    }
+
+    // Append data at far address `addr`, extending the last segment when contiguous
+    pub fn add_data(&mut self, addr: u32, data: &[u8]) -> Result<(), LoadError> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let end = addr.checked_add(data.len() as u32 - 1).ok_or(LoadError::AddressOutOfRange(addr))?;
+        if !far_range_valid(addr, end) {
+            return Err(LoadError::AddressOutOfRange(end));
+        }
+
+        // Only merge while the merged segment is still one valid range, so a segment
+        // never spans two bank windows
+        if let Some(last) = self.segments.last_mut() {
+            if last.addr + last.data.len() as u32 == addr && far_range_valid(last.addr, end) {
+                last.data.extend_from_slice(data);
+                return Ok(());
+            }
+        }
+
+        self.segments.push(Segment { addr, data: data.to_vec() });
+        Ok(())
+    }
+
+    pub fn set_entry(&mut self, entry: u32) -> Result<(), LoadError> {
+        if entry > 0xFFFF {
+            return Err(LoadError::AddressOutOfRange(entry));
+        }
+        self.entry = Some(entry as u16);
+        Ok(())
+    }
+
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.iter().find(|s| s.name == name).map(|s| s.addr)
+    }
+
+    // Copy all segments into memory (banked segments into their banks) and point the
+    // CPU at the entry point
+    pub fn load(&self, cpu: &mut CpuState, mem: &mut Memory) {
+        for segment in &self.segments {
+            mem.load_far(segment.addr, &segment.data);
+        }
+        if let Some(entry) = self.entry {
+            cpu.pc = entry;
+        }
+    }
+}
+
+impl Default for Image {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Both ends in the same bank; outside bank 0 they must also share a bank window
+fn far_range_valid(first: u32, last: u32) -> bool {
+    let bank = first >> 16;
+    if last >> 16 != bank || bank as usize >= memory::NUM_MEMORY_BANKS {
+        return false;
+    }
+    bank == 0 || {
+        let window = memory::window_of(first as u16);
+        window.is_some() && window == memory::window_of(last as u16)
+    }
+}
+
+// Decode a string of hex digit pairs ("0A1B..") into bytes
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut bytes = Vec::with_capacity(text.len() / 2);
+    for i in (0..text.len()).step_by(2) {
+        let pair = text.get(i..i + 2)?;
+        bytes.push(u8::from_str_radix(pair, 16).ok()?);
+    }
+    Some(bytes)
+}
+
+fn push_hex(out: &mut String, bytes: &[u8]) {
+    const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+    for b in bytes {
+        out.push(DIGITS[(b >> 4) as usize] as char);
+        out.push(DIGITS[(b & 0xF) as usize] as char);
+    }
+}
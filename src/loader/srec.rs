@@ -0,0 +1,107 @@
+// Motorola S-record reader/writer
+//
+// S<T><CC><ADDR><DD..><SS>
+//  CC = byte count of address + data + checksum, SS = one's complement of the sum
+use super::{decode_hex, push_hex, Image, LoadError};
+
+const BYTES_PER_RECORD: usize = 16;
+
+pub fn parse(text: &str) -> Result<Image, LoadError> {
+    let mut image = Image::new();
+    let mut data_records: u32 = 0;
+
+    for (n, line) in text.lines().enumerate() {
+        let line_no = n + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let kind = line
+            .strip_prefix('S')
+            .and_then(|rest| rest.chars().next())
+            .and_then(|c| c.to_digit(10))
+            .ok_or(LoadError::InvalidRecord(line_no))?;
+        let record = decode_hex(&line[2..]).ok_or(LoadError::InvalidRecord(line_no))?;
+        if record.len() < 2 || record.len() != record[0] as usize + 1 {
+            return Err(LoadError::InvalidRecord(line_no));
+        }
+
+        let sum = record[..record.len() - 1].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        if !sum != record[record.len() - 1] {
+            return Err(LoadError::BadChecksum(line_no));
+        }
+
+        let addr_len = match kind {
+            0 | 1 | 5 | 9 => 2,
+            2 | 6 | 8 => 3,
+            3 | 7 => 4,
+            _ => return Err(LoadError::UnsupportedRecord(line_no)),
+        };
+        if record.len() < addr_len + 2 {
+            return Err(LoadError::InvalidRecord(line_no));
+        }
+        let addr = record[1..1 + addr_len].iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+        let data = &record[1 + addr_len..record.len() - 1];
+
+        match kind {
+            0 => {} // Header, ignored
+            1..=3 => {
+                image.add_data(addr, data)?;
+                data_records += 1;
+            }
+            5 | 6 => {
+                // Record count: the address field holds the number of data records seen
+                if addr != data_records {
+                    return Err(LoadError::InvalidRecord(line_no));
+                }
+            }
+            _ => {
+                // S7/S8/S9 terminate the file with the start address
+                image.set_entry(addr)?;
+                return Ok(image);
+            }
+        }
+    }
+
+    Err(LoadError::MissingEnd)
+}
+
+pub fn write(image: &Image) -> String {
+    let mut out = String::new();
+    let mut data_records: u32 = 0;
+
+    write_record(&mut out, 0, 0, b"RRISC");
+
+    for segment in &image.segments {
+        let mut addr = segment.addr;
+        for chunk in segment.data.chunks(BYTES_PER_RECORD) {
+            // Banked segments need the 24-bit address of an S2 record
+            let kind = if addr > 0xFFFF { 2 } else { 1 };
+            write_record(&mut out, kind, addr, chunk);
+            addr += chunk.len() as u32;
+            data_records += 1;
+        }
+    }
+
+    // S5 holds a 16-bit count, S6 a 24-bit one
+    let count_kind = if data_records > 0xFFFF { 6 } else { 5 };
+    write_record(&mut out, count_kind, data_records, &[]);
+    write_record(&mut out, 9, image.entry.unwrap_or(0) as u32, &[]);
+    out
+}
+
+fn write_record(out: &mut String, kind: u8, addr: u32, data: &[u8]) {
+    let addr_len = if kind == 2 || kind == 6 { 3 } else { 2 };
+    let mut record = Vec::with_capacity(data.len() + addr_len + 2);
+    record.push((data.len() + addr_len + 1) as u8);
+    record.extend_from_slice(&addr.to_be_bytes()[4 - addr_len..]);
+    record.extend_from_slice(data);
+    let sum = record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    record.push(!sum);
+
+    out.push('S');
+    out.push((b'0' + kind) as char);
+    push_hex(out, &record);
+    out.push('\n');
+}
@@ -8,48 +8,47 @@ mod core;
 mod isa;
 mod memory;
 mod peripherals;
+mod utils;
 
 use core::{CpuState, control_unit};
+use core::rename::RenameScheme;
+use core::smt::FetchPolicy;
 use memory::Memory;
 
 #[entry]
 fn main() -> ! {
-    // Initialize the CPU and memory
-    let mut cpu = CpuState::new();
-    let mut mem = Memory::new();
-
     // Test program demonstrating Tomasulo's algorithm benefits
-    // This program has data dependencies that benefit from out-of-order execution
+    // This program has data dependencies that benefit from out-of-order execution.
+    // Instruction words are stored little endian, as Memory::fetch reads them.
     let program = [
         // Load immediate values
-        0x01, 0x00, 0x00, 0x0A, // LoadImm { dst: 0, value: 10 }  - R0 = 10
-        0x01, 0x01, 0x00, 0x14, // LoadImm { dst: 1, value: 20 }  - R1 = 20
-        0x01, 0x02, 0x00, 0x05, // LoadImm { dst: 2, value: 5 }   - R2 = 5
-        0x01, 0x03, 0x00, 0x03, // LoadImm { dst: 3, value: 3 }   - R3 = 3
+        0x0A, 0x00, 0x00, 0x04, // LoadImm { dst: 0, value: 10 }  - R0 = 10
+        0x14, 0x00, 0x04, 0x04, // LoadImm { dst: 1, value: 20 }  - R1 = 20
+        0x05, 0x00, 0x08, 0x04, // LoadImm { dst: 2, value: 5 }   - R2 = 5
+        0x03, 0x00, 0x0C, 0x04, // LoadImm { dst: 3, value: 3 }   - R3 = 3
 
         // Arithmetic operations with dependencies
-        0x10, 0x04, 0x00, 0x01, // Add { dst: 4, src1: 0, src2: 1 } - R4 = R0 + R1 (10 + 20 = 30)
-        0x11, 0x05, 0x02, 0x03, // Sub { dst: 5, src1: 2, src2: 3 } - R5 = R2 - R3 (5 - 3 = 2)
-        0x10, 0x06, 0x04, 0x05, // Add { dst: 6, src1: 4, src2: 5 } - R6 = R4 + R5 (30 + 2 = 32)
+        0x01, 0x00, 0x10, 0x10, // Add { dst: 4, src1: 0, src2: 1 } - R4 = R0 + R1 (10 + 20 = 30)
+        0x03, 0x08, 0x14, 0x14, // Sub { dst: 5, src1: 2, src2: 3 } - R5 = R2 - R3 (5 - 3 = 2)
+        0x05, 0x10, 0x18, 0x10, // Add { dst: 6, src1: 4, src2: 5 } - R6 = R4 + R5 (30 + 2 = 32)
 
         // Independent operations that can execute in parallel
-        0x20, 0x07, 0x00, 0x01, // And { dst: 7, src1: 0, src2: 1 } - R7 = R0 & R1
-        0x21, 0x08, 0x02, 0x03, // Or  { dst: 8, src1: 2, src2: 3 } - R8 = R2 | R3
+        0x01, 0x00, 0x1C, 0x20, // And { dst: 7, src1: 0, src2: 1 } - R7 = R0 & R1
+        0x03, 0x08, 0x20, 0x24, // Or  { dst: 8, src1: 2, src2: 3 } - R8 = R2 | R3
 
-        0xE0, 0x00, 0x00, 0x00, // Halt
+        0x00, 0x00, 0x00, 0xE0, // Halt
     ];
 
-    mem.load_program(&program, 0);
-
-    // Test 1: Run with in-order execution
-    println!("=== Testing In-Order Execution ===");
-    let mut cpu_in_order = cpu.clone();
-    let mut mem_in_order = mem.clone();
+    // Test 1: Run on the five-stage in-order pipeline
+    println!("=== Testing In-Order Execution (5-stage pipeline) ===");
+    let mut cpu_in_order = CpuState::new();
+    let mut mem_in_order = Memory::new();
+    mem_in_order.load_program(&program, 0);
 
-    control_unit::disable_out_of_order(&mut cpu_in_order);
-    let start_cycles = cpu_in_order.pipeline.cycles;
+    control_unit::enable_pipelined(&mut cpu_in_order);
+    let start_cycles = cpu_in_order.cycles();
     control_unit::run(&mut cpu_in_order, &mut mem_in_order);
-    let in_order_cycles = cpu_in_order.pipeline.cycles - start_cycles;
+    let in_order_cycles = cpu_in_order.cycles() - start_cycles;
 
     println!("In-order execution completed in {} cycles", in_order_cycles);
     control_unit::print_cpu_state(&cpu_in_order);
@@ -61,17 +60,46 @@ fn main() -> ! {
     mem_ooo.load_program(&program, 0);
 
     control_unit::enable_out_of_order(&mut cpu_ooo);
-    let start_cycles_ooo = cpu_ooo.pipeline.cycles;
+    let start_cycles_ooo = cpu_ooo.cycles();
     control_unit::run(&mut cpu_ooo, &mut mem_ooo);
-    let ooo_cycles = cpu_ooo.pipeline.cycles - start_cycles_ooo;
+    let ooo_cycles = cpu_ooo.cycles() - start_cycles_ooo;
 
     println!("Out-of-order execution completed in {} cycles", ooo_cycles);
     control_unit::print_cpu_state(&cpu_ooo);
 
+    // Test 3: Same engine, renaming onto a physical register file instead of ROB tags
+    println!("\n=== Testing Out-of-Order Execution (physical register file) ===");
+    let mut cpu_prf = CpuState::new();
+    let mut mem_prf = Memory::new();
+    mem_prf.load_program(&program, 0);
+
+    control_unit::enable_out_of_order(&mut cpu_prf);
+    control_unit::set_rename_scheme(&mut cpu_prf, RenameScheme::PhysicalRegisters);
+    control_unit::run(&mut cpu_prf, &mut mem_prf);
+    let prf_cycles = cpu_prf.cycles();
+
+    println!("Physical register file execution completed in {} cycles", prf_cycles);
+    control_unit::print_cpu_state(&cpu_prf);
+
+    // Test 4: Two hardware threads running their own copy of the program on one core
+    println!("\n=== Testing SMT (2 threads, ICOUNT fetch) ===");
+    let mut cpu_smt = CpuState::new();
+    let mut mem_smt = Memory::new();
+    mem_smt.load_program(&program, 0);
+
+    control_unit::enable_smt(&mut cpu_smt, &[0, 0], FetchPolicy::ICount);
+    control_unit::run(&mut cpu_smt, &mut mem_smt);
+    let smt_cycles = cpu_smt.cycles();
+
+    println!("SMT execution completed in {} cycles", smt_cycles);
+    control_unit::print_cpu_state(&cpu_smt);
+
     // Compare results
     println!("\n=== Performance Comparison ===");
     println!("In-order cycles: {}", in_order_cycles);
     println!("Out-of-order cycles: {}", ooo_cycles);
+    println!("Out-of-order cycles (physical register file): {}", prf_cycles);
+    println!("SMT cycles (program run twice): {}", smt_cycles);
 
     if ooo_cycles < in_order_cycles {
         let improvement = ((in_order_cycles - ooo_cycles) as f32 / in_order_cycles as f32) * 100.0;
@@ -87,13 +115,24 @@ fn main() -> ! {
     let mut registers_match = true;
     for i in 0..10 {
         let in_order_val = cpu_in_order.regs.read(i);
-        let ooo_val = cpu_ooo.regs.read(i);
-        if in_order_val != ooo_val {
-            println!("MISMATCH: R{} = {} (in-order) vs {} (out-of-order)", 
-                     i, in_order_val, ooo_val);
+        // Each engine is checked against the in-order reference on its own
+        let engines = [
+            ("out-of-order", cpu_ooo.regs.read(i)),
+            ("physical register file", cpu_prf.regs.read(i)),
+            ("SMT thread 0", cpu_smt.regs.read(i)),
+            ("SMT thread 1", cpu_smt.smt.contexts[0].regs.read(i)),
+        ];
+        let mut mismatched = false;
+        for (engine, val) in engines {
+            if val != in_order_val {
+                println!("MISMATCH: R{} = {} (in-order) vs {} ({})", i, in_order_val, val, engine);
+                mismatched = true;
+            }
+        }
+        if mismatched {
             registers_match = false;
         } else {
-            println!("R{} = {} (both modes)", i, in_order_val);
+            println!("R{} = {} (all modes)", i, in_order_val);
         }
     }
 
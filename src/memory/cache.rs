@@ -0,0 +1,277 @@
+// Set-associative cache timing model. Only tags are kept: data always lives in Memory,
+// so a cache changes how long an access takes, never what it returns. Addresses are
+// far addresses, so the same window address in two banks never aliases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Replacement {
+    Lru,
+    Fifo,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WritePolicy {
+    WriteBack,    // Stores dirty the line, memory is written on eviction
+    WriteThrough, // Every store is also written to memory
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    pub size: usize,      // Total capacity in bytes
+    pub line_size: usize, // Bytes per line, a power of two
+    pub associativity: usize,
+    pub replacement: Replacement,
+    pub write_policy: WritePolicy,
+    pub write_allocate: bool, // Whether a store miss fills the line
+    pub hit_latency: u32,
+    pub miss_latency: u32, // Total cycles of a miss when no lower level is modelled
+}
+
+impl CacheConfig {
+    // 1 KiB, 16-byte lines, 2-way LRU write-back write-allocate
+    pub fn new() -> Self {
+        Self {
+            size: 1024,
+            line_size: 16,
+            associativity: 2,
+            replacement: Replacement::Lru,
+            write_policy: WritePolicy::WriteBack,
+            write_allocate: true,
+            hit_latency: 1,
+            miss_latency: 10,
+        }
+    }
+
+    pub fn num_sets(&self) -> usize {
+        (self.size / (self.line_size * self.associativity)).max(1)
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheLine {
+    pub valid: bool,
+    pub dirty: bool,
+    pub tag: u32,
+    pub last_used: u64,
+    pub filled: u64,
+    pub prefetched: bool, // Brought in by a prefetch and not yet used by a demand access
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub reads: u64,
+    pub writes: u64,
+    pub read_misses: u64,
+    pub write_misses: u64,
+    pub writebacks: u64,    // Dirty lines written back on eviction
+    pub memory_writes: u64, // Stores sent to memory (write-through or not allocated)
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> u64 {
+        self.reads + self.writes
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.read_misses + self.write_misses
+    }
+
+    pub fn hit_rate(&self) -> f32 {
+        let accesses = self.accesses();
+        if accesses == 0 { 0.0 } else { (accesses - self.misses()) as f32 / accesses as f32 }
+    }
+}
+
+// What a lookup needs from the next level down
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheAccess {
+    pub hit: bool,
+    pub filled: bool,           // The line was read from the next level
+    pub writeback: Option<u32>, // Address of a dirty line evicted by the fill
+    pub write_through: bool,    // The store itself goes on to the next level
+    pub prefetch_hit: bool,     // First demand use of a prefetched line
+    pub evicted_prefetch: bool, // The fill evicted a prefetched line that was never used
+}
+
+#[derive(Debug)]
+pub struct Cache {
+    pub name: &'static str,
+    pub config: CacheConfig,
+    pub sets: Vec<Vec<CacheLine>>,
+    pub stats: CacheStats,
+    clock: u64,
+    seed: u32, // xorshift state for random replacement
+}
+
+impl Cache {
+    // Panics on a line size that is zero or not a power of two: lines are located by
+    // dividing the address by it
+    pub fn new(name: &'static str, config: CacheConfig) -> Self {
+        assert!(
+            config.line_size.is_power_of_two(),
+            "{}: line size {} is not a power of two",
+            name,
+            config.line_size
+        );
+        let ways = config.associativity.max(1);
+        Self {
+            name,
+            config,
+            sets: vec![vec![CacheLine::default(); ways]; config.num_sets()],
+            stats: CacheStats::default(),
+            clock: 0,
+            seed: 0x2545_F491,
+        }
+    }
+
+    pub fn line_address(&self, addr: u32) -> u32 {
+        addr / self.config.line_size as u32
+    }
+
+    // Drop every line without writing anything back
+    pub fn invalidate(&mut self) {
+        self.sets.iter_mut().flatten().for_each(|line| *line = CacheLine::default());
+    }
+
+    pub fn contains(&self, addr: u32) -> bool {
+        let (set, tag) = self.locate(addr);
+        self.sets[set].iter().any(|line| line.valid && line.tag == tag)
+    }
+
+    // Access as a standalone cache: a hit takes `hit_latency`, anything else `miss_latency`.
+    // Returns the latency of the access in cycles.
+    pub fn access(&mut self, addr: u32, write: bool) -> u32 {
+        if self.lookup(addr, write).hit {
+            self.config.hit_latency
+        } else {
+            self.config.miss_latency
+        }
+    }
+
+    // Look up the line holding `addr`, filling it on a miss as the policies dictate, and
+    // report what the next level has to do
+    pub fn lookup(&mut self, addr: u32, write: bool) -> CacheAccess {
+        self.clock += 1;
+        let (set, tag) = self.locate(addr);
+        let clock = self.clock;
+
+        if write {
+            self.stats.writes += 1;
+        } else {
+            self.stats.reads += 1;
+        }
+        let write_through = write && self.config.write_policy == WritePolicy::WriteThrough;
+        let mut result = CacheAccess { write_through, ..CacheAccess::default() };
+
+        if let Some(line) = self.sets[set].iter_mut().find(|line| line.valid && line.tag == tag) {
+            line.last_used = clock;
+            result.prefetch_hit = line.prefetched;
+            line.prefetched = false;
+            if write_through {
+                self.stats.memory_writes += 1;
+            } else if write {
+                line.dirty = true;
+            }
+            result.hit = true;
+            return result;
+        }
+
+        if write {
+            self.stats.write_misses += 1;
+            if !self.config.write_allocate {
+                self.stats.memory_writes += 1;
+                result.write_through = true;
+                return result;
+            }
+        } else {
+            self.stats.read_misses += 1;
+        }
+
+        if write_through {
+            self.stats.memory_writes += 1;
+        }
+        self.fill(set, tag, write && !write_through, false, &mut result);
+        result
+    }
+
+    // Record a lookup that found the tag of a line still being filled as the miss it is:
+    // it waited for the same fill as the access that missed
+    pub fn count_as_miss(&mut self, write: bool) {
+        if write {
+            self.stats.write_misses += 1;
+        } else {
+            self.stats.read_misses += 1;
+        }
+    }
+
+    // Bring in the line holding `addr` ahead of demand. None when it is already present.
+    pub fn prefetch(&mut self, addr: u32) -> Option<CacheAccess> {
+        if self.contains(addr) {
+            return None;
+        }
+        self.clock += 1;
+        let (set, tag) = self.locate(addr);
+        let mut result = CacheAccess::default();
+        self.fill(set, tag, false, true, &mut result);
+        Some(result)
+    }
+
+    fn fill(&mut self, set: usize, tag: u32, dirty: bool, prefetched: bool, result: &mut CacheAccess) {
+        let way = self.victim(set);
+        let victim = self.sets[set][way];
+        if victim.valid && victim.dirty {
+            self.stats.writebacks += 1;
+            result.writeback = Some(self.address_of(set, victim.tag));
+        }
+        result.evicted_prefetch = victim.valid && victim.prefetched;
+        result.filled = true;
+        self.sets[set][way] = CacheLine {
+            valid: true,
+            dirty,
+            tag,
+            last_used: self.clock,
+            filled: self.clock,
+            prefetched,
+        };
+    }
+
+    fn locate(&self, addr: u32) -> (usize, u32) {
+        let line = self.line_address(addr);
+        let sets = self.sets.len() as u32;
+        ((line % sets) as usize, line / sets)
+    }
+
+    fn address_of(&self, set: usize, tag: u32) -> u32 {
+        (tag * self.sets.len() as u32 + set as u32) * self.config.line_size as u32
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        if let Some(way) = self.sets[set].iter().position(|line| !line.valid) {
+            return way;
+        }
+        match self.config.replacement {
+            Replacement::Lru => oldest(&self.sets[set], |line| line.last_used),
+            Replacement::Fifo => oldest(&self.sets[set], |line| line.filled),
+            Replacement::Random => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 17;
+                self.seed ^= self.seed << 5;
+                self.seed as usize % self.sets[set].len()
+            }
+        }
+    }
+}
+
+fn oldest(lines: &[CacheLine], age: impl Fn(&CacheLine) -> u64) -> usize {
+    lines
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, line)| age(line))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
@@ -0,0 +1,216 @@
+use crate::memory::cache::Cache;
+
+// What sits below the first-level caches: an optional unified L2 in front of DRAM, and
+// the miss status holding registers (MSHRs) that bound how many data cache misses can
+// be outstanding at once. Latencies are in core cycles; `clock` advances once per
+// pipeline cycle so outstanding misses retire on time.
+#[derive(Debug, Clone, Copy)]
+pub struct DramConfig {
+    pub banks: usize,
+    pub row_size: u32,          // Bytes per row, a power of two
+    pub cas_latency: u32,       // Column access on the open row
+    pub activate_latency: u32,  // Opening a row in an idle bank
+    pub precharge_latency: u32, // Closing the row that is open
+}
+
+impl DramConfig {
+    pub fn new() -> Self {
+        Self {
+            banks: 4,
+            row_size: 1024,
+            cas_latency: 10,
+            activate_latency: 10,
+            precharge_latency: 10,
+        }
+    }
+}
+
+impl Default for DramConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DramStats {
+    pub reads: u64,
+    pub writes: u64,
+    pub row_hits: u64,
+    pub row_empty: u64,     // Bank had no open row
+    pub row_conflicts: u64, // Another row had to be closed first
+}
+
+impl DramStats {
+    pub fn row_hit_rate(&self) -> f32 {
+        let accesses = self.reads + self.writes;
+        if accesses == 0 { 0.0 } else { self.row_hits as f32 / accesses as f32 }
+    }
+}
+
+// Open-page DRAM: each bank keeps its last row open in the row buffer. Consecutive rows
+// are interleaved across the banks.
+#[derive(Debug)]
+pub struct Dram {
+    pub config: DramConfig,
+    pub open_rows: Vec<Option<u32>>,
+    pub stats: DramStats,
+}
+
+impl Dram {
+    pub fn new(config: DramConfig) -> Self {
+        Self {
+            config,
+            open_rows: vec![None; config.banks.max(1)],
+            stats: DramStats::default(),
+        }
+    }
+
+    pub fn access(&mut self, addr: u32, write: bool) -> u32 {
+        if write {
+            self.stats.writes += 1;
+        } else {
+            self.stats.reads += 1;
+        }
+        let row = addr / self.config.row_size;
+        let bank = row as usize % self.open_rows.len();
+        let latency = match self.open_rows[bank] {
+            Some(open) if open == row => {
+                self.stats.row_hits += 1;
+                self.config.cas_latency
+            }
+            Some(_) => {
+                self.stats.row_conflicts += 1;
+                self.config.precharge_latency + self.config.activate_latency + self.config.cas_latency
+            }
+            None => {
+                self.stats.row_empty += 1;
+                self.config.activate_latency + self.config.cas_latency
+            }
+        };
+        self.open_rows[bank] = Some(row);
+        latency
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Mshr {
+    pub line: u32, // Line address being filled, in lines of the cache that missed
+    pub ready_at: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HierarchyStats {
+    pub mshr_merges: u64,        // Misses to a line that was already being filled
+    pub mshr_full_stalls: u64,   // Load attempts turned away because every MSHR was busy
+    pub busy_cycles: u64,        // Cycles with at least one miss outstanding
+    pub outstanding_cycles: u64, // Sum over cycles of the misses outstanding
+    pub peak_outstanding: usize,
+}
+
+impl HierarchyStats {
+    // Average number of misses in flight while memory is busy
+    pub fn memory_level_parallelism(&self) -> f32 {
+        if self.busy_cycles == 0 { 0.0 } else { self.outstanding_cycles as f32 / self.busy_cycles as f32 }
+    }
+}
+
+#[derive(Debug)]
+pub struct MemoryHierarchy {
+    pub l2: Option<Cache>,
+    pub dram: Dram,
+    pub max_mshrs: usize,
+    pub mshrs: Vec<Mshr>,
+    // Instruction cache fills. Fetch waits out its own miss, so they need no MSHR limit;
+    // they are kept so another thread's fetch of the line waits for the same fill.
+    pub fetch_fills: Vec<Mshr>,
+    pub stats: HierarchyStats,
+    pub clock: u64,
+}
+
+impl MemoryHierarchy {
+    pub fn new(l2: Option<Cache>, dram: DramConfig, max_mshrs: usize) -> Self {
+        Self {
+            l2,
+            dram: Dram::new(dram),
+            max_mshrs: max_mshrs.max(1),
+            mshrs: Vec::new(),
+            fetch_fills: Vec::new(),
+            stats: HierarchyStats::default(),
+            clock: 0,
+        }
+    }
+
+    // Advance one cycle, retiring the misses that have completed
+    pub fn tick(&mut self) {
+        self.clock += 1;
+        let now = self.clock;
+        self.mshrs.retain(|mshr| mshr.ready_at > now);
+        self.fetch_fills.retain(|fill| fill.ready_at > now);
+        if !self.mshrs.is_empty() {
+            self.stats.busy_cycles += 1;
+            self.stats.outstanding_cycles += self.mshrs.len() as u64;
+        }
+    }
+
+    // Cycles a first-level cache waits for the line at `addr`
+    pub fn read(&mut self, addr: u32) -> u32 {
+        match self.l2.as_mut() {
+            Some(l2) => {
+                let access = l2.lookup(addr, false);
+                if let Some(victim) = access.writeback {
+                    self.dram.access(victim, true);
+                }
+                if access.hit {
+                    l2.config.hit_latency
+                } else {
+                    l2.config.hit_latency + self.dram.access(addr, false)
+                }
+            }
+            None => self.dram.access(addr, false),
+        }
+    }
+
+    // Writes from a first-level cache drain through a write buffer and are not charged
+    // to the access that caused them
+    pub fn write(&mut self, addr: u32) {
+        match self.l2.as_mut() {
+            Some(l2) => {
+                let access = l2.lookup(addr, true);
+                if let Some(victim) = access.writeback {
+                    self.dram.access(victim, true);
+                }
+                if access.write_through {
+                    self.dram.access(addr, true);
+                } else if access.filled {
+                    self.dram.access(addr, false);
+                }
+            }
+            None => {
+                self.dram.access(addr, true);
+            }
+        }
+    }
+
+    // Completion cycle of an outstanding miss to `line`
+    pub fn pending(&self, line: u32) -> Option<u64> {
+        self.mshrs.iter().find(|mshr| mshr.line == line).map(|mshr| mshr.ready_at)
+    }
+
+    // Completion cycle of an outstanding instruction cache fill of `line`
+    pub fn pending_fetch(&self, line: u32) -> Option<u64> {
+        self.fetch_fills.iter().find(|fill| fill.line == line).map(|fill| fill.ready_at)
+    }
+
+    pub fn fill_fetch(&mut self, line: u32, latency: u32) {
+        self.fetch_fills.push(Mshr { line, ready_at: self.clock + latency as u64 });
+    }
+
+    pub fn free_mshrs(&self) -> usize {
+        self.max_mshrs.saturating_sub(self.mshrs.len())
+    }
+
+    pub fn allocate_mshr(&mut self, line: u32, latency: u32) {
+        self.mshrs.push(Mshr { line, ready_at: self.clock + latency as u64 });
+        self.stats.peak_outstanding = self.stats.peak_outstanding.max(self.mshrs.len());
+    }
+}
@@ -1,26 +1,116 @@
+pub mod cache;
+pub mod hierarchy;
+pub mod prefetch;
+
+// Bank switching: the code window and the data window each show one 16 KiB bank,
+// selected by `code_bank` / `data_bank`. Bank 0 is the window's own range of `data`,
+// banks 1.. live in `banks`, so programs that never switch see plain 64 KiB memory.
+// Both windows draw from the same pool of banks.
+//
+// A far address names a byte in any bank: bits [23:16] bank, bits [15:0] the address
+// it has while that bank is selected. Bank 0 covers the whole 64 KiB space, other
+// banks only the window ranges.
+pub const BANK_SIZE: usize = 0x4000;
+pub const NUM_MEMORY_BANKS: usize = 16;
+pub const CODE_WINDOW: u16 = 0x4000; // 0x4000-0x7FFF
+pub const DATA_WINDOW: u16 = 0x8000; // 0x8000-0xBFFF
+
 pub struct Memory {
     pub data: [u8; 65536], // 64KB of emulated memory size, 
                            // can shrink to 32KB (32768) if desired
+    pub banks: Vec<u8>,    // Banks 1.., a code and a data window of BANK_SIZE bytes each
+    pub code_bank: u8,
+    pub data_bank: u8,
 }
 
 impl Memory {
     pub fn new() -> Self {
-        Self { data: [0; 65536] }
+        Self {
+            data: [0; 65536],
+            banks: vec![0; (NUM_MEMORY_BANKS - 1) * 2 * BANK_SIZE],
+            code_bank: 0,
+            data_bank: 0,
+        }
     }
 
     pub fn read(&self, addr: u16) -> u8 {
-        if (addr as usize) < self.data.len() {
-            self.data[addr as usize]
-        } else {
-            0 // Return 0 for out-of-bounds reads
-        }
+        self.read_far(self.far_address(addr)).unwrap_or(0)
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
-        if (addr as usize) < self.data.len() {
-            self.data[addr as usize] = value;
+        let far = self.far_address(addr);
+        self.write_far(far, value);
+    }
+
+    // Far address of a CPU address under the current bank selection
+    pub fn far_address(&self, addr: u16) -> u32 {
+        let bank = match window_of(addr) {
+            Some(CODE_WINDOW) => self.code_bank,
+            Some(_) => self.data_bank,
+            None => 0,
+        };
+        (bank as u32) << 16 | addr as u32
+    }
+
+    // None for a bank that does not exist or an address outside the windows of a bank > 0
+    pub fn read_far(&self, far: u32) -> Option<u8> {
+        self.far_index(far).map(|index| match index {
+            Ok(i) => self.data[i],
+            Err(i) => self.banks[i],
+        })
+    }
+
+    pub fn write_far(&mut self, far: u32, value: u8) -> bool {
+        match self.far_index(far) {
+            Some(Ok(i)) => self.data[i] = value,
+            Some(Err(i)) => self.banks[i] = value,
+            None => return false,
         }
-        // Silently ignore out-of-bounds writes
+        true
+    }
+
+    // Index of the byte of storage behind a far address: the banks follow the 64 KiB of
+    // bank 0, so two far addresses name the same byte exactly when their indices match
+    pub fn storage_index(&self, far: u32) -> Option<usize> {
+        self.far_index(far).map(|index| match index {
+            Ok(i) => i,
+            Err(i) => self.data.len() + i,
+        })
+    }
+
+    pub fn is_valid_far(&self, far: u32) -> bool {
+        self.far_index(far).is_some()
+    }
+
+    pub fn load_far(&mut self, far: u32, data: &[u8]) {
+        for (i, byte) in data.iter().enumerate() {
+            self.write_far(far + i as u32, *byte);
+        }
+    }
+
+    pub fn select_code_bank(&mut self, bank: u8) {
+        self.code_bank = bank % NUM_MEMORY_BANKS as u8;
+    }
+
+    pub fn select_data_bank(&mut self, bank: u8) {
+        self.data_bank = bank % NUM_MEMORY_BANKS as u8;
+    }
+
+    // Ok(index into data) or Err(index into banks)
+    fn far_index(&self, far: u32) -> Option<Result<usize, usize>> {
+        let bank = (far >> 16) as usize;
+        let addr = far as u16;
+        if bank == 0 {
+            return Some(Ok(addr as usize));
+        }
+        let window = window_of(addr)?;
+        if bank >= NUM_MEMORY_BANKS {
+            return None;
+        }
+        // The code and data windows of a bank are separate storage, so every far address
+        // names a different byte
+        let half = if window == CODE_WINDOW { 0 } else { 1 };
+        Some(Err(((bank - 1) * 2 + half) * BANK_SIZE + (addr - window) as usize))
     }
 
     // NEW: Missing functions that execute.rs needs
@@ -39,10 +129,10 @@ impl Memory {
         // Fetch 4 bytes to form a u32 instruction (little endian)
         let idx = addr as usize;
         if idx + 3 < self.data.len() {
-            ((self.data[idx] as u32) << 0) |
-            ((self.data[idx + 1] as u32) << 8) |
-            ((self.data[idx + 2] as u32) << 16) |
-            ((self.data[idx + 3] as u32) << 24)
+            ((self.read(addr) as u32) << 0) |
+            ((self.read(addr + 1) as u32) << 8) |
+            ((self.read(addr + 2) as u32) << 16) |
+            ((self.read(addr + 3) as u32) << 24)
         } else {
             0 // Return NOP for out-of-bounds
         }
@@ -60,3 +150,12 @@ impl Memory {
         self.data.len()
     }
 }
+
+// Base of the bank window containing `addr`, if any
+pub fn window_of(addr: u16) -> Option<u16> {
+    match addr {
+        0x4000..=0x7FFF => Some(CODE_WINDOW),
+        0x8000..=0xBFFF => Some(DATA_WINDOW),
+        _ => None,
+    }
+}
@@ -0,0 +1,202 @@
+// Hardware prefetchers for the data cache. The prefetcher watches demand accesses (far
+// byte address, line size and the pc of the instruction) and proposes addresses to
+// bring in; the caller drops the ones already cached. A prefetched line counts as useful
+// when a demand access touches it before eviction, and as late when that access arrives
+// while its fill is still in flight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefetchPolicy {
+    NextLine,                     // The lines following a miss
+    Stride { table_size: usize }, // Per load pc, once the same stride repeats
+    Stream { streams: usize },    // Runs of misses in ascending or descending lines
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StrideEntry {
+    pub valid: bool,
+    pub pc: u16,
+    pub last_addr: u32,
+    pub stride: i32,
+    pub confidence: u8, // Saturates at 3, prefetching from 2
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stream {
+    pub valid: bool,
+    pub last_line: u32,
+    pub direction: i32, // 0 until a second miss trains it
+    pub last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrefetchStats {
+    pub issued: u64,
+    pub redundant: u64,      // Proposed lines already cached or being filled
+    pub dropped: u64,        // Proposed lines turned away for lack of an MSHR
+    pub useful: u64,         // Prefetched lines later used by a demand access
+    pub late: u64,           // ... whose fill had not finished by then
+    pub evicted_unused: u64,
+    pub demand_misses: u64,  // Data cache misses left with the prefetcher running
+}
+
+impl PrefetchStats {
+    // Fraction of prefetches that were used
+    pub fn accuracy(&self) -> f32 {
+        if self.issued == 0 { 0.0 } else { self.useful as f32 / self.issued as f32 }
+    }
+
+    // Fraction of the misses there would have been that prefetching removed
+    pub fn coverage(&self) -> f32 {
+        let misses = self.useful + self.demand_misses;
+        if misses == 0 { 0.0 } else { self.useful as f32 / misses as f32 }
+    }
+
+    // Fraction of useful prefetches that arrived before they were needed
+    pub fn timeliness(&self) -> f32 {
+        if self.useful == 0 { 0.0 } else { (self.useful - self.late) as f32 / self.useful as f32 }
+    }
+}
+
+#[derive(Debug)]
+pub struct Prefetcher {
+    pub policy: PrefetchPolicy,
+    pub degree: u32, // Lines proposed per trigger
+    pub stride_table: Vec<StrideEntry>,
+    pub streams: Vec<Stream>,
+    pub in_flight: Vec<(u32, u64)>, // (line address, cycle its fill completes)
+    pub stats: PrefetchStats,
+    pub clock: u64,
+}
+
+impl Prefetcher {
+    pub fn new(policy: PrefetchPolicy, degree: u32) -> Self {
+        let (table_size, streams) = match policy {
+            PrefetchPolicy::NextLine => (0, 0),
+            PrefetchPolicy::Stride { table_size } => (table_size.max(1), 0),
+            PrefetchPolicy::Stream { streams } => (0, streams.max(1)),
+        };
+        Self {
+            policy,
+            degree: degree.max(1),
+            stride_table: vec![StrideEntry::default(); table_size],
+            streams: vec![Stream::default(); streams],
+            in_flight: Vec::new(),
+            stats: PrefetchStats::default(),
+            clock: 0,
+        }
+    }
+
+    // Advance one cycle, forgetting prefetches whose fill has completed
+    pub fn tick(&mut self) {
+        self.clock += 1;
+        let now = self.clock;
+        self.in_flight.retain(|&(_, ready_at)| ready_at > now);
+    }
+
+    // Addresses to prefetch after a demand access. `trigger` is set for a miss or the
+    // first use of a prefetched line, which is what moves next-line and stream prefetching
+    // along; the stride table trains on every access.
+    pub fn observe(&mut self, addr: u32, line_size: u32, pc: u16, trigger: bool) -> Vec<u32> {
+        let line = addr / line_size;
+        let lines: Vec<i64> = match self.policy {
+            PrefetchPolicy::NextLine if trigger => (1..=self.degree as i64).map(|i| line as i64 + i).collect(),
+            PrefetchPolicy::NextLine => Vec::new(),
+            PrefetchPolicy::Stride { .. } => {
+                return self
+                    .train_stride(addr, pc)
+                    .map(|stride| {
+                        (1..=self.degree as i64)
+                            .map(|i| addr as i64 + stride as i64 * i)
+                            .filter(|&target| target >= 0)
+                            .map(|target| target as u32)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+            }
+            PrefetchPolicy::Stream { .. } if trigger => match self.train_stream(line) {
+                0 => Vec::new(),
+                direction => (1..=self.degree as i64).map(|i| line as i64 + direction as i64 * i).collect(),
+            },
+            PrefetchPolicy::Stream { .. } => Vec::new(),
+        };
+        lines
+            .into_iter()
+            .filter(|&target| target >= 0)
+            .map(|target| target as u32 * line_size)
+            .collect()
+    }
+
+    // Record an issued prefetch of `line` that completes after `latency` cycles
+    pub fn issued(&mut self, line: u32, latency: u32) {
+        self.stats.issued += 1;
+        self.in_flight.push((line, self.clock + latency as u64));
+    }
+
+    // Count the first demand use of a prefetched line; returns the cycles still to wait
+    // for its fill
+    pub fn used(&mut self, line: u32) -> u32 {
+        self.stats.useful += 1;
+        match self.in_flight.iter().find(|&&(pending, _)| pending == line) {
+            Some(&(_, ready_at)) => {
+                self.stats.late += 1;
+                ready_at.saturating_sub(self.clock) as u32
+            }
+            None => 0,
+        }
+    }
+
+    // Stride once the same non-zero stride has repeated three times in a row from this pc
+    fn train_stride(&mut self, addr: u32, pc: u16) -> Option<i32> {
+        let index = (pc as usize >> 2) % self.stride_table.len();
+        let entry = &mut self.stride_table[index];
+        if !entry.valid || entry.pc != pc {
+            *entry = StrideEntry { valid: true, pc, last_addr: addr, stride: 0, confidence: 0 };
+            return None;
+        }
+
+        let stride = addr.wrapping_sub(entry.last_addr) as i32;
+        entry.last_addr = addr;
+        if stride != 0 && stride == entry.stride {
+            entry.confidence = (entry.confidence + 1).min(3);
+        } else if entry.confidence > 0 {
+            entry.confidence -= 1;
+        } else {
+            entry.stride = stride;
+        }
+        (entry.confidence >= 2).then_some(entry.stride)
+    }
+
+    // Direction of the stream `line` continues (0 when it only starts or trains one).
+    // A miss within two lines of a stream's last line continues it; anything else
+    // replaces the least recently used stream.
+    fn train_stream(&mut self, line: u32) -> i32 {
+        let clock = self.clock;
+        let near = self.streams.iter_mut().find(|stream| {
+            stream.valid && (line as i64 - stream.last_line as i64).abs() <= 2
+        });
+        match near {
+            Some(stream) if stream.last_line == line => {
+                stream.last_used = clock;
+                0
+            }
+            Some(stream) => {
+                let direction = if line > stream.last_line { 1 } else { -1 };
+                let confirmed = stream.direction == direction;
+                stream.direction = direction;
+                stream.last_line = line;
+                stream.last_used = clock;
+                if confirmed { direction } else { 0 }
+            }
+            None => {
+                let victim = self
+                    .streams
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, stream)| if stream.valid { stream.last_used + 1 } else { 0 })
+                    .map(|(i, _)| i)
+                    .unwrap_or(0);
+                self.streams[victim] = Stream { valid: true, last_line: line, direction: 0, last_used: clock };
+                0
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+// The instruction encoder and the assembler built on it
+use crate::asm::{self, AsmError};
+use crate::core::{control_unit, CpuState};
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+#[test]
+fn encode_inverts_decode() {
+    let mut state: u32 = 0x1234_5678;
+    let mut decoded = 0;
+    for _ in 0..200_000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        // Decoding may ignore unused fields, so compare instructions rather than words
+        if let Some(instruction) = isa::decode(state) {
+            assert_eq!(isa::decode(isa::encode(&instruction)), Some(instruction), "{:08x}", state);
+            decoded += 1;
+        }
+    }
+    assert!(decoded > 100_000);
+    assert_eq!(isa::encode(&Instruction::Halt), 0xE000_0000);
+}
+
+#[test]
+fn assembled_program_runs() {
+    let source = "
+        .equ count, 10
+        .org 0x100
+_start: ldi r1, 0           ; sum
+        ldi r2, 1
+        li r3, count
+loop:   add r1, r1, r2
+        add r2, r2, 1
+        bgt r2, r3, done
+        jmp loop
+done:   la r4, table
+        ld r5, [r4+2]
+        st r1, [r4]+
+        call double
+        halt
+double: add r1, r1, r1
+        ret
+table:  .word 0x1111, -2
+";
+    let image = asm::assemble(source).unwrap();
+    assert_eq!(image.entry, Some(0x100));
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    cpu.regs.write(15, 0xF000);
+    for _ in 0..1000 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted);
+    let table = image.symbol("table").unwrap();
+    assert_eq!(cpu.regs.read(1), 110);
+    assert_eq!(cpu.regs.read(4), table + 2);
+    assert_eq!(cpu.regs.read(5), 0xFFFE);
+    assert_eq!(mem.load_u16(table), 55);
+    assert_eq!(cpu.regs.read(15), 0xF000);
+}
+
+// Decode the instruction at `addr` of an assembled image
+fn instruction_at(image: &crate::loader::Image, addr: u16) -> Option<Instruction> {
+    let segment = image.segments.iter().find(|segment| segment.addr <= addr as u32 && addr as u32 - segment.addr < segment.data.len() as u32)?;
+    let at = (addr as u32 - segment.addr) as usize;
+    isa::decode(u32::from_le_bytes(segment.data[at..at + 4].try_into().unwrap()))
+}
+
+#[test]
+fn relative_branches_reach_their_full_displacement() {
+    // 8-bit word offsets for the branches, 10-bit for jmp, both ways
+    let image = asm::assemble("
+back:   nop
+        .org 0x1FC
+        beq r1, r2, back    ; -127 words
+        bne r1, r2, back    ; -128 words
+        blt32 r1, r2, ahead ; +127 words
+        .org 0x400
+ahead:  nop
+        .org 0x800
+        jmp back            ; -512 words
+        jmp high            ; +511 words
+        .org 0x1000
+high:   nop
+").unwrap();
+    assert_eq!(instruction_at(&image, 0x1FC), Some(Instruction::BranchEqualRel { src1: 1, src2: 2, offset: -127 }));
+    assert_eq!(instruction_at(&image, 0x200), Some(Instruction::BranchNotEqualRel { src1: 1, src2: 2, offset: -128 }));
+    assert_eq!(instruction_at(&image, 0x204), Some(Instruction::BranchLessThan32 { src1: 1, src2: 2, offset: 127 }));
+    assert_eq!(instruction_at(&image, 0x800), Some(Instruction::JumpRel { offset: -512 }));
+    assert_eq!(instruction_at(&image, 0x804), Some(Instruction::JumpRel { offset: 511 }));
+
+    // One word further is out of range
+    assert_eq!(
+        asm::assemble(".org 0x204
+beq r1, r2, 0").unwrap_err(),
+        AsmError::OutOfRange { line: 2, value: -0x204 }
+    );
+    assert_eq!(
+        asm::assemble("bne r1, r2, 0x200").unwrap_err(),
+        AsmError::OutOfRange { line: 1, value: 0x200 }
+    );
+    assert_eq!(
+        asm::assemble(".org 0x804
+jmp 0").unwrap_err(),
+        AsmError::OutOfRange { line: 2, value: -0x804 }
+    );
+    assert_eq!(asm::assemble("jmp 0x800").unwrap_err(), AsmError::OutOfRange { line: 1, value: 0x800 });
+}
+
+#[test]
+fn relative_branches_run_backwards_and_forwards() {
+    // Count r1 down from 200 with a backward branch 126 words long, then jump forward
+    let mut source = String::from("_start: ldi r1, 200\nloop:   sub r1, r1, 1\n");
+    for _ in 0..125 {
+        source.push_str("        nop\n");
+    }
+    source.push_str("        bne r1, r0, loop\n        jmp out\n        .org 0x900\nout:    ldi r2, 7\n        halt\n");
+    let image = asm::assemble(&source).unwrap();
+    assert_eq!(instruction_at(&image, 4 * 127), Some(Instruction::BranchNotEqualRel { src1: 1, src2: 0, offset: -126 }));
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    for _ in 0..100_000 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted);
+    assert_eq!((cpu.regs.read(1), cpu.regs.read(2)), (0, 7));
+}
+
+#[test]
+fn load_pseudos_pick_their_sequences() {
+    // li takes the shortest sequence for a constant; la and forward references keep the
+    // two-instruction pair so the label can be anywhere
+    let image = asm::assemble("
+        li r1, 5
+        li r2, 0x1234
+        li r3, -1
+        li r4, later
+        la r5, 5
+        li r6, 0x1200
+later:  halt
+").unwrap();
+    assert_eq!(image.symbol("later"), Some(4 + 8 + 4 + 8 + 8 + 4));
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    for _ in 0..100 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted);
+    let values: Vec<u16> = (1..=6).map(|reg| cpu.regs.read(reg)).collect();
+    assert_eq!(values, [5, 0x1234, 0xFFFF, 36, 5, 0x1200]);
+}
+
+#[test]
+fn bit_manipulation_at_the_edges() {
+    let image = asm::assemble("
+        li r1, 0x8001
+        ldi r4, 0
+        li r8, 0xFFFF
+        li r10, 0x12F0
+        clz r2, r1
+        ctz r3, r1
+        clz r5, r4          ; 16 for zero
+        ctz r6, r4
+        popc r7, r1
+        popc r9, r8
+        bswap r11, r10
+        rol r12, r1, 1      ; carry = the bit that wrapped, 1
+        rcl r16, r4, 1      ; carry comes in at the bottom and goes out
+        rcr r17, r1, 1      ; bit 0 goes out to carry
+        adc r29, r4, r4
+        ror r13, r1, 15
+        ror r14, r1, 1
+        adc r30, r4, r4
+        bset r18, r4, 15
+        bclr r19, r8, 0
+        btst r20, r1, 15
+        btst r21, r1, 14
+        bfx r22, r10, 0, 16 ; whole register
+        bfx r23, r10, 12, 4 ; top nibble
+        bfx r24, r10, 12, 8 ; clipped at bit 15
+        bfx r25, r10, 4, 1
+        mov r26, r8
+        bfi r26, r4, 15, 1
+        bfi r27, r10, 8, 16 ; clipped at bit 15
+        li r28, 0x1234
+        bfi r28, r8, 0, 16
+        halt
+").unwrap();
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    for _ in 0..100 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted);
+    let expected: [(u8, u16); 27] = [
+        (2, 0), (3, 0), (5, 16), (6, 16), (7, 2), (9, 16), (11, 0xF012),
+        (12, 0x0003), (16, 0x0001), (17, 0x4000), (29, 1), (13, 0x0003), (14, 0xC000), (30, 1),
+        (18, 0x8000), (19, 0xFFFE), (20, 1), (21, 0),
+        (22, 0x12F0), (23, 0x1), (24, 0x1), (25, 1), (26, 0x7FFF), (27, 0xF000), (28, 0xFFFF),
+        (1, 0x8001), (10, 0x12F0),
+    ];
+    for (reg, value) in expected {
+        assert_eq!(cpu.regs.read(reg), value, "r{}", reg);
+    }
+
+    // Field positions and widths outside the encodable range are rejected
+    assert_eq!(asm::assemble("bfx r1, r2, 16, 1").unwrap_err(), AsmError::OutOfRange { line: 1, value: 16 });
+    assert_eq!(asm::assemble("bfi r1, r2, 0, 0").unwrap_err(), AsmError::OutOfRange { line: 1, value: 0 });
+    assert_eq!(asm::assemble("bset r1, r2, 16").unwrap_err(), AsmError::OutOfRange { line: 1, value: 16 });
+}
+
+#[test]
+fn call_from_banked_code_stays_in_its_bank() {
+    let source = "
+        .org 0x100
+_start: la r2, banked
+        fjmp 2, r2
+done:   halt
+        .org 0x24000
+banked: ldi r1, 5
+        call double
+        call double
+        la r2, done
+        fjmp 0, r2
+double: add r1, r1, r1
+        ret
+";
+    let image = asm::assemble(source).unwrap();
+    let segment = image.segments.iter().find(|segment| segment.addr == 0x24000).unwrap();
+    let call = u32::from_le_bytes(segment.data[12..16].try_into().unwrap());
+    assert_eq!(isa::decode(call), Some(Instruction::FarCall { bank: 2, target: asm::CALL_REGISTER }));
+
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    cpu.regs.write(15, 0xF000);
+    for _ in 0..1000 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted);
+    assert_eq!(cpu.pc, image.symbol("done").unwrap());
+    assert_eq!((cpu.regs.read(1), mem.code_bank, cpu.regs.read(15)), (20, 0, 0xF000));
+}
+
+#[test]
+fn assembler_rejects_overlapping_output() {
+    assert_eq!(asm::assemble("nop\nnop\n.org 4\nnop").unwrap_err(), AsmError::Overlap { line: 4, addr: 4 });
+    assert_eq!(
+        asm::assemble(".org 0x10\nnop\n.org 0\n.word 1, 2, 3, 4, 5, 6, 7, 8, 9").unwrap_err(),
+        AsmError::Overlap { line: 4, addr: 0x10 }
+    );
+    // The same address in different banks is different memory
+    assert!(asm::assemble(".org 0x4000\nnop\n.org 0x14000\nnop\n.org 0x24000\nnop").is_ok());
+    assert_eq!(asm::assemble(".org 0x12000\nnop").unwrap_err(), AsmError::OutOfRange { line: 1, value: 0x12000 });
+    assert_eq!(asm::assemble(".org 0x17FFC\nnop\nnop").unwrap_err(), AsmError::OutOfMemory);
+}
+
+#[test]
+fn assembler_picks_the_instruction_form_from_the_operands() {
+    let image = asm::assemble("add r1, r2, r3\nadd r1, r2, -3\nshl r1, r2, r4\nldbs r1, [r2-1]\nldbs r1, [r2]+\n").unwrap();
+    let words: Vec<Option<Instruction>> = image.segments[0]
+        .data
+        .chunks(4)
+        .map(|word| isa::decode(u32::from_le_bytes([word[0], word[1], word[2], word[3]])))
+        .collect();
+    assert_eq!(
+        words,
+        [
+            Some(Instruction::Add { dst: 1, src1: 2, src2: 3 }),
+            Some(Instruction::AddImm { dst: 1, src: 2, imm: -3 }),
+            Some(Instruction::ShiftLeftReg { dst: 1, src: 2, amount: 4 }),
+            Some(Instruction::LoadByteSigned { dst: 1, base: 2, offset: -1 }),
+            Some(Instruction::LoadByteSignedPostInc { dst: 1, base: 2 }),
+        ]
+    );
+}
+
+#[test]
+fn assembler_reports_errors_with_their_line() {
+    assert_eq!(
+        asm::assemble("nop\nfrob r1").unwrap_err(),
+        AsmError::UnknownMnemonic { line: 2, mnemonic: "frob".to_string() }
+    );
+    assert_eq!(
+        asm::assemble("jmp nowhere").unwrap_err(),
+        AsmError::UndefinedSymbol { line: 1, name: "nowhere".to_string() }
+    );
+    assert_eq!(
+        asm::assemble("a: nop\na: nop").unwrap_err(),
+        AsmError::DuplicateSymbol { line: 2, name: "a".to_string() }
+    );
+    assert_eq!(
+        asm::assemble("beq r1, r2, far\n.org 0x400\nfar: halt").unwrap_err(),
+        AsmError::OutOfRange { line: 1, value: 0x400 }
+    );
+    assert_eq!(
+        asm::assemble("add r1, r2").unwrap_err(),
+        AsmError::OperandCount { line: 1, expected: 3, found: 2 }
+    );
+    assert_eq!(
+        asm::assemble("mov r1, [r2").unwrap_err(),
+        AsmError::BadOperand { line: 1, operand: "[r2".to_string() }
+    );
+}
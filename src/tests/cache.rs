@@ -0,0 +1,86 @@
+use crate::memory::cache::{Cache, CacheConfig};
+
+#[test]
+#[should_panic(expected = "not a power of two")]
+fn zero_line_size_is_rejected() {
+    Cache::new("L1D", CacheConfig { line_size: 0, ..CacheConfig::new() });
+}
+
+#[test]
+#[should_panic(expected = "not a power of two")]
+fn odd_line_size_is_rejected() {
+    Cache::new("L1D", CacheConfig { line_size: 24, ..CacheConfig::new() });
+}
+
+#[test]
+fn default_config_is_accepted() {
+    let cache = Cache::new("L1D", CacheConfig::new());
+    assert_eq!(cache.sets.len(), 32);
+}
+
+fn cpu_with_hierarchy() -> crate::core::CpuState {
+    use crate::memory::hierarchy::{DramConfig, MemoryHierarchy};
+    let mut cpu = crate::core::CpuState::new();
+    cpu.icache = Some(Cache::new("L1I", CacheConfig::new()));
+    cpu.dcache = Some(Cache::new("L1D", CacheConfig::new()));
+    cpu.hierarchy = Some(MemoryHierarchy::new(None, DramConfig::new(), 4));
+    cpu
+}
+
+#[test]
+fn access_to_a_line_being_filled_waits_and_misses() {
+    use crate::core::mpu::Access;
+    let mem = crate::memory::Memory::new();
+
+    for (first, second, cache) in [(Access::Write, Access::Read, "L1D"), (Access::Execute, Access::Execute, "L1I")] {
+        let mut cpu = cpu_with_hierarchy();
+        let miss = cpu.cache_stall(&mem, 0, 0x100, 2, first);
+        cpu.tick_memory();
+        let merged = cpu.cache_stall(&mem, 0, 0x102, 2, second);
+        assert_eq!(merged, miss - 1, "{}", cache);
+
+        let stats = if cache == "L1I" { cpu.icache.unwrap().stats } else { cpu.dcache.unwrap().stats };
+        assert_eq!(stats.misses(), 2, "{}", cache);
+        assert_eq!(cpu.hierarchy.unwrap().stats.mshr_merges, 1, "{}", cache);
+    }
+}
+
+#[test]
+fn access_after_the_fill_hits() {
+    use crate::core::mpu::Access;
+    let mem = crate::memory::Memory::new();
+    let mut cpu = cpu_with_hierarchy();
+
+    let miss = cpu.cache_stall(&mem, 0, 0x100, 2, Access::Read);
+    for _ in 0..=miss {
+        cpu.tick_memory();
+    }
+    assert_eq!(cpu.cache_stall(&mem, 0, 0x102, 2, Access::Read), 0);
+    assert_eq!(cpu.dcache.unwrap().stats.misses(), 1);
+}
+
+#[test]
+fn prefetched_lines_count_as_useful_and_late_when_used_early() {
+    use crate::core::mpu::Access;
+    use crate::memory::prefetch::{PrefetchPolicy, Prefetcher};
+    let mem = crate::memory::Memory::new();
+    let mut cpu = cpu_with_hierarchy();
+    cpu.prefetcher = Some(Prefetcher::new(PrefetchPolicy::NextLine, 1));
+
+    // The miss on 0x100 prefetches 0x110; once that fill is done using it costs nothing
+    let miss = cpu.cache_stall(&mem, 0, 0x100, 2, Access::Read);
+    for _ in 0..=miss {
+        cpu.tick_memory();
+    }
+    assert_eq!(cpu.cache_stall(&mem, 0, 0x110, 2, Access::Read), 0);
+
+    // That use prefetched 0x120, which is still being filled when it is read
+    assert!(cpu.cache_stall(&mem, 0, 0x120, 2, Access::Read) > 0);
+    // A second use of the same line is an ordinary hit
+    cpu.cache_stall(&mem, 0, 0x112, 2, Access::Read);
+
+    let stats = cpu.prefetcher.unwrap().stats;
+    assert_eq!((stats.issued, stats.useful, stats.late, stats.demand_misses), (3, 2, 1, 1));
+    assert_eq!((stats.accuracy(), stats.timeliness()), (2.0 / 3.0, 0.5));
+    assert_eq!(cpu.dcache.unwrap().stats.misses(), 1);
+}
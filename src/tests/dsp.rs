@@ -0,0 +1,85 @@
+// Q15 saturation and rounding at the ends of the range, through the CPU so the overflow
+// flag is covered too
+use crate::core::{control_unit, CpuState};
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+const MINUS_ONE: u16 = 0x8000; // -1.0
+const ALMOST_ONE: u16 = 0x7FFF; // 1 - 2^-15, the largest Q15 value
+const HALF: u16 = 0x4000;
+
+// Run `instruction` with r1 = a, r2 = b and {r4:r3} = acc, return r3, r4 and the overflow flag
+fn execute(instruction: Instruction, a: u16, b: u16, acc: u32) -> (u16, u16, bool) {
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    for (i, word) in [isa::encode(&instruction), isa::encode(&Instruction::Halt)].iter().enumerate() {
+        mem.load_program(&word.to_le_bytes(), 4 * i as u16);
+    }
+    cpu.regs.write(1, a);
+    cpu.regs.write(2, b);
+    cpu.regs.write(3, acc as u16);
+    cpu.regs.write(4, (acc >> 16) as u16);
+    while control_unit::step(&mut cpu, &mut mem) {}
+    (cpu.regs.read(3), cpu.regs.read(4), cpu.flags.overflow)
+}
+
+fn binary(instruction: Instruction, a: u16, b: u16) -> (u16, bool) {
+    let (result, _, overflow) = execute(instruction, a, b, 0);
+    (result, overflow)
+}
+
+#[test]
+fn q15_multiply_saturates_only_minus_one_squared() {
+    let qmul = Instruction::Q15Mult { dst: 3, src1: 1, src2: 2 };
+    assert_eq!(binary(qmul, MINUS_ONE, MINUS_ONE), (ALMOST_ONE, true)); // +1.0 does not fit
+    assert_eq!(binary(qmul, MINUS_ONE, ALMOST_ONE), (0x8001, false));
+    assert_eq!(binary(qmul, ALMOST_ONE, MINUS_ONE), (0x8001, false));
+    assert_eq!(binary(qmul, ALMOST_ONE, ALMOST_ONE), (0x7FFE, false));
+    assert_eq!(binary(qmul, MINUS_ONE, HALF), (0xC000, false));
+    assert_eq!(binary(qmul, MINUS_ONE, 0), (0, false));
+}
+
+#[test]
+fn q15_multiply_rounds_half_up() {
+    let qmul = Instruction::Q15Mult { dst: 3, src1: 1, src2: 2 };
+    // 1 * 0.5 is half an lsb: ties go up, towards +infinity
+    assert_eq!(binary(qmul, 1, HALF), (1, false));
+    assert_eq!(binary(qmul, 0xFFFF, HALF), (0, false));
+    assert_eq!(binary(qmul, 3, HALF), (2, false)); // 1.5 lsb
+    assert_eq!(binary(qmul, 0xFFFD, HALF), (0xFFFF, false)); // -1.5 lsb
+    // Just under and over half an lsb
+    assert_eq!(binary(qmul, 1, HALF - 1), (0, false));
+    assert_eq!(binary(qmul, 0xFFFF, HALF + 1), (0xFFFF, false));
+}
+
+#[test]
+fn saturating_add_and_subtract_clamp_at_the_ends() {
+    let qadd = Instruction::SatAdd { dst: 3, src1: 1, src2: 2 };
+    let qsub = Instruction::SatSub { dst: 3, src1: 1, src2: 2 };
+    assert_eq!(binary(qadd, ALMOST_ONE, 1), (ALMOST_ONE, true));
+    assert_eq!(binary(qadd, ALMOST_ONE, 0), (ALMOST_ONE, false));
+    assert_eq!(binary(qadd, MINUS_ONE, 0xFFFF), (MINUS_ONE, true));
+    assert_eq!(binary(qadd, MINUS_ONE, ALMOST_ONE), (0xFFFF, false));
+    assert_eq!(binary(qsub, MINUS_ONE, 1), (MINUS_ONE, true));
+    assert_eq!(binary(qsub, 0, MINUS_ONE), (ALMOST_ONE, true)); // -(-1.0)
+    assert_eq!(binary(qsub, 0xFFFF, MINUS_ONE), (ALMOST_ONE, false));
+
+    let abs = Instruction::Abs { dst: 3, src: 1 };
+    assert_eq!(binary(abs, MINUS_ONE, 0), (ALMOST_ONE, true));
+    assert_eq!(binary(abs, 0x8001, 0), (ALMOST_ONE, false));
+}
+
+#[test]
+fn multiply_accumulate_saturates_the_32_bit_accumulator() {
+    let mac = Instruction::MultAcc { acc: 3, src1: 1, src2: 2 };
+    let wide = |a: u16, b: u16, acc: i32| {
+        let (low, high, overflow) = execute(mac, a, b, acc as u32);
+        ((high as u32) << 16 | low as u32, overflow)
+    };
+    // -1.0 * -1.0 in Q30 is 2^30, which fits
+    assert_eq!(wide(MINUS_ONE, MINUS_ONE, 0), (1 << 30, false));
+    assert_eq!(wide(MINUS_ONE, MINUS_ONE, i32::MAX - (1 << 30)), (i32::MAX as u32, false));
+    assert_eq!(wide(MINUS_ONE, MINUS_ONE, i32::MAX - (1 << 30) + 1), (i32::MAX as u32, true));
+    assert_eq!(wide(MINUS_ONE, ALMOST_ONE, i32::MIN + 5), (i32::MIN as u32, true));
+    assert_eq!(wide(MINUS_ONE, ALMOST_ONE, 0), ((-(0x8000 * 0x7FFF)) as u32, false));
+}
@@ -0,0 +1,472 @@
+// Every execution mode has to leave the same architectural state as the plain
+// in-order interpreter
+use crate::core::{CpuState, control_unit};
+use crate::core::memory_dependence::LoadSpeculation;
+use crate::core::rename::RenameScheme;
+use crate::core::smt::FetchPolicy;
+use crate::core::value_prediction::ValuePredictorKind;
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+use crate::memory::cache::{Cache, CacheConfig};
+
+// The demo program from main.rs: immediates, a dependent add chain, independent logic ops
+const PROGRAM: [Instruction; 10] = [
+    Instruction::LoadImm { dst: 0, value: 10 },
+    Instruction::LoadImm { dst: 1, value: 20 },
+    Instruction::LoadImm { dst: 2, value: 5 },
+    Instruction::LoadImm { dst: 3, value: 3 },
+    Instruction::Add { dst: 4, src1: 0, src2: 1 },
+    Instruction::Sub { dst: 5, src1: 2, src2: 3 },
+    Instruction::Add { dst: 6, src1: 4, src2: 5 },
+    Instruction::And { dst: 7, src1: 0, src2: 1 },
+    Instruction::Or { dst: 8, src1: 2, src2: 3 },
+    Instruction::Halt,
+];
+
+fn run(program: &[u8], setup: impl Fn(&mut CpuState)) -> CpuState {
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    mem.load_program(program, 0);
+    setup(&mut cpu);
+    run_to_halt(&mut cpu, &mut mem);
+    cpu
+}
+
+fn run_to_halt(cpu: &mut CpuState, mem: &mut Memory) {
+    for _ in 0..10_000 {
+        if !control_unit::step(cpu, mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted, "program did not halt");
+}
+
+fn registers(regs: &crate::core::register_file::RegisterFile) -> Vec<u16> {
+    (0..16).map(|reg| regs.read(reg)).collect()
+}
+
+fn flags(cpu: &CpuState) -> [bool; 4] {
+    [cpu.flags.zero, cpu.flags.carry, cpu.flags.negative, cpu.flags.overflow]
+}
+
+fn program(instructions: &[Instruction]) -> Vec<u8> {
+    instructions.iter().flat_map(|instruction| isa::encode(instruction).to_le_bytes()).collect()
+}
+
+// A name and the setup that selects an engine or configures one
+type Engine = (&'static str, fn(&mut CpuState));
+
+// Every engine besides the in-order interpreter, with the setup that selects it
+fn engines() -> [Engine; 4] {
+    [
+        ("five-stage", control_unit::enable_pipelined),
+        ("scoreboard", control_unit::enable_scoreboard),
+        ("tomasulo", control_unit::enable_out_of_order),
+        ("physical registers", |cpu| {
+            control_unit::enable_out_of_order(cpu);
+            control_unit::set_rename_scheme(cpu, RenameScheme::PhysicalRegisters);
+        }),
+    ]
+}
+
+#[test]
+fn every_engine_matches_in_order() {
+    let expected = registers(&run(&program(&PROGRAM), |_| {}).regs);
+    assert_eq!(&expected[..9], &[10, 20, 5, 3, 30, 2, 32, 0, 7]);
+
+    for (name, setup) in engines() {
+        assert_eq!(registers(&run(&program(&PROGRAM), setup).regs), expected, "{}", name);
+    }
+}
+
+#[test]
+fn byte_loads_extend_and_post_increment() {
+    use Instruction::*;
+    let mut program = program(&[
+        LoadImm { dst: 1, value: 0x100 },
+        LoadByteSignedPostInc { dst: 2, base: 1 },
+        LoadBytePostInc { dst: 3, base: 1 },
+        LoadByteSigned { dst: 4, base: 1, offset: -2 },
+        Halt,
+    ]);
+    program.resize(0x100, 0);
+    program.extend_from_slice(&[0x80, 0x7F]);
+
+    let expected = registers(&run(&program, |_| {}).regs);
+    assert_eq!(&expected[1..5], &[0x102, 0xFF80, 0x7F, 0xFF80]);
+    for (name, setup) in engines() {
+        assert_eq!(registers(&run(&program, setup).regs), expected, "{}", name);
+    }
+}
+
+#[test]
+fn smt_threads_each_run_the_program() {
+    let expected = registers(&run(&program(&PROGRAM), |_| {}).regs);
+    for policy in [FetchPolicy::RoundRobin, FetchPolicy::ICount] {
+        let cpu = run(&program(&PROGRAM), |cpu| control_unit::enable_smt(cpu, &[0, 0], policy));
+        assert_eq!(registers(&cpu.regs), expected);
+        assert_eq!(registers(&cpu.smt.contexts[0].regs), expected);
+        assert_eq!(cpu.smt.instructions(&cpu), 20);
+    }
+}
+
+#[test]
+fn carry_reaches_later_flag_readers() {
+    use Instruction::*;
+    let program = program(&[
+        LoadImm { dst: 1, value: -1 },
+        LoadImm { dst: 2, value: 1 },
+        Add { dst: 3, src1: 1, src2: 2 }, // Carry out
+        AddCarry { dst: 4, src1: 2, src2: 2 },
+        Add { dst: 5, src1: 2, src2: 2 }, // No carry
+        AddCarry { dst: 6, src1: 5, src2: 5 },
+        Halt,
+    ]);
+
+    let cpu = run(&program, |_| {});
+    let expected = registers(&cpu.regs);
+    assert_eq!(&expected[3..7], &[0, 3, 2, 4]);
+    for (name, setup) in engines() {
+        let engine = run(&program, setup);
+        assert_eq!(registers(&engine.regs), expected, "{}", name);
+        assert_eq!(flags(&engine), flags(&cpu), "{}", name);
+    }
+}
+
+#[test]
+fn shifts_and_rotates_set_carry() {
+    use Instruction::*;
+    let program = program(&[
+        LoadImm { dst: 1, value: -0x1FF }, // 0xFE01
+        LoadImm { dst: 2, value: 1 },
+        LoadImm { dst: 8, value: 2 },
+        ShiftLeft { dst: 3, src: 1, amount: 1 }, // Carry out of bit 15
+        AddCarry { dst: 4, src1: 0, src2: 0 },
+        ShiftRightReg { dst: 5, src: 1, amount: 2 }, // Carry out of bit 0
+        AddCarry { dst: 6, src1: 0, src2: 0 },
+        RotateRightReg { dst: 7, src: 1, amount: 8 }, // Bit 15 clear
+        AddCarry { dst: 9, src1: 2, src2: 0 },
+        Halt,
+    ]);
+
+    let cpu = run(&program, |_| {});
+    let expected = registers(&cpu.regs);
+    assert_eq!(&expected[3..10], &[0xFC02, 1, 0x7F00, 1, 0x7F80, 2, 1]);
+    for (name, setup) in engines() {
+        let engine = run(&program, setup);
+        assert_eq!(registers(&engine.regs), expected, "{}", name);
+        assert_eq!(flags(&engine), flags(&cpu), "{}", name);
+    }
+}
+
+#[test]
+fn register_pair_results_reach_their_consumers() {
+    use Instruction::*;
+    let program = program(&[
+        LoadImm { dst: 1, value: 300 },
+        LoadImm { dst: 2, value: 500 },
+        MultWide { dst: 4, src1: 1, src2: 2 },
+        Add { dst: 6, src1: 5, src2: 1 },
+        MultAcc { acc: 4, src1: 1, src2: 2 },
+        Add { dst: 7, src1: 5, src2: 0 },
+        Halt,
+    ]);
+
+    let expected = registers(&run(&program, |_| {}).regs);
+    assert_eq!(&expected[4..8], &[0x93E0, 4, 302, 4]);
+    for (name, setup) in engines() {
+        assert_eq!(registers(&run(&program, setup).regs), expected, "{}", name);
+    }
+    let cpu = run(&program, |cpu| control_unit::enable_smt(cpu, &[0, 0], FetchPolicy::RoundRobin));
+    assert_eq!(registers(&cpu.regs), expected);
+    assert_eq!(registers(&cpu.smt.contexts[0].regs), expected);
+}
+
+#[test]
+fn fetch_page_fault_is_taken_once() {
+    // Page 0 is mapped read/execute for the supervisor, page 1 is not mapped. The code
+    // runs off the end of page 0 into the page fault handler, which halts.
+    let mut image = vec![0; 0x100];
+    image[0x8C..0x90].copy_from_slice(&program(&[Instruction::Halt])); // vector_base + 4 * 3
+    image[0xC0..].copy_from_slice(&program(&[Instruction::AddImm { dst: 16, src: 16, imm: 1 }; 16]));
+    let setup = |cpu: &mut CpuState, mem: &mut Memory| {
+        for (addr, value) in [(0xF000, 0xF021u16), (0xF020, 0x000B)] {
+            mem.write(addr, value as u8);
+            mem.write(addr + 1, (value >> 8) as u8);
+        }
+        cpu.mmu.enabled = true;
+        cpu.mmu.page_directory = 0xF000;
+        cpu.vector_base = 0x80;
+        cpu.pc = 0xC0;
+    };
+
+    let engines = std::iter::once(("in-order", (|_| {}) as fn(&mut CpuState))).chain(engines());
+    for (name, select) in engines {
+        let mut cpu = CpuState::new();
+        let mut mem = Memory::new();
+        mem.load_program(&image, 0);
+        setup(&mut cpu, &mut mem);
+        select(&mut cpu);
+        run_to_halt(&mut cpu, &mut mem);
+        assert_eq!(cpu.regs.read(16), 16, "{}", name);
+        assert_eq!(cpu.fault_addr, 0x100, "{}", name);
+        assert_eq!(cpu.mmu.stats.page_faults, 1, "{}", name);
+    }
+}
+
+#[test]
+fn far_jump_to_the_same_address_in_another_bank() {
+    // FarJump at 0x4000 in bank 0 lands on 0x4000 in bank 1. The fall-through
+    // instruction in bank 0 must not run.
+    use Instruction::*;
+    let bank0 = program(&[FarJump { bank: 1, target: 1 }, AddImm { dst: 2, src: 0, imm: 1 }, Halt]);
+    let bank1 = program(&[AddImm { dst: 3, src: 0, imm: 7 }, Halt]);
+
+    let engines = std::iter::once(("in-order", (|_| {}) as fn(&mut CpuState))).chain(engines());
+    for (name, select) in engines {
+        let mut cpu = CpuState::new();
+        let mut mem = Memory::new();
+        mem.load_program(&bank0, 0x4000);
+        mem.load_far(1 << 16 | 0x4000, &bank1);
+        cpu.regs.write(1, 0x4000);
+        cpu.pc = 0x4000;
+        select(&mut cpu);
+        run_to_halt(&mut cpu, &mut mem);
+        assert_eq!((cpu.regs.read(2), cpu.regs.read(3)), (0, 7), "{}", name);
+        assert_eq!(mem.code_bank, 1, "{}", name);
+    }
+}
+
+#[test]
+fn instruction_cache_miss_holds_the_instruction_back() {
+    let program = program(&[Instruction::LoadImm { dst: 1, value: 1 }, Instruction::Halt]);
+    let engines: [Engine; 2] = [
+        ("scoreboard", control_unit::enable_scoreboard),
+        ("tomasulo", control_unit::enable_out_of_order),
+    ];
+    for (name, select) in engines {
+        let mut cpu = CpuState::new();
+        let mut mem = Memory::new();
+        mem.load_program(&program, 0);
+        cpu.icache = Some(Cache::new("L1I", CacheConfig { miss_latency: 20, ..CacheConfig::new() }));
+        select(&mut cpu);
+        for _ in 0..10 {
+            control_unit::step(&mut cpu, &mut mem);
+        }
+        assert_eq!(cpu.regs.read(1), 0, "{} ran the instruction during the miss", name);
+        run_to_halt(&mut cpu, &mut mem);
+        assert_eq!(cpu.regs.read(1), 1, "{}", name);
+    }
+}
+
+#[test]
+fn stores_reach_loads_through_another_mapping_of_the_page() {
+    // Virtual pages 0x20 and 0x30 both map physical page 0x30, so each load has to see
+    // the store before it even though their virtual addresses differ. The multiply keeps
+    // the first store from committing before its load reads, and holds up the data of
+    // the second store until after its load has gone ahead.
+    use Instruction::*;
+    let program = program(&[
+        MultWide { dst: 8, src1: 5, src2: 6 },
+        StoreIndexed { src: 3, base: 1, offset: 0 },
+        LoadIndexed { dst: 4, base: 2, offset: 0 },
+        StoreIndexed { src: 8, base: 1, offset: 2 },
+        LoadIndexed { dst: 10, base: 2, offset: 2 },
+        Halt,
+    ]);
+    let engines: [Engine; 3] = [
+        ("in-order", |_| {}),
+        ("blind", |cpu| {
+            control_unit::enable_out_of_order(cpu);
+            control_unit::set_load_speculation(cpu, LoadSpeculation::Blind);
+        }),
+        ("store sets", |cpu| {
+            control_unit::enable_out_of_order(cpu);
+            control_unit::set_load_speculation(cpu, LoadSpeculation::StoreSets);
+        }),
+    ];
+    for (name, select) in engines {
+        let mut cpu = CpuState::new();
+        let mut mem = Memory::new();
+        mem.load_program(&program, 0);
+        let page_tables = [
+            (0xF000, 0xF021u16), (0xF004, 0xF041), (0xF006, 0xF061), // Directory
+            (0xF020, 0x000B), (0xF040, 0x3007), (0xF060, 0x3007),    // Tables
+        ];
+        for (addr, value) in page_tables {
+            mem.write(addr, value as u8);
+            mem.write(addr + 1, (value >> 8) as u8);
+        }
+        cpu.mmu.enabled = true;
+        cpu.mmu.page_directory = 0xF000;
+        cpu.unit_latencies.mac = 10;
+        for (reg, value) in [(1, 0x2010), (2, 0x3010), (3, 0x1234), (5, 0x100), (6, 3)] {
+            cpu.regs.write(reg, value);
+        }
+        select(&mut cpu);
+        run_to_halt(&mut cpu, &mut mem);
+        assert_eq!((cpu.regs.read(4), cpu.regs.read(10)), (0x1234, 0x300), "{}", name);
+    }
+}
+
+#[test]
+fn a_word_store_straddling_pages_reaches_a_load_of_its_second_byte() {
+    // The store writes 0x20FF and 0x2100, which map to physical 0x30FF and 0x5000, and
+    // the byte load reads 0x5000 through page 0x22. Only the store's last byte overlaps
+    // the load, and its data waits for the multiply.
+    use Instruction::*;
+    let program = program(&[
+        MultWide { dst: 8, src1: 5, src2: 6 },
+        StoreIndexed { src: 8, base: 1, offset: 0 },
+        LoadByte { dst: 4, base: 2, offset: 0 },
+        Halt,
+    ]);
+    let engines: [Engine; 3] = [
+        ("in-order", |_| {}),
+        ("blind", |cpu| {
+            control_unit::enable_out_of_order(cpu);
+            control_unit::set_load_speculation(cpu, LoadSpeculation::Blind);
+        }),
+        ("store sets", |cpu| {
+            control_unit::enable_out_of_order(cpu);
+            control_unit::set_load_speculation(cpu, LoadSpeculation::StoreSets);
+        }),
+    ];
+    for (name, select) in engines {
+        let mut cpu = CpuState::new();
+        let mut mem = Memory::new();
+        mem.load_program(&program, 0);
+        let page_tables = [
+            (0xF000, 0xF021u16), (0xF004, 0xF041),                // Directory
+            (0xF020, 0x000B), (0xF040, 0x3007), (0xF042, 0x5007), // Tables
+            (0xF044, 0x5007),
+        ];
+        for (addr, value) in page_tables {
+            mem.write(addr, value as u8);
+            mem.write(addr + 1, (value >> 8) as u8);
+        }
+        cpu.mmu.enabled = true;
+        cpu.mmu.page_directory = 0xF000;
+        cpu.unit_latencies.mac = 10;
+        for (reg, value) in [(1, 0x20FF), (2, 0x2200), (5, 0x100), (6, 3)] {
+            cpu.regs.write(reg, value);
+        }
+        select(&mut cpu);
+        run_to_halt(&mut cpu, &mut mem);
+        assert_eq!(cpu.regs.read(4), 3, "{}", name);
+        assert_eq!((mem.data[0x30FF], mem.data[0x5000]), (0x00, 0x03), "{}", name);
+    }
+}
+
+#[test]
+fn value_prediction_and_reuse_match_in_order() {
+    // The inner loop's branch resolves at issue once r1 is predicted, so instances of the
+    // AddImm sit next to each other in the ROB, several deep with a slow ALU. When the
+    // outer loop starts it over, the stride prediction for its first instance is wrong.
+    // Each start also repeats the operands of the one before, which reuse picks up.
+    use Instruction::*;
+    let program = program(&[
+        LoadImm { dst: 4, value: 40 },
+        LoadImm { dst: 8, value: 3 },
+        LoadImm { dst: 1, value: 0 },
+        AddImm { dst: 1, src: 1, imm: 1 },
+        BranchNotEqual { src1: 1, src2: 4, addr: 12 },
+        AddImm { dst: 7, src: 7, imm: 1 },
+        BranchNotEqual { src1: 7, src2: 8, addr: 8 },
+        Halt,
+    ]);
+    let expected = registers(&run(&program, |_| {}).regs);
+    let configurations: [Engine; 3] = [
+        ("last value", |cpu| control_unit::set_value_prediction(cpu, Some(ValuePredictorKind::LastValue))),
+        ("stride", |cpu| control_unit::set_value_prediction(cpu, Some(ValuePredictorKind::Stride))),
+        ("reuse", |cpu| control_unit::set_instruction_reuse(cpu, true)),
+    ];
+    for (name, configure) in configurations {
+        for scheme in [RenameScheme::RobTags, RenameScheme::PhysicalRegisters] {
+            let cpu = run(&program, |cpu| {
+                control_unit::enable_out_of_order(cpu);
+                control_unit::set_rename_scheme(cpu, scheme);
+                cpu.unit_latencies.alu = 4;
+                configure(cpu);
+            });
+            assert_eq!(registers(&cpu.regs), expected, "{} with {:?}", name, scheme);
+            // Squashed instructions, the branches among them too, do not count as retired
+            assert_eq!(cpu.pipeline.instructions, 2 + 3 * (1 + 40 * 2 + 2) + 1, "{} with {:?}", name, scheme);
+            if let Some(buffer) = &cpu.pipeline.reuse_buffer {
+                assert!(buffer.stats.reused > 0, "nothing reused with {:?}", scheme);
+            }
+        }
+    }
+}
+
+#[test]
+fn ten_bit_register_fields_reach_the_extended_registers() {
+    use Instruction::*;
+    let program = program(&[
+        LoadImm { dst: 1, value: 77 },
+        MoveWide { dst: 300, src: 1 },
+        Move { dst: 2, src: 300 },
+        Add { dst: 3, src1: 1, src2: 300 },
+        Push { src: 300 },
+        MoveWide { dst: 300, src: 0 },
+        Pop { dst: 1000 },
+        Move { dst: 4, src: 1000 },
+        Move { dst: 5, src: 300 },
+        Halt,
+    ]);
+    let setup = |cpu: &mut CpuState| cpu.regs.write(15, 0xF000);
+    let expected = run(&program, setup);
+    assert_eq!(&registers(&expected.regs)[1..6], &[77, 77, 154, 77, 0]);
+    assert_eq!((expected.regs.read_10bit(300), expected.regs.read_10bit(1000)), (0, 77));
+
+    for (name, select) in engines() {
+        let cpu = run(&program, |cpu| {
+            setup(cpu);
+            select(cpu);
+        });
+        assert_eq!(registers(&cpu.regs), registers(&expected.regs), "{}", name);
+        assert_eq!((cpu.regs.read_10bit(300), cpu.regs.read_10bit(1000)), (0, 77), "{}", name);
+    }
+}
+
+#[test]
+fn scoreboard_holds_back_war_and_waw_hazards() {
+    // The Add waits for the slow multiply with r1 still unread, so the LoadImm of r1 may
+    // not write its result until the Add has read it (WAR). The load writes r8 while the
+    // multiply still owes it, so it may not even issue (WAW).
+    use Instruction::*;
+    let build = |later_writes: [u8; 2]| {
+        let mut image = program(&[
+            MultWide { dst: 8, src1: 5, src2: 6 },
+            Add { dst: 10, src1: 8, src2: 1 },
+            LoadImm { dst: later_writes[0], value: 7 },
+            LoadIndexed { dst: later_writes[1], base: 0, offset: 0x40 },
+            Halt,
+        ]);
+        image.resize(0x40, 0);
+        image.extend_from_slice(&[9, 0]);
+        image
+    };
+    let setup = |cpu: &mut CpuState| {
+        for (reg, value) in [(1, 3), (5, 0x100), (6, 2)] {
+            cpu.regs.write(reg, value);
+        }
+        cpu.unit_latencies.mac = 6;
+    };
+    let scoreboard = |cpu: &mut CpuState| {
+        setup(cpu);
+        control_unit::enable_scoreboard(cpu);
+    };
+
+    let hazards = build([1, 8]);
+    let expected = run(&hazards, setup);
+    assert_eq!((expected.regs.read(1), expected.regs.read(8), expected.regs.read(10)), (7, 9, 0x203));
+    let cpu = run(&hazards, scoreboard);
+    assert_eq!(registers(&cpu.regs), registers(&expected.regs));
+    assert!(cpu.unit_stats.war_stalls > 0);
+    assert!(cpu.unit_stats.waw_stalls > 0);
+
+    // With the later writes going to registers nobody else uses there is nothing to wait for
+    let cpu = run(&build([2, 3]), scoreboard);
+    assert_eq!((cpu.unit_stats.war_stalls, cpu.unit_stats.waw_stalls), (0, 0));
+}
@@ -0,0 +1,177 @@
+// Exception and interrupt entry, privilege and the register range transfers around them
+use crate::asm::{self, AsmError};
+use crate::core::{control_unit, CpuState, PrivilegeMode, INTERRUPT_CAUSE};
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+fn load(source: &str) -> (CpuState, Memory) {
+    let image = asm::assemble(source).unwrap();
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    (cpu, mem)
+}
+
+fn run_to_halt(cpu: &mut CpuState, mem: &mut Memory) {
+    for _ in 0..1000 {
+        if !control_unit::step(cpu, mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted, "program did not halt");
+}
+
+#[test]
+fn register_ranges_exclude_the_stack_pointer() {
+    for (first, last) in [(12, 15), (15, 15), (0, 255)] {
+        assert_eq!(isa::decode(isa::encode(&Instruction::PushMultiple { first, last })), None);
+        assert_eq!(isa::decode(isa::encode(&Instruction::PopMultiple { first, last })), None);
+    }
+    assert!(isa::decode(isa::encode(&Instruction::PushMultiple { first: 16, last: 20 })).is_some());
+    assert_eq!(asm::assemble("pushm r12, r15").unwrap_err(), AsmError::BadOperand { line: 1, operand: "r15".to_string() });
+    assert_eq!(asm::assemble("popm r14, r16").unwrap_err(), AsmError::BadOperand { line: 1, operand: "r16".to_string() });
+
+    // A range up to r14 saves and restores around the stack pointer
+    let (mut cpu, mut mem) = load("
+        ldi r12, 1
+        ldi r13, 2
+        ldi r14, 3
+        pushm r12, r14
+        ldi r12, 0
+        ldi r13, 0
+        ldi r14, 0
+        popm r12, r14
+        halt
+");
+    cpu.regs.write(15, 0xF000);
+    run_to_halt(&mut cpu, &mut mem);
+    assert_eq!([12, 13, 14, 15].map(|reg| cpu.regs.read(reg)), [1, 2, 3, 0xF000]);
+    assert_eq!([mem.load_u16(0xEFFA), mem.load_u16(0xEFFC), mem.load_u16(0xEFFE)], [1, 2, 3]);
+}
+
+#[test]
+fn interrupt_entry_switches_to_the_exception_bank() {
+    // The handler sets r3 in its own bank and r16, which is not banked, then returns
+    let source = format!("
+_start: ldi r3, 1
+        ei
+loop:   add r1, r1, 1
+        bne r16, r0, done
+        jmp loop
+done:   halt
+        .org {}
+        ldi r3, 9
+        ldi r16, 1
+        rfe
+", 0xFFC0 + 4 * INTERRUPT_CAUSE);
+    let (mut cpu, mut mem) = load(&source);
+    cpu.exception_bank = 2;
+    cpu.interrupt_pending = true; // Held off until ei has run
+
+    control_unit::step(&mut cpu, &mut mem);
+    control_unit::step(&mut cpu, &mut mem);
+    assert!(cpu.interrupt_pending && cpu.interrupts_enabled);
+    control_unit::step(&mut cpu, &mut mem);
+    assert!(!cpu.interrupt_pending && !cpu.interrupts_enabled);
+    assert_eq!((cpu.regs.bank, cpu.cause, cpu.saved_pc), (2, INTERRUPT_CAUSE, 8));
+
+    run_to_halt(&mut cpu, &mut mem);
+    assert_eq!((cpu.regs.bank, cpu.regs.read(3), cpu.regs.read(1)), (0, 1, 1));
+    assert!(cpu.interrupts_enabled);
+    cpu.regs.select_bank(2);
+    assert_eq!(cpu.regs.read(3), 9);
+}
+
+// A kernel at 0 that drops to user mode at `user` through rfe, with the system call and
+// privileged instruction vectors at 0x800 jumping to handlers that report into r20-r23
+// (not banked, so visible whatever bank the handler runs in)
+const KERNEL: &str = "
+        .equ STATUS, 0
+        .equ SAVED_STATUS, 1
+        .equ SAVED_PC, 2
+        .equ CAUSE, 3
+_start: ldi r1, 0b0010      ; user mode, interrupts enabled, bank 0
+        mtc SAVED_STATUS, r1
+        la r1, user
+        mtc SAVED_PC, r1
+        rfe
+        .org 0x800
+        jmp syscall
+        jmp privileged
+        .org 0x900
+syscall:
+        mfc r20, CAUSE
+        mfc r21, STATUS
+        mfc r22, SAVED_STATUS
+        mfc r23, SAVED_PC
+        rfe
+privileged:
+        mfc r20, CAUSE
+        mfc r23, SAVED_PC
+        halt
+";
+
+fn run_kernel(user: &str) -> CpuState {
+    let (mut cpu, mut mem) = load(&format!("{}\n        .org 0x400\nuser:\n{}", KERNEL, user));
+    cpu.vector_base = 0x800;
+    run_to_halt(&mut cpu, &mut mem);
+    cpu
+}
+
+#[test]
+fn system_call_enters_supervisor_mode_and_returns_after_it() {
+    let cpu = run_kernel("
+        ldi r1, 7
+        sys 42
+        add r1, r1, 1
+        halt
+");
+    // In the handler: supervisor, interrupts off, exception bank 1; saved: user mode with
+    // interrupts on, and the pc after the system call
+    assert_eq!(cpu.regs.read(20), 42 << 8);
+    assert_eq!(cpu.regs.read(21), 0b0101);
+    assert_eq!(cpu.regs.read(22), 0b0010);
+    assert_eq!(cpu.regs.read(23), 0x408);
+
+    // ReturnFromException restored the pc, the mode, interrupts and the bank
+    assert_eq!(cpu.mode, PrivilegeMode::User);
+    assert!(cpu.interrupts_enabled);
+    assert_eq!((cpu.regs.bank, cpu.regs.read(1), cpu.pc), (0, 8, 0x40C));
+}
+
+#[test]
+fn privileged_instructions_fault_in_user_mode() {
+    let user = ["ei", "di", "rfe", "bank 2", "mtc 4, r1", "mfc r1, 0", "tlbflush", "tlbflushp r1", "setdb r1"];
+    for instruction in user {
+        let cpu = run_kernel(&format!("        li r1, 0x600\n        {}\n        halt", instruction));
+        assert_eq!(cpu.regs.read(20), 1, "{}", instruction); // PrivilegedInstruction
+        assert_eq!(cpu.regs.read(23), 0x404, "{}", instruction); // Re-executes the instruction
+        assert_eq!(cpu.mode, PrivilegeMode::Supervisor, "{}", instruction);
+        // Nothing of the instruction took effect
+        assert!(!cpu.interrupts_enabled, "{}", instruction);
+        assert_eq!((cpu.regs.bank, cpu.vector_base), (1, 0x800), "{}", instruction);
+    }
+}
+
+#[test]
+fn user_far_transfers_may_not_leave_the_code_bank() {
+    // Within bank 0 a far call and return work as in supervisor mode
+    let cpu = run_kernel("
+        li r1, 0x600
+        fcall 0, r1
+        ldi r6, 1
+        halt
+        .org 0x600
+        ldi r5, 1
+        fret
+");
+    assert_eq!((cpu.mode, cpu.regs.read(5), cpu.regs.read(6)), (PrivilegeMode::User, 1, 1));
+
+    // To another bank they are privileged, as is a return to a bank put on the stack
+    let user = ["fjmp 1, r1", "fcall 2, r1", "push r2\n        push r1\n        fret"];
+    for transfer in user {
+        let cpu = run_kernel(&format!("        li r1, 0x600\n        ldi r2, 1\n        {}\n        halt", transfer));
+        assert_eq!(cpu.regs.read(20), 1, "{}", transfer); // PrivilegedInstruction
+        assert_eq!(cpu.mode, PrivilegeMode::Supervisor, "{}", transfer);
+    }
+}
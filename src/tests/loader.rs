@@ -0,0 +1,248 @@
+// Round trips through the image and object file writers and parsers
+use crate::isa::{self, Instruction};
+use crate::linker::{self, LinkError, LinkerConfig};
+use crate::linker::object::{ObjectFile, RelocKind, SectionKind};
+use crate::loader::{exe, ihex, srec, Image, LoadError, Segment, Symbol};
+
+fn sample_image() -> Image {
+    let mut image = Image::new();
+    let code: Vec<u8> = (0..40u8).collect();
+    image.add_data(0x0100, &code).unwrap();
+    image.add_data(0x2000, &[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+    image.add_data(0x0001_4000, &[1, 2, 3, 4, 5]).unwrap(); // Bank 1, code window
+    image.entry = Some(0x0100);
+    image.symbols.push(Symbol { name: "_start".to_string(), addr: 0x0100 });
+    image.symbols.push(Symbol { name: "table".to_string(), addr: 0x2000 });
+    image
+}
+
+fn assert_same_contents(a: &Image, b: &Image) {
+    assert_eq!(a.entry, b.entry);
+    let segments = |image: &Image| image.segments.iter().map(|s| (s.addr, s.data.clone())).collect::<Vec<_>>();
+    assert_eq!(segments(a), segments(b));
+}
+
+#[test]
+fn intel_hex_round_trip() {
+    let image = sample_image();
+    assert_same_contents(&ihex::parse(&ihex::write(&image)).unwrap(), &image);
+}
+
+#[test]
+fn intel_hex_rejects_bad_checksum() {
+    let text = ihex::write(&sample_image()).replacen(":10010000", ":10010001", 1);
+    assert_eq!(ihex::parse(&text).unwrap_err(), LoadError::BadChecksum(1));
+}
+
+#[test]
+fn srecord_round_trip() {
+    let image = sample_image();
+    assert_same_contents(&srec::parse(&srec::write(&image)).unwrap(), &image);
+}
+
+#[test]
+fn executable_round_trip_keeps_symbols() {
+    let image = sample_image();
+    let parsed = exe::parse(&exe::write(&image).unwrap()).unwrap();
+    assert_same_contents(&parsed, &image);
+    assert_eq!(parsed.symbol("_start"), Some(0x0100));
+    assert_eq!(parsed.symbol("table"), Some(0x2000));
+}
+
+#[test]
+fn executable_round_trip_of_the_whole_address_space() {
+    let data: Vec<u8> = (0..0x10000u32).map(|i| (i * 7) as u8).collect();
+    let image = Image::from_binary(&data, 0, None).unwrap();
+    let parsed = exe::parse(&exe::write(&image).unwrap()).unwrap();
+    assert_same_contents(&parsed, &image);
+}
+
+#[test]
+fn executable_rejects_truncated_data() {
+    let bytes = exe::write(&sample_image()).unwrap();
+    assert_eq!(exe::parse(&bytes[..bytes.len() - 1]).unwrap_err(), LoadError::Truncated);
+
+    // A segment whose data offset points past the end of the file
+    let mut bytes = exe::write(&sample_image()).unwrap();
+    bytes[24..28].copy_from_slice(&u32::MAX.to_le_bytes());
+    assert_eq!(exe::parse(&bytes).unwrap_err(), LoadError::Truncated);
+}
+
+#[test]
+fn executable_rejects_addresses_past_the_end_of_the_address_space() {
+    // The first segment's load address, so its 40 bytes would wrap past u32::MAX
+    let mut bytes = exe::write(&sample_image()).unwrap();
+    bytes[16..20].copy_from_slice(&0xFFFF_FFF0u32.to_le_bytes());
+    assert_eq!(exe::parse(&bytes).unwrap_err(), LoadError::AddressOutOfRange(0xFFFF_FFF0));
+}
+
+#[test]
+fn executable_rejects_overlong_symbol_names() {
+    let mut image = sample_image();
+    image.symbols.push(Symbol { name: "x".repeat(256), addr: 0 });
+    assert_eq!(exe::write(&image).unwrap_err(), LoadError::NameTooLong(2));
+    image.symbols[2].name.pop();
+    assert_eq!(exe::parse(&exe::write(&image).unwrap()).unwrap().symbols[2].name.len(), 255);
+}
+
+#[test]
+fn add_data_rejects_ranges_that_wrap() {
+    let mut image = Image::new();
+    assert_eq!(image.add_data(u32::MAX, &[1, 2]).unwrap_err(), LoadError::AddressOutOfRange(u32::MAX));
+    assert_eq!(image.add_data(0xFFFF_FFF0, &[0; 32]).unwrap_err(), LoadError::AddressOutOfRange(0xFFFF_FFF0));
+    assert!(image.segments.is_empty());
+}
+
+#[test]
+fn add_data_never_merges_across_a_bank_window() {
+    // The end of bank 1's code window and the start of its data window are contiguous far
+    // addresses but different windows
+    let mut image = Image::new();
+    image.add_data(0x0001_7FF0, &[1; 16]).unwrap();
+    image.add_data(0x0001_8000, &[2; 4]).unwrap();
+    let segments: Vec<(u32, usize)> = image.segments.iter().map(|s| (s.addr, s.data.len())).collect();
+    assert_eq!(segments, [(0x0001_7FF0, 16), (0x0001_8000, 4)]);
+
+    // A single range across the boundary is still rejected
+    assert_eq!(image.add_data(0x0001_7FFE, &[0; 4]).unwrap_err(), LoadError::AddressOutOfRange(0x0001_8001));
+
+    // Bank 0 has no windows and merges as before
+    image.add_data(0x7FF0, &[0; 16]).unwrap();
+    image.add_data(0x8000, &[0; 4]).unwrap();
+    assert_eq!(image.segments.last().map(|s| (s.addr, s.data.len())), Some((0x7FF0, 20)));
+}
+
+#[test]
+fn srecord_counts_past_65535_records_with_s6() {
+    let mut image = Image::new();
+    for i in 0..0x10001u32 {
+        image.segments.push(Segment { addr: i & 0xFFFF, data: vec![i as u8] });
+    }
+    let text = srec::write(&image);
+    let count = text.lines().rev().nth(1).unwrap();
+    assert!(count.starts_with("S604010001"), "{}", count);
+    let parsed = srec::parse(&text).unwrap();
+    assert_eq!(parsed.segments.iter().map(|s| s.data.len()).sum::<usize>(), 0x10001);
+
+    let small = srec::write(&sample_image());
+    assert!(small.lines().rev().nth(1).unwrap().starts_with("S503"));
+}
+
+fn sample_object() -> ObjectFile {
+    let mut object = ObjectFile::new("main.o");
+    let text = object.add_section(".text", SectionKind::Text, 4, vec![0; 8]);
+    let data = object.add_section(".data", SectionKind::Data, 2, vec![0x34, 0x12, 0, 0]);
+    let start = object.add_symbol("_start", Some(text), 0, true);
+    object.add_symbol("value", Some(data), 0, true);
+    let external = object.add_symbol("helper", None, 0, false);
+    object.add_relocation(text, 0, RelocKind::Imm10, external, 0);
+    object.add_relocation(data, 2, RelocKind::Data16, start, 4);
+    object
+}
+
+#[test]
+fn object_file_round_trip() {
+    let object = sample_object();
+    let parsed = ObjectFile::parse("main.o", &object.to_bytes()).unwrap();
+    assert_eq!(parsed.to_bytes(), object.to_bytes());
+    assert_eq!(parsed.sections.len(), 2);
+    assert_eq!(parsed.symbols[2].section, None);
+    assert_eq!(parsed.relocations[1].kind, RelocKind::Data16);
+    assert_eq!(parsed.relocations[1].addend, 4);
+}
+
+#[test]
+fn link_resolves_across_objects() {
+    let mut helper = ObjectFile::new("helper.o");
+    let text = helper.add_section(".text", SectionKind::Text, 4, vec![0; 4]);
+    helper.add_symbol("helper", Some(text), 0, true);
+
+    let output = linker::link(&[sample_object(), helper], &LinkerConfig::new()).unwrap();
+    let image = &output.image;
+    assert_eq!(image.entry, Some(0));
+    assert_eq!(image.symbol("helper"), Some(8));
+    assert_eq!(image.symbol("value"), Some(12));
+
+    let bytes = &image.segments[0].data;
+    assert_eq!(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & 0x3FF, 8);
+    assert_eq!(&bytes[12..16], &[0x34, 0x12, 4, 0]); // _start + 4
+}
+
+// An object with one text section of `words` and a global symbol at its start
+fn text_object(name: &str, symbol: &str, words: &[u32]) -> ObjectFile {
+    let mut object = ObjectFile::new(name);
+    let data = words.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let text = object.add_section(".text", SectionKind::Text, 4, data);
+    object.add_symbol(symbol, Some(text), 0, true);
+    object
+}
+
+#[test]
+fn link_reports_undefined_and_duplicate_symbols() {
+    let config = LinkerConfig::new();
+    assert_eq!(
+        linker::link(&[sample_object()], &config).unwrap_err(),
+        LinkError::UndefinedSymbol("helper".to_string())
+    );
+    let objects = [sample_object(), text_object("a.o", "helper", &[0]), text_object("b.o", "helper", &[0])];
+    assert_eq!(linker::link(&objects, &config).unwrap_err(), LinkError::DuplicateSymbol("helper".to_string()));
+}
+
+#[test]
+fn link_checks_relocations_against_their_field() {
+    let load_imm = isa::encode(&Instruction::LoadImm { dst: 1, value: 0 });
+    let load = isa::encode(&Instruction::Load { dst: 1, addr: 0 });
+    let branch = isa::encode(&Instruction::BranchEqualRel { src1: 1, src2: 2, offset: 0 });
+    let link = |word: u32, kind: RelocKind, addend: i16| {
+        let mut object = text_object("main.o", "_start", &[word]);
+        let target = object.add_symbol("target", Some(0), 0, false);
+        object.add_relocation(0, 0, kind, target, addend);
+        linker::link(&[object], &LinkerConfig::new())
+            .map(|output| u32::from_le_bytes(output.image.segments[0].data[..4].try_into().unwrap()))
+    };
+    let overflow = |kind: RelocKind, value: i32| LinkError::RelocationOverflow { symbol: "target".to_string(), kind, value };
+
+    // The unsigned absolute field takes 0..=0x3FF, LoadImm's sign-extended one -512..=511
+    assert_eq!(isa::decode(link(load, RelocKind::Imm10, 0x3FF).unwrap()), Some(Instruction::Load { dst: 1, addr: 0x3FF }));
+    assert_eq!(link(load, RelocKind::Imm10, -1).unwrap_err(), overflow(RelocKind::Imm10, -1));
+    assert_eq!(isa::decode(link(load_imm, RelocKind::Imm10, -512).unwrap()), Some(Instruction::LoadImm { dst: 1, value: -512 }));
+    assert_eq!(isa::decode(link(load_imm, RelocKind::Imm10, 511).unwrap()), Some(Instruction::LoadImm { dst: 1, value: 511 }));
+    assert_eq!(link(load_imm, RelocKind::Imm10, 512).unwrap_err(), overflow(RelocKind::Imm10, 512));
+
+    // Relative branches: 8-bit word offsets from the branch itself
+    assert_eq!(
+        isa::decode(link(branch, RelocKind::BranchRel8, -128 * 4).unwrap()),
+        Some(Instruction::BranchEqualRel { src1: 1, src2: 2, offset: -128 })
+    );
+    assert_eq!(link(branch, RelocKind::BranchRel8, 128 * 4).unwrap_err(), overflow(RelocKind::BranchRel8, 128 * 4));
+    assert_eq!(link(branch, RelocKind::BranchRel8, 2).unwrap_err(), overflow(RelocKind::BranchRel8, 2));
+}
+
+#[test]
+fn link_rejects_overlapping_sections() {
+    let mut config = LinkerConfig::new();
+    config.data_base = Some(4); // Inside the 8 bytes of .text
+    assert_eq!(
+        linker::link(&[sample_object(), text_object("helper.o", "helper", &[0])], &config).unwrap_err(),
+        LinkError::SectionOverlap { object: "main.o".to_string(), section: ".data".to_string(), addr: 4 }
+    );
+    config.data_base = Some(12);
+    assert!(linker::link(&[sample_object(), text_object("helper.o", "helper", &[0])], &config).is_ok());
+}
+
+#[test]
+fn object_file_rejects_unknown_kinds() {
+    let mut bytes = sample_object().to_bytes();
+    bytes[12] = 7; // Kind of the first section
+    assert_eq!(ObjectFile::parse("main.o", &bytes).unwrap_err(), LoadError::Malformed(12));
+
+    let bytes = sample_object().to_bytes();
+    let mut truncated = bytes.clone();
+    truncated.pop();
+    assert_eq!(ObjectFile::parse("main.o", &truncated).unwrap_err(), LoadError::Truncated);
+    // Relocations are the last 9 bytes each: section, offset, then the kind
+    let mut bad_reloc = bytes;
+    let kind = bad_reloc.len() - 9 + 4;
+    bad_reloc[kind] = 0xFF;
+    assert_eq!(ObjectFile::parse("main.o", &bad_reloc).unwrap_err(), LoadError::Malformed(kind));
+}
@@ -0,0 +1,29 @@
+// Banked memory seen through the code and data windows
+use crate::memory::{Memory, CODE_WINDOW, DATA_WINDOW};
+
+#[test]
+fn every_far_address_names_its_own_byte() {
+    let mut mem = Memory::new();
+    let addresses = [
+        0x0000_4000, 0x0000_8000, // Bank 0 is flat
+        0x0001_4000, 0x0001_8000, // The windows of one bank
+        0x0002_4000, 0x0002_8000,
+        0x000F_7FFF, 0x000F_BFFF, // The last byte of each window of the last bank
+    ];
+    for (i, &far) in addresses.iter().enumerate() {
+        assert!(mem.write_far(far, i as u8 + 1), "{:#x}", far);
+    }
+    for (i, &far) in addresses.iter().enumerate() {
+        assert_eq!(mem.read_far(far), Some(i as u8 + 1), "{:#x}", far);
+    }
+
+    // Outside the windows a bank has nothing, and there are only 16 banks
+    assert_eq!(mem.read_far(0x0001_0000), None);
+    assert_eq!(mem.read_far(0x0001_C000), None);
+    assert!(!mem.write_far(0x0010_4000, 1));
+
+    // Selecting banks picks which of them the windows show
+    mem.select_code_bank(1);
+    mem.select_data_bank(2);
+    assert_eq!((mem.read(CODE_WINDOW), mem.read(DATA_WINDOW)), (3, 6));
+}
@@ -0,0 +1,63 @@
+// Page table walks and the faults translation reports
+use crate::core::mmu::{Mmu, TranslationFault, PTE_READ, PTE_USER, PTE_VALID};
+use crate::core::mpu::Access;
+use crate::core::PrivilegeMode;
+use crate::memory::Memory;
+
+fn store(mem: &mut Memory, addr: u16, value: u16) {
+    mem.data[addr as usize] = value as u8;
+    mem.data[addr as usize + 1] = (value >> 8) as u8;
+}
+
+// Directory at `directory` whose entry 0 points at a table 0x20 bytes above it, mapping
+// virtual page 0x01 to physical page 0x30 with `flags`
+fn map_page_1(mem: &mut Memory, directory: u16, flags: u16) -> Mmu {
+    let mut mmu = Mmu::new(4);
+    mmu.enabled = true;
+    mmu.page_directory = directory;
+    store(mem, directory, (directory + 0x20) | PTE_VALID);
+    store(mem, directory + 0x20 + 2, 0x3000 | flags);
+    mmu
+}
+
+#[test]
+fn translation_faults_say_whether_the_page_is_unmapped_or_denied() {
+    let mut mem = Memory::new();
+    let mut mmu = map_page_1(&mut mem, 0xF000, PTE_READ | PTE_VALID);
+
+    assert_eq!(mmu.translate(&mem, 0x0110, Access::Read, PrivilegeMode::Supervisor), Ok(true));
+    assert_eq!(mmu.translate(&mem, 0x0110, Access::Read, PrivilegeMode::Supervisor), Ok(false));
+    assert_eq!(mmu.physical(&mem, 0x0110), Ok(0x3010));
+    assert_eq!(mmu.translate(&mem, 0x0110, Access::Write, PrivilegeMode::Supervisor), Err(TranslationFault::Denied));
+    assert_eq!(mmu.translate(&mem, 0x0110, Access::Read, PrivilegeMode::User), Err(TranslationFault::Denied));
+
+    // An invalid table entry and an invalid directory entry
+    assert_eq!(mmu.translate(&mem, 0x0210, Access::Read, PrivilegeMode::Supervisor), Err(TranslationFault::NotMapped));
+    assert_eq!(mmu.translate(&mem, 0x1110, Access::Read, PrivilegeMode::Supervisor), Err(TranslationFault::NotMapped));
+    assert_eq!(mmu.stats.page_faults, 4);
+
+    // Unmapped addresses do not pass through untranslated
+    assert_eq!(mmu.physical(&mem, 0x0210), Err(TranslationFault::NotMapped));
+    assert_eq!(mmu.physical(&mem, 0x1110), Err(TranslationFault::NotMapped));
+    mem.data[0x0210] = 0x5A;
+    assert_eq!(mmu.read_u8(&mem, 0x0210), 0);
+    mmu.write_u8(&mut mem, 0x0210, 0xA5);
+    assert_eq!(mem.data[0x0210], 0x5A);
+}
+
+#[test]
+fn page_tables_in_a_bank_window_are_read_from_physical_memory() {
+    // The tables sit at 0x8000, inside the data window. Selecting another data bank must
+    // not change what the walker reads.
+    let mut mem = Memory::new();
+    let mut mmu = map_page_1(&mut mem, 0x8000, PTE_USER | PTE_READ | PTE_VALID);
+    mem.select_data_bank(1);
+    for i in 0..0x40 {
+        mem.write(0x8000 + i, 0xFF);
+    }
+
+    assert_eq!(mmu.translate(&mem, 0x0110, Access::Read, PrivilegeMode::User), Ok(true));
+    assert_eq!(mmu.physical(&mem, 0x0110), Ok(0x3010));
+    mmu.flush_tlb();
+    assert_eq!(mmu.physical(&mem, 0x0110), Ok(0x3010));
+}
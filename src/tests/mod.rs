@@ -0,0 +1,14 @@
+mod asm;
+mod cache;
+mod dsp;
+mod engines;
+mod exceptions;
+mod loader;
+mod memory;
+mod mmu;
+mod protection;
+mod registers;
+mod rename;
+mod smt;
+mod softfloat;
+mod value_prediction;
@@ -0,0 +1,95 @@
+// The MPU's region checks, on their own and as faults taken by the CPU
+use crate::asm;
+use crate::core::mpu::{Access, Mpu, Permissions, Region};
+use crate::core::{control_unit, CpuState, PrivilegeMode};
+use crate::memory::Memory;
+
+const NONE: Permissions = Permissions { read: false, write: false, execute: false };
+const READ: Permissions = Permissions { read: true, write: false, execute: false };
+const READ_WRITE: Permissions = Permissions { read: true, write: true, execute: false };
+const READ_EXECUTE: Permissions = Permissions { read: true, write: false, execute: true };
+
+fn region(base: u16, size: u32, user: Permissions, supervisor: Permissions) -> Region {
+    Region { enabled: true, base, size, user, supervisor }
+}
+
+#[test]
+fn accesses_without_permission_fault_at_the_first_byte() {
+    let mut mpu = Mpu::new();
+    mpu.regions[0] = region(0x1000, 0x100, READ, READ_WRITE);
+    assert_eq!(mpu.check(0x1000, 2, Access::Write, PrivilegeMode::User), Ok(())); // Disabled MPU
+    mpu.enabled = true;
+
+    assert_eq!(mpu.check(0x1000, 2, Access::Read, PrivilegeMode::User), Ok(()));
+    assert_eq!(mpu.check(0x1000, 2, Access::Write, PrivilegeMode::User), Err(0x1000));
+    assert_eq!(mpu.check(0x1000, 2, Access::Write, PrivilegeMode::Supervisor), Ok(()));
+    assert_eq!(mpu.check(0x1010, 4, Access::Execute, PrivilegeMode::Supervisor), Err(0x1010));
+
+    // Outside every region only the supervisor may go, so an access running off the end
+    // of the region faults at the first byte past it
+    assert_eq!(mpu.check(0x10FF, 2, Access::Read, PrivilegeMode::User), Err(0x1100));
+    assert_eq!(mpu.check(0x10FF, 2, Access::Read, PrivilegeMode::Supervisor), Ok(()));
+    assert_eq!(mpu.check(0xFFFF, 2, Access::Read, PrivilegeMode::User), Err(0xFFFF));
+
+    // A region covering all 64 KiB, and a disabled one, which covers nothing
+    mpu.regions[1] = region(0, 0x10000, READ, READ);
+    assert_eq!(mpu.check(0xFFFF, 2, Access::Read, PrivilegeMode::User), Ok(()));
+    mpu.regions[1].enabled = false;
+    assert_eq!(mpu.check(0xFFFF, 2, Access::Read, PrivilegeMode::User), Err(0xFFFF));
+}
+
+#[test]
+fn the_highest_numbered_region_decides_where_regions_overlap() {
+    let mut mpu = Mpu::new();
+    mpu.enabled = true;
+    mpu.regions[2] = region(0x1000, 0x1000, READ_WRITE, READ_WRITE);
+    mpu.regions[5] = region(0x1800, 0x10, NONE, READ); // A guard inside it
+
+    assert_eq!(mpu.check(0x17FE, 2, Access::Write, PrivilegeMode::User), Ok(()));
+    assert_eq!(mpu.check(0x17FE, 4, Access::Write, PrivilegeMode::User), Err(0x1800));
+    assert_eq!(mpu.check(0x1800, 2, Access::Write, PrivilegeMode::Supervisor), Err(0x1800));
+    assert_eq!(mpu.check(0x180E, 4, Access::Read, PrivilegeMode::User), Err(0x180E));
+    assert_eq!(mpu.check(0x1810, 2, Access::Write, PrivilegeMode::User), Ok(()));
+
+    // A lower-numbered region does not win, even where it is the narrower one
+    mpu.regions[1] = region(0x1800, 0x10, READ_WRITE, READ_WRITE);
+    assert_eq!(mpu.check(0x1800, 2, Access::Read, PrivilegeMode::User), Err(0x1800));
+    mpu.regions[5].enabled = false;
+    assert_eq!(mpu.check(0x1800, 2, Access::Read, PrivilegeMode::User), Ok(()));
+}
+
+#[test]
+fn user_store_to_a_read_only_region_raises_a_protection_fault() {
+    let image = asm::assemble("
+        .org 0x100
+user:   ld r1, [r2]
+        add r1, r1, 1
+        st r1, [r2]
+        halt
+        .org 0x808          ; vector_base + 4 * ProtectionFault
+        halt
+").unwrap();
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    image.load(&mut cpu, &mut mem);
+    mem.store_u16(0x2000, 41);
+    cpu.mpu.enabled = true;
+    cpu.mpu.regions[0] = region(0x100, 0x100, READ_EXECUTE, READ_EXECUTE);
+    cpu.mpu.regions[1] = region(0x2000, 0x100, READ, READ_WRITE);
+    cpu.vector_base = 0x800; // Outside every region: supervisor only
+    cpu.regs.write(2, 0x2000);
+    cpu.mode = PrivilegeMode::User;
+    cpu.pc = 0x100;
+
+    for _ in 0..100 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted && cpu.pc == 0x808);
+    assert_eq!((cpu.cause, cpu.fault_addr, cpu.saved_pc), (2, 0x2000, 0x108));
+    assert_eq!((cpu.mode, cpu.saved_mode), (PrivilegeMode::Supervisor, PrivilegeMode::User));
+    assert_eq!(mem.load_u16(0x2000), 41);
+    cpu.regs.select_bank(0); // The handler runs in the exception bank
+    assert_eq!(cpu.regs.read(1), 42);
+}
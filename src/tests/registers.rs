@@ -0,0 +1,56 @@
+// The 10-bit register space, the banked r0-r15 and the register dump
+use crate::core::register_file::{RegisterFile, NUM_REGISTERS};
+
+#[test]
+fn ten_bit_accesses_reach_the_extended_registers() {
+    let mut regs = RegisterFile::new();
+    regs.write_10bit(255, 1);
+    regs.write_10bit(256, 2);
+    regs.write_10bit(1023, 3);
+    assert_eq!((regs.read_10bit(255), regs.read_10bit(256), regs.read_10bit(1023)), (1, 2, 3));
+    assert_eq!(regs.read(255), 1); // The 8-bit path sees the low registers only
+
+    // Past the register space: writes are dropped and reads give zero
+    regs.write_10bit(NUM_REGISTERS as u16, 4);
+    assert_eq!(regs.read_10bit(NUM_REGISTERS as u16), 0);
+    assert_eq!(regs.read_physical(NUM_REGISTERS as u16), 0); // Bank 1's r0, untouched
+}
+
+#[test]
+fn ten_bit_accesses_to_r0_r15_follow_the_bank() {
+    let mut regs = RegisterFile::new();
+    regs.write_10bit(3, 0x10);
+    regs.write_10bit(16, 0x20);
+    regs.select_bank(2);
+    assert_eq!((regs.read_10bit(3), regs.read(3)), (0, 0));
+    regs.write_10bit(3, 0x23);
+    assert_eq!(regs.read(3), 0x23);
+    assert_eq!(regs.read_10bit(16), 0x20); // Not banked
+    regs.select_bank(0);
+    assert_eq!((regs.read_10bit(3), regs.read(3)), (0x10, 0x10));
+}
+
+#[test]
+fn dump_shows_every_bank_and_the_extended_registers() {
+    let mut regs = RegisterFile::new();
+    regs.write(1, 0x0011);
+    regs.select_bank(3);
+    regs.write(15, 0xBEEF);
+    regs.write(200, 0x00C8);
+    regs.write_10bit(1023, 0x03FF);
+
+    let dump = regs.dump();
+    let lines: Vec<&str> = dump.lines().collect();
+    assert_eq!(lines[0], "Register bank 3 (selected):");
+    assert_eq!(lines[2], "  r8-r15:      0000 0000 0000 0000 0000 0000 0000 BEEF");
+    assert_eq!(lines[3], "Register bank 0:");
+    assert_eq!(lines[4], "  r0-r7:       0000 0011 0000 0000 0000 0000 0000 0000");
+    assert_eq!(lines[12], "Registers r16-r1023 (rows of zeros left out):");
+    assert_eq!(
+        &lines[13..],
+        [
+            "  r200-r207:   00C8 0000 0000 0000 0000 0000 0000 0000",
+            "  r1016-r1023: 0000 0000 0000 0000 0000 0000 0000 03FF",
+        ]
+    );
+}
@@ -0,0 +1,69 @@
+// Renaming onto the physical register file and recovering it after a misprediction
+use crate::core::{control_unit, CpuState};
+use crate::core::rename::{PhysicalRegisterFile, RenameScheme};
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+#[test]
+fn restoring_a_checkpoint_undoes_the_renames_after_the_branch() {
+    let mut prf = PhysicalRegisterFile::new(4, 8, 2);
+    let first = prf.rename(1, 0).unwrap();
+    prf.checkpoint(1); // Branch in ROB entry 1
+    let map = prf.speculative_map.clone();
+    let free = prf.free_registers();
+
+    prf.rename(1, 2).unwrap();
+    prf.rename(2, 3).unwrap();
+    prf.checkpoint(4); // A younger branch
+    prf.rename(3, 5).unwrap();
+    assert_eq!(prf.free_registers(), free - 3);
+
+    assert!(prf.restore(1));
+    assert_eq!(prf.speculative_map, map);
+    assert_eq!(prf.free_registers(), free);
+    assert!(prf.checkpoints.is_empty(), "the younger branch's checkpoint outlived it");
+    assert_eq!(prf.stats.restores, 1);
+    // The rename from before the branch stands, and the freed registers come back in order
+    assert_eq!(prf.lookup(1), (None, Some(0)));
+    assert_eq!(prf.rename(2, 2).map(|(new, _)| new), Some(first.0 + 1));
+    assert!(!prf.restore(4));
+}
+
+#[test]
+fn mispredicted_branches_recover_from_their_checkpoints() {
+    // With a slow ALU the loop branch issues before the AddImm feeding it has finished,
+    // predicted not taken, and is then taken nine times with renames behind it
+    use Instruction::*;
+    let program: Vec<u8> = [
+        LoadImm { dst: 4, value: 10 },
+        AddImm { dst: 1, src: 1, imm: 1 },
+        AddImm { dst: 2, src: 2, imm: 3 },
+        BranchNotEqual { src1: 1, src2: 4, addr: 4 },
+        AddImm { dst: 3, src: 2, imm: 1 },
+        Halt,
+    ]
+    .iter()
+    .flat_map(|instruction| isa::encode(instruction).to_le_bytes())
+    .collect();
+
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    mem.load_program(&program, 0);
+    control_unit::enable_out_of_order(&mut cpu);
+    control_unit::set_rename_scheme(&mut cpu, RenameScheme::PhysicalRegisters);
+    cpu.unit_latencies.alu = 4;
+    for _ in 0..10_000 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted, "program did not halt");
+
+    assert_eq!((cpu.regs.read(1), cpu.regs.read(2), cpu.regs.read(3)), (10, 30, 31));
+    let stats = cpu.physical_registers.stats;
+    assert!(cpu.pipeline.mispredicted_branches > 0);
+    assert_eq!(stats.restores, cpu.pipeline.mispredicted_branches, "{:?}", stats);
+    assert_eq!(stats.unwound, 0);
+    assert!(cpu.physical_registers.checkpoints.is_empty());
+    assert_eq!(cpu.physical_registers.in_flight(), 0);
+}
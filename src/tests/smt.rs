@@ -0,0 +1,66 @@
+// Fetch policies and per-thread statistics of the SMT core
+use crate::core::{control_unit, CpuState};
+use crate::core::smt::FetchPolicy;
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+fn program(instructions: &[Instruction]) -> Vec<u8> {
+    instructions.iter().flat_map(|instruction| isa::encode(instruction).to_le_bytes()).collect()
+}
+
+// Thread 0 at 0 runs a chain of slow dependent multiplies, thread 1 at 0x100 independent adds.
+// Returns the CPU and the cycle thread 1 halted in.
+fn run(policy: FetchPolicy) -> (CpuState, u64) {
+    use Instruction::*;
+    let mut mem = Memory::new();
+    mem.load_program(&program(&[[Q15Mult { dst: 1, src1: 1, src2: 1 }; 12].as_slice(), &[Halt]].concat()), 0);
+    let adds: Vec<Instruction> = (2..14).map(|dst| AddImm { dst, src: 0, imm: dst as i16 }).collect();
+    mem.load_program(&program(&[adds.as_slice(), &[Halt]].concat()), 0x100);
+
+    let mut cpu = CpuState::new();
+    cpu.regs.write(1, 1);
+    cpu.unit_latencies.mac = 8;
+    control_unit::enable_smt(&mut cpu, &[0, 0x100], policy);
+    let mut second_done = None;
+    for _ in 0..10_000 {
+        let running = control_unit::step(&mut cpu, &mut mem);
+        if second_done.is_none() && cpu.smt.contexts[0].halted && cpu.smt.contexts[0].reorder_buffer.is_empty() {
+            second_done = Some(cpu.smt.cycles);
+        }
+        if !running {
+            break;
+        }
+    }
+    assert!(cpu.halted, "program did not halt");
+    (cpu, second_done.unwrap())
+}
+
+#[test]
+fn icount_fetches_for_the_thread_that_is_not_backed_up() {
+    // Round-robin keeps handing thread 0 fetch slots while its multiplies pile up in the
+    // stations; ICOUNT gives them to thread 1, which finishes sooner. Thread 0's chain
+    // bounds the total either way.
+    let (round_robin, round_robin_done) = run(FetchPolicy::RoundRobin);
+    let (icount, icount_done) = run(FetchPolicy::ICount);
+    assert!(icount_done < round_robin_done, "{} vs {}", icount_done, round_robin_done);
+    assert_eq!(icount.smt.cycles, round_robin.smt.cycles);
+
+    for cpu in [&round_robin, &icount] {
+        assert_eq!(cpu.regs.read(1), round_robin.regs.read(1));
+        assert_eq!(cpu.smt.contexts[0].regs.read(13), 13);
+    }
+}
+
+#[test]
+fn each_thread_reports_its_own_ipc() {
+    let (cpu, _) = run(FetchPolicy::ICount);
+    let smt = &cpu.smt;
+    for thread in 0..smt.threads() {
+        let pipeline = smt.pipeline(&cpu, thread);
+        assert_eq!(pipeline.cycles, smt.cycles, "thread {}", thread);
+        assert_eq!(pipeline.instructions, 13, "thread {}", thread);
+        assert_eq!(pipeline.ipc(), 13.0 / smt.cycles as f32, "thread {}", thread);
+    }
+    assert_eq!(smt.instructions(&cpu), 26);
+    assert_eq!(smt.ipc(&cpu), 26.0 / smt.cycles as f32);
+}
@@ -0,0 +1,255 @@
+// The assembly soft-float runtime against the host's f32 on random operands
+use crate::asm::{self, SOFTFLOAT};
+use crate::core::{control_unit, CpuState};
+use crate::loader::Image;
+use crate::memory::Memory;
+
+const STACK: u16 = 0xF000;
+const CASES: usize = 2000;
+
+// A routine and the host operation it must match
+type Operation = (&'static str, fn(f32, f32) -> f32);
+
+struct Runtime {
+    image: Image,
+    mem: Memory,
+    returned: u16, // A halt the routines return to
+}
+
+impl Runtime {
+    fn new() -> Self {
+        let source = format!(".org 0x1000\n{}\nreturned:\n    halt\n", SOFTFLOAT);
+        let image = asm::assemble(&source).unwrap();
+        let mut mem = Memory::new();
+        let mut cpu = CpuState::new();
+        image.load(&mut cpu, &mut mem);
+        let returned = image.symbol("returned").unwrap();
+        Self { image, mem, returned }
+    }
+
+    // Call `routine` with r0.. set to `args` as if with `call`, return {r1:r0}
+    fn call(&mut self, routine: &str, args: &[u16]) -> u32 {
+        let mut cpu = CpuState::new();
+        for (reg, &value) in args.iter().enumerate() {
+            cpu.regs.write(reg as u8, value);
+        }
+        // The frame FarCall leaves: return pc on top, code bank above it
+        let sp = STACK - 4;
+        self.mem.store_u16(sp, self.returned);
+        self.mem.store_u16(sp + 2, 0);
+        cpu.regs.write(15, sp);
+        cpu.pc = self.image.symbol(routine).unwrap();
+
+        for _ in 0..20_000 {
+            if !control_unit::step(&mut cpu, &mut self.mem) {
+                break;
+            }
+        }
+        assert!(cpu.halted && cpu.pc == self.returned, "{} did not return", routine);
+        assert_eq!(cpu.regs.read(15), STACK, "{} left the stack unbalanced", routine);
+        (cpu.regs.read(1) as u32) << 16 | cpu.regs.read(0) as u32
+    }
+
+    fn f32_op(&mut self, routine: &str, a: f32, b: f32) -> f32 {
+        let (a, b) = (a.to_bits(), b.to_bits());
+        f32::from_bits(self.call(routine, &[a as u16, (a >> 16) as u16, b as u16, (b >> 16) as u16]))
+    }
+}
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    // Mostly uniform bit patterns, with exponents clustered so that operands interact
+    // (cancellation, subnormal results, overflow) and some special values
+    fn f32(&mut self) -> f32 {
+        let bits = self.next() as u32;
+        match self.next() % 8 {
+            0 => [0.0, -0.0, 1.0, -1.0, f32::INFINITY, f32::NEG_INFINITY, f32::NAN, f32::MIN_POSITIVE][bits as usize % 8],
+            1 => f32::from_bits(bits & 0x807F_FFFF), // Subnormal
+            2 => f32::from_bits(bits & 0x80FF_FFFF | 0x3F00_0000), // Near 1
+            3 => f32::from_bits(bits & 0x87FF_FFFF | 0x7800_0000), // Near the top of the range
+            4 => f32::from_bits(bits & 0x83FF_FFFF), // Near the bottom of the range
+            _ => f32::from_bits(bits),
+        }
+    }
+
+    fn f16(&mut self) -> u16 {
+        let bits = self.next() as u16;
+        match self.next() % 8 {
+            0 => [0x0000, 0x8000, 0x3C00, 0xBC00, 0x7C00, 0xFC00, 0x7E00, 0x7BFF][bits as usize % 8],
+            1 => bits & 0x83FF,               // Subnormal
+            2 => bits & 0x87FF | 0x3800,      // Near 1
+            _ => bits,
+        }
+    }
+}
+
+fn same(actual: f32, expected: f32) -> bool {
+    if expected.is_nan() { actual.is_nan() } else { actual.to_bits() == expected.to_bits() }
+}
+
+// Reference binary16 conversions, done on the bit patterns
+fn f16_to_f32(h: u16) -> f32 {
+    let sign = if h & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((h >> 10) & 0x1F) as i32;
+    let fraction = (h & 0x3FF) as f32;
+    match exponent {
+        0 => sign * fraction * 2f32.powi(-24),
+        0x1F if fraction == 0.0 => sign * f32::INFINITY,
+        0x1F => f32::NAN,
+        _ => sign * (1.0 + fraction / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+fn f32_to_f16(x: f32) -> u16 {
+    let sign = if x.is_sign_negative() { 0x8000 } else { 0 };
+    if x.is_nan() {
+        return 0x7E00;
+    }
+    let magnitude = x.abs() as f64;
+    if magnitude >= 65520.0 {
+        return sign | 0x7C00; // Rounds past the largest finite value, 65504
+    }
+    // Count in units of the result's last place (exact, a power of two apart), round
+    // half to even and rebuild the value, which then fits binary16
+    let exponent = ((x.to_bits() >> 23) & 0xFF) as i32 - 127;
+    let ulp = 2f64.powi(exponent.max(-14) - 10);
+    let value = (magnitude / ulp).round_ties_even() * ulp;
+    if value < 2f64.powi(-14) {
+        return sign | (value * 2f64.powi(24)) as u16;
+    }
+    let bits = (value as f32).to_bits();
+    let exponent = (((bits >> 23) & 0xFF) as i32 - 127 + 15) as u16;
+    sign | exponent << 10 | ((bits >> 13) & 0x3FF) as u16
+}
+
+#[test]
+fn reference_binary16_conversions() {
+    assert_eq!(f32_to_f16(1.0), 0x3C00);
+    assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+    assert_eq!(f32_to_f16(65519.0), 0x7BFF);
+    assert_eq!(f32_to_f16(65520.0), 0x7C00);
+    assert_eq!(f32_to_f16(2f32.powi(-24)), 0x0001);
+    assert_eq!(f32_to_f16(2f32.powi(-25)), 0x0000); // Tie to even
+    assert_eq!(f32_to_f16(-1.5 * 2f32.powi(-24)), 0x8002);
+    assert_eq!(f32_to_f16(1.0 + 2f32.powi(-11)), 0x3C00);
+    assert_eq!(f32_to_f16(1.0 + 3.0 * 2f32.powi(-11)), 0x3C02);
+    for h in 0..=0xFFFFu16 {
+        let x = f16_to_f32(h);
+        if !x.is_nan() {
+            assert_eq!(f32_to_f16(x), h, "{:04x}", h);
+        }
+    }
+}
+
+#[test]
+fn binary32_arithmetic_matches_the_host() {
+    let mut runtime = Runtime::new();
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    let operations: [Operation; 4] =
+        [("f32_add", |a, b| a + b), ("f32_sub", |a, b| a - b), ("f32_mul", |a, b| a * b), ("f32_div", |a, b| a / b)];
+    for (routine, reference) in operations {
+        for _ in 0..CASES {
+            let (a, b) = (rng.f32(), rng.f32());
+            let actual = runtime.f32_op(routine, a, b);
+            assert!(
+                same(actual, reference(a, b)),
+                "{}({:08x}, {:08x}) = {:08x}, expected {:08x}",
+                routine, a.to_bits(), b.to_bits(), actual.to_bits(), reference(a, b).to_bits()
+            );
+        }
+    }
+}
+
+#[test]
+fn binary32_compare_matches_the_host() {
+    let mut runtime = Runtime::new();
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    for i in 0..CASES {
+        let a = rng.f32();
+        // Equal operands now and then, including +0 against -0
+        let b = if i % 5 == 0 { f32::from_bits(a.to_bits() ^ if a == 0.0 { 0x8000_0000 } else { 0 }) } else { rng.f32() };
+        let expected = match a.partial_cmp(&b) {
+            Some(order) => order as i8 as u16,
+            None => 2,
+        };
+        let (a_bits, b_bits) = (a.to_bits(), b.to_bits());
+        let actual = runtime.call("f32_cmp", &[a_bits as u16, (a_bits >> 16) as u16, b_bits as u16, (b_bits >> 16) as u16]);
+        assert_eq!(actual as u16, expected, "f32_cmp({:08x}, {:08x})", a_bits, b_bits);
+    }
+}
+
+#[test]
+fn binary32_integer_conversions_match_the_host() {
+    let mut runtime = Runtime::new();
+    let mut rng = XorShift(0xD1B5_4A32_D192_ED03);
+    for i in 0..CASES {
+        let a = rng.f32();
+        let actual = runtime.call("f32_to_i32", &[a.to_bits() as u16, (a.to_bits() >> 16) as u16]) as i32;
+        assert_eq!(actual, a as i32, "f32_to_i32({:08x})", a.to_bits());
+
+        // Small, large and extreme integers
+        let n = match i % 4 {
+            0 => rng.next() as i32 >> (rng.next() % 32),
+            1 => [0, 1, -1, i32::MIN, i32::MAX, 16_777_217, -16_777_217][rng.next() as usize % 7],
+            _ => rng.next() as i32,
+        };
+        let actual = f32::from_bits(runtime.call("i32_to_f32", &[n as u16, (n as u32 >> 16) as u16]));
+        assert_eq!(actual.to_bits(), (n as f32).to_bits(), "i32_to_f32({})", n);
+    }
+}
+
+#[test]
+fn binary16_arithmetic_matches_the_host() {
+    let mut runtime = Runtime::new();
+    let mut rng = XorShift(0x8538_ECB5_BD45_6EA3);
+    let operations: [Operation; 4] =
+        [("f16_add", |a, b| a + b), ("f16_sub", |a, b| a - b), ("f16_mul", |a, b| a * b), ("f16_div", |a, b| a / b)];
+    for (routine, reference) in operations {
+        for _ in 0..CASES {
+            let (a, b) = (rng.f16(), rng.f16());
+            let actual = runtime.call(routine, &[a, b]) as u16;
+            let expected = f32_to_f16(reference(f16_to_f32(a), f16_to_f32(b)));
+            assert!(
+                same(f16_to_f32(actual), f16_to_f32(expected)),
+                "{}({:04x}, {:04x}) = {:04x}, expected {:04x}",
+                routine, a, b, actual, expected
+            );
+        }
+    }
+}
+
+#[test]
+fn binary16_conversions_and_compare_match_the_host() {
+    let mut runtime = Runtime::new();
+    let mut rng = XorShift(0x1405_7B7E_F767_814F);
+    for h in 0..=0xFFFFu16 {
+        let x = f16_to_f32(h);
+        let widened = f32::from_bits(runtime.call("f16_to_f32", &[h]));
+        assert!(same(widened, x), "f16_to_f32({:04x})", h);
+        assert_eq!(runtime.call("f16_to_i16", &[h]) as i16, x as i16, "f16_to_i16({:04x})", h);
+    }
+    for _ in 0..CASES {
+        let a = rng.f32();
+        let actual = runtime.call("f32_to_f16", &[a.to_bits() as u16, (a.to_bits() >> 16) as u16]) as u16;
+        assert!(same(f16_to_f32(actual), f16_to_f32(f32_to_f16(a))), "f32_to_f16({:08x}) = {:04x}", a.to_bits(), actual);
+
+        let n = rng.next() as i16 >> (rng.next() % 16);
+        let actual = runtime.call("i16_to_f16", &[n as u16]) as u16;
+        assert_eq!(actual, f32_to_f16(n as f32), "i16_to_f16({})", n);
+
+        let (a, b) = (rng.f16(), rng.f16());
+        let expected = match f16_to_f32(a).partial_cmp(&f16_to_f32(b)) {
+            Some(order) => order as i8 as u16,
+            None => 2,
+        };
+        assert_eq!(runtime.call("f16_cmp", &[a, b]) as u16, expected, "f16_cmp({:04x}, {:04x})", a, b);
+    }
+}
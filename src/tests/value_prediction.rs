@@ -0,0 +1,59 @@
+// Instruction reuse: results kept with the operands that produced them
+use crate::core::{control_unit, CpuState};
+use crate::core::value_prediction::ReuseBuffer;
+use crate::isa::{self, Instruction};
+use crate::memory::Memory;
+
+#[test]
+fn reuse_needs_the_same_instruction_and_operands_at_the_pc() {
+    let add = Instruction::Add { dst: 3, src1: 1, src2: 2 };
+    let sub = Instruction::Sub { dst: 3, src1: 1, src2: 2 };
+    let mut buffer = ReuseBuffer::new(4);
+    assert_eq!(buffer.lookup(0x10, add, (2, 3)), None);
+    buffer.record(0x10, add, (2, 3), 5);
+
+    assert_eq!(buffer.lookup(0x10, add, (2, 3)), Some(5));
+    assert_eq!(buffer.lookup(0x10, add, (3, 2)), None);
+    assert_eq!(buffer.lookup(0x10, sub, (2, 3)), None); // Another bank's code at the pc
+    assert_eq!(buffer.lookup(0x14, add, (2, 3)), None);
+
+    // 0x20 maps to the same entry as 0x10 and takes it over
+    buffer.record(0x20, add, (2, 3), 5);
+    assert_eq!(buffer.lookup(0x10, add, (2, 3)), None);
+    assert_eq!(buffer.lookup(0x20, add, (2, 3)), Some(5));
+    assert_eq!((buffer.stats.lookups, buffer.stats.reused), (7, 2));
+}
+
+#[test]
+fn a_loop_invariant_add_is_reused_after_its_first_pass() {
+    // r3 = r5 + r6 has the same operands on every pass; the counter's AddImm never does
+    use Instruction::*;
+    let program: Vec<u8> = [
+        LoadImm { dst: 4, value: 10 },
+        Add { dst: 3, src1: 5, src2: 6 },
+        AddImm { dst: 1, src: 1, imm: 1 },
+        BranchNotEqual { src1: 1, src2: 4, addr: 4 },
+        Halt,
+    ]
+    .iter()
+    .flat_map(|instruction| isa::encode(instruction).to_le_bytes())
+    .collect();
+
+    let mut cpu = CpuState::new();
+    let mut mem = Memory::new();
+    mem.load_program(&program, 0);
+    cpu.regs.write(5, 100);
+    cpu.regs.write(6, 23);
+    control_unit::enable_out_of_order(&mut cpu);
+    control_unit::set_instruction_reuse(&mut cpu, true);
+    for _ in 0..10_000 {
+        if !control_unit::step(&mut cpu, &mut mem) {
+            break;
+        }
+    }
+    assert!(cpu.halted, "program did not halt");
+
+    assert_eq!((cpu.regs.read(1), cpu.regs.read(3)), (10, 123));
+    let stats = cpu.pipeline.reuse_buffer.unwrap().stats;
+    assert_eq!(stats.reused, 9, "{:?}", stats);
+}
@@ -1,8 +1,12 @@
 pub fn sign_extend_10(val: u16) -> i16 {
     let sign_bit = 1 << 9;
-    if (val & sign_bit) == 1 {
+    if (val & sign_bit) != 0 {
         (val | !0x3FF) as i16
     } else {
         val as i16
     }
-}
\ No newline at end of file
+}
+
+pub fn sign_extend_8(val: u8) -> i16 {
+    val as i8 as i16
+}